@@ -1,14 +1,21 @@
+use std::time::Duration;
 use eframe::emath::Align;
-use egui::{ColorImage, Direction, Layout, ScrollArea};
+use egui::{Color32, ColorImage, Direction, FontId, Layout, ScrollArea};
+use egui::text::{LayoutJob, TextFormat};
 use egui::TextEdit;
 use egui_extras::{RetainedImage, Size, StripBuilder};
 use logo_renderer::Context;
+use logo_renderer::logo_runtime::logo_interp::events::Event;
+use logo_renderer::logo_runtime::logo_interp::lexer::TokenKind;
+#[cfg(not(target_arch = "wasm32"))]
+use logo_renderer::logo_runtime::logo_interp::vfs::DirectoryFileSystem;
 
 pub struct LogoApp {
     context: Context,
     proc_text: String,
     cmd_log: String,
     cur_cmd: String,
+    profiling: bool,
     img: Option<RetainedImage>
 }
 
@@ -19,8 +26,14 @@ impl Default for LogoApp {
             proc_text: "".to_owned(),
             cmd_log: "".to_owned(),
             cur_cmd: "".to_owned(),
+            profiling: false,
             img: None
         };
+        // `save` and `load` work with files next to where the app is started
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            res.context.state.files = Box::new(DirectoryFileSystem::new("."));
+        }
         res.run_code();
         res
     }
@@ -34,8 +47,54 @@ impl LogoApp {
     pub fn run_code(&mut self) {
         self.cmd_log += "\n";
         self.cmd_log += self.cur_cmd.as_str();
+        self.context.set_profiling(self.profiling);
         let bytes = self.context.render(&self.proc_text, &self.cur_cmd);
+        self.context.set_profiling(false);
         self.cur_cmd.clear();
+        if let Some(report) = self.context.take_profile_report() {
+            self.cmd_log += "\n";
+            self.cmd_log += report.trim_end();
+        }
+        self.show_image(bytes);
+    }
+
+    /// Runs processes started by `launch` and `forever` a tick per frame.
+    fn run_processes(&mut self, ctx: &egui::Context) {
+        if !self.context.is_running() {
+            return;
+        }
+        let bytes = self.context.tick();
+        self.show_image(bytes);
+        ctx.request_repaint_after(Duration::from_secs_f64(1.0 / 60.0));
+    }
+
+    /// Sends keys typed while no text field has focus to the key handler of the program.
+    fn forward_keys(&mut self, ctx: &egui::Context) {
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+        let events = ctx.input(|i| i.events.clone());
+        for event in events {
+            let key = match event {
+                egui::Event::Text(text) => text,
+                egui::Event::Key {key, pressed: true, ..} => match key {
+                    egui::Key::ArrowLeft => "left".to_string(),
+                    egui::Key::ArrowRight => "right".to_string(),
+                    egui::Key::ArrowUp => "up".to_string(),
+                    egui::Key::ArrowDown => "down".to_string(),
+                    egui::Key::Enter => "enter".to_string(),
+                    egui::Key::Escape => "escape".to_string(),
+                    egui::Key::Backspace => "backspace".to_string(),
+                    _ => continue
+                },
+                _ => continue
+            };
+            let bytes = self.context.dispatch_event(Event::Key(key));
+            self.show_image(bytes);
+        }
+    }
+
+    fn show_image(&mut self, bytes: Result<Vec<u8>, String>) {
         match bytes {
             Ok(bytes) => {
                 self.img = Some(RetainedImage::from_color_image("name", ColorImage::from_rgba_unmultiplied([800, 450], bytes.as_slice())));
@@ -48,8 +107,34 @@ impl LogoApp {
     }
 }
 
+/// Colours the source by kinds of its tokens.
+fn highlight(context: &Context, text: &str, font: FontId) -> LayoutJob {
+    let mut job = LayoutJob::default();
+    let mut pos = 0;
+    for token in context.highlight(text) {
+        job.append(&text[pos..token.span.start], 0.0, TextFormat::simple(font.clone(), Color32::GRAY));
+        let color = match token.kind {
+            TokenKind::Primitive => Color32::from_rgb(0, 90, 190),
+            TokenKind::Procedure => Color32::from_rgb(130, 40, 170),
+            TokenKind::Keyword => Color32::from_rgb(180, 60, 0),
+            TokenKind::Variable => Color32::from_rgb(0, 130, 130),
+            TokenKind::Number => Color32::from_rgb(20, 130, 20),
+            TokenKind::QuotedWord | TokenKind::String => Color32::from_rgb(170, 110, 0),
+            TokenKind::Comment => Color32::GRAY,
+            TokenKind::Bracket | TokenKind::Operator | TokenKind::Word => Color32::DARK_GRAY
+        };
+        job.append(&text[token.span.clone()], 0.0, TextFormat::simple(font.clone(), color));
+        pos = token.span.end;
+    }
+    job.append(&text[pos..], 0.0, TextFormat::simple(font, Color32::GRAY));
+    job
+}
+
 impl eframe::App for LogoApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.run_processes(ctx);
+        self.forward_keys(ctx);
+        let mut click = None;
         egui::CentralPanel::default().show(ctx, |ui| {
             StripBuilder::new(ui)
                 .size(Size::relative(0.25))
@@ -57,10 +142,28 @@ impl eframe::App for LogoApp {
                 .horizontal(|mut strip| {
                     strip.cell(|ui| {
                         ScrollArea::vertical().show(ui, |ui| {
-                            ui.label("Procedures");
+                            ui.horizontal(|ui| {
+                                ui.label("Procedures");
+                                if ui.button("Format").clicked() {
+                                    match self.context.format(&self.proc_text) {
+                                        Ok(formatted) => self.proc_text = formatted,
+                                        Err(e) => {
+                                            self.cmd_log += "\n";
+                                            self.cmd_log += e.as_str();
+                                        }
+                                    }
+                                }
+                            });
+                            let context = &self.context;
+                            let mut layouter = |ui: &egui::Ui, text: &str, wrap_width: f32| {
+                                let mut job = highlight(context, text, egui::TextStyle::Monospace.resolve(ui.style()));
+                                job.wrap.max_width = wrap_width;
+                                ui.fonts(|fonts| fonts.layout_job(job))
+                            };
                             ui.add_sized(ui.available_size(),
                                 TextEdit::multiline(&mut self.proc_text)
                                     .font(egui::TextStyle::Monospace)
+                                    .layouter(&mut layouter)
                                     .lock_focus(true)
                                     .desired_width(f32::INFINITY)
                             );
@@ -76,7 +179,10 @@ impl eframe::App for LogoApp {
                                     if let Some(img) = &self.img {
                                         let mut size = img.size_vec2();
                                         size *= (ui.available_width() / size.x).min(ui.available_height() / size.y);
-                                        img.show_size(ui, size);
+                                        let response = img.show_size(ui, size).interact(egui::Sense::click());
+                                        if let Some(pos) = response.interact_pointer_pos().filter(|_| response.clicked()) {
+                                            click = Some((pos - response.rect.min) * (img.size_vec2().x / response.rect.width()));
+                                        }
                                     }
                                 });
                                 strip.cell(|ui| {
@@ -89,6 +195,10 @@ impl eframe::App for LogoApp {
                                 });
                                 strip.cell(|ui| {
                                     ui.horizontal(|ui| {
+                                        ui.checkbox(&mut self.profiling, "Profile");
+                                        if ui.add_enabled(self.context.is_running(), egui::Button::new("Stop")).clicked() {
+                                            self.context.stop_all();
+                                        }
                                         ui.label("Command:");
                                         let edit = TextEdit::singleline(&mut self.cur_cmd)
                                             .desired_width(f32::INFINITY);
@@ -104,6 +214,11 @@ impl eframe::App for LogoApp {
                     });
                 });
         });
+        if let Some(pos) = click {
+            let (x, y) = self.context.canvas_to_turtle(pos.x as f64, pos.y as f64);
+            let bytes = self.context.dispatch_event(Event::Click {x, y});
+            self.show_image(bytes);
+        }
     }
 }
 
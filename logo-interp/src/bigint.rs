@@ -0,0 +1,209 @@
+use std::cmp::Ordering;
+use std::f64::consts::LOG10_2;
+use num_bigint::{BigInt, Sign};
+use num_traits::{FromPrimitive, One, Signed, ToPrimitive, Zero};
+use crate::core::{LogoConvertible, LogoValue, Word};
+use crate::executor_state::{EState, Rest};
+
+/// Number input of the arithmetic primitives with the `bigint` feature. Words made of digits are integers
+/// of any size, other numbers are floats. Integer arithmetic stays exact, mixing in a float gives a float.
+#[derive(Debug, Clone)]
+pub enum Number {
+    Int(BigInt),
+    Float(f64)
+}
+
+impl Number {
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            Number::Int(val) => val.to_f64().unwrap_or(f64::NAN),
+            Number::Float(val) => *val
+        }
+    }
+
+    fn combine(self, other: Number, int_op: fn(BigInt, BigInt) -> BigInt, float_op: fn(f64, f64) -> f64) -> Number {
+        match (self, other) {
+            (Number::Int(a), Number::Int(b)) => Number::Int(int_op(a, b)),
+            (a, b) => Number::Float(float_op(a.to_f64(), b.to_f64()))
+        }
+    }
+
+    /// Integer with the value of a float, floats which aren't finite stay as they are.
+    fn from_float(val: f64) -> Number {
+        match BigInt::from_f64(val) {
+            Some(val) => Number::Int(val),
+            None => Number::Float(val)
+        }
+    }
+}
+
+/// Integers and floats are equal when they have the same value.
+impl PartialEq for Number {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Number::Int(a), Number::Int(b)) => a == b,
+            (Number::Float(a), Number::Float(b)) => a == b,
+            (Number::Int(a), Number::Float(b)) | (Number::Float(b), Number::Int(a)) => {
+                b.fract() == 0.0 && BigInt::from_f64(*b).as_ref() == Some(a)
+            }
+        }
+    }
+}
+
+/// Integers are compared exactly, also with floats, which may be too far apart to tell as floats.
+impl PartialOrd for Number {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (Number::Int(a), Number::Int(b)) => Some(a.cmp(b)),
+            (Number::Float(a), Number::Float(b)) => a.partial_cmp(b),
+            (Number::Int(a), Number::Float(b)) => compare_int_float(a, *b),
+            (Number::Float(a), Number::Int(b)) => compare_int_float(b, *a).map(Ordering::reverse)
+        }
+    }
+}
+
+fn compare_int_float(a: &BigInt, b: f64) -> Option<Ordering> {
+    if b.is_nan() {
+        return None;
+    }
+    if b.is_infinite() {
+        return Some(if b > 0.0 { Ordering::Less } else { Ordering::Greater });
+    }
+    let floor = BigInt::from_f64(b.floor())?;
+    match a.cmp(&floor) {
+        Ordering::Equal if b.fract() != 0.0 => Some(Ordering::Less),
+        ordering => Some(ordering)
+    }
+}
+
+impl LogoConvertible for Number {
+    fn to_logo(&self) -> LogoValue {
+        match self {
+            Number::Int(val) => LogoValue::Word(Word(val.to_string())),
+            Number::Float(val) => val.to_logo()
+        }
+    }
+
+    fn from_logo(value: LogoValue) -> Result<Self, String> {
+        if let LogoValue::Word(word) = &value {
+            let digits = word.0.strip_prefix('-').unwrap_or(&word.0);
+            if !digits.is_empty() && digits.bytes().all(|x| x.is_ascii_digit()) {
+                if let Ok(val) = word.0.parse::<BigInt>() {
+                    return Ok(Number::Int(val));
+                }
+            }
+        }
+        Ok(Number::Float(f64::from_logo(value)?))
+    }
+}
+
+pub(crate) fn difference<S>(_: &mut EState<S>, a: Number, b: Number) -> Result<Number, String> {
+    Ok(a.combine(b, |a, b| a - b, |a, b| a - b))
+}
+
+pub(crate) fn int<S>(_: &mut EState<S>, val: Number) -> Result<Number, String> {
+    match val {
+        Number::Int(val) => Ok(Number::Int(val)),
+        Number::Float(val) => Ok(Number::from_float(val.trunc()))
+    }
+}
+
+pub(crate) fn minus<S>(_: &mut EState<S>, val: Number) -> Result<Number, String> {
+    match val {
+        Number::Int(val) => Ok(Number::Int(-val)),
+        Number::Float(val) => Ok(Number::Float(-val))
+    }
+}
+
+/// Most digits of an exact power, whatever the limits are, larger ones take too long to compute.
+const MAX_POWER_DIGITS: usize = 1_000_000;
+
+/// Integer powers with a non-negative exponent are exact, the rest are floats.
+pub(crate) fn power<S>(state: &mut EState<S>, a: Number, b: Number) -> Result<Number, String> {
+    if let (Number::Int(base), Number::Int(exp)) = (&a, &b) {
+        if exp.sign() != Sign::Minus {
+            // Powers of 0, 1 and -1 stay small however large the exponent is
+            if base.is_zero() || base.magnitude().is_one() {
+                let result = if exp.is_zero() { BigInt::one() } else if exp.bit(0) { base.clone() } else { base.abs() };
+                return Ok(Number::Int(result));
+            }
+            // The result has at least this many digits, so huge powers fail before they're computed
+            let min_digits = (base.bits().saturating_sub(1) as f64 * exp.to_f64().unwrap_or(f64::INFINITY) * LOG10_2)
+                .min(usize::MAX as f64) as usize;
+            let max = state.limits.max_string_length.map_or(MAX_POWER_DIGITS, |max| max.min(MAX_POWER_DIGITS));
+            if min_digits > max {
+                return Err(format!("Word length limit of {} exceeded", max));
+            }
+            return Ok(Number::Int(base.pow(exp.to_u32().unwrap())));
+        }
+    }
+    Ok(Number::Float(a.to_f64().powf(b.to_f64())))
+}
+
+pub(crate) fn product<S>(_: &mut EState<S>, a: Number, b: Number, rest: Rest<Number>) -> Result<Number, String> {
+    Ok(rest.0.into_iter().fold(a.combine(b, |a, b| a * b, |a, b| a * b), |acc, x| {
+        acc.combine(x, |a, b| a * b, |a, b| a * b)
+    }))
+}
+
+pub(crate) fn remainder<S>(_: &mut EState<S>, a: Number, b: Number) -> Result<Number, String> {
+    match (a, b) {
+        (Number::Int(_), Number::Int(b)) if b.is_zero() => Err("Can't divide by zero".to_string()),
+        (a, b) => Ok(a.combine(b, |a, b| a % b, |a, b| a % b))
+    }
+}
+
+pub(crate) fn round<S>(_: &mut EState<S>, val: Number) -> Result<Number, String> {
+    match val {
+        Number::Int(val) => Ok(Number::Int(val)),
+        Number::Float(val) => Ok(Number::from_float(val.round()))
+    }
+}
+
+pub(crate) fn sum<S>(_: &mut EState<S>, x: Number, y: Number, rest: Rest<Number>) -> Result<Number, String> {
+    Ok(rest.0.into_iter().fold(x.combine(y, |a, b| a + b, |a, b| a + b), |acc, x| {
+        acc.combine(x, |a, b| a + b, |a, b| a + b)
+    }))
+}
+
+pub(crate) fn greater<S>(_: &mut EState<S>, a: Number, b: Number) -> Result<bool, String> {
+    Ok(a > b)
+}
+
+pub(crate) fn less<S>(_: &mut EState<S>, a: Number, b: Number) -> Result<bool, String> {
+    Ok(a < b)
+}
+
+/// Numbers are compared by value, so `3000000000` equals `3e9`.
+pub(crate) fn equal<S>(_: &mut EState<S>, a: LogoValue, b: LogoValue) -> Result<bool, String> {
+    if let (Ok(x), Ok(y)) = (Number::from_logo(a.clone()), Number::from_logo(b.clone())) {
+        return Ok(x == y);
+    }
+    Ok(a == b)
+}
+
+#[test]
+fn test_bigint() {
+    use crate::executor::execute_str;
+    use crate::executor_state::Function;
+    use crate::stdlib::add_stdlib;
+
+    let mut state = EState::new(Vec::<String>::new());
+    add_stdlib(&mut state);
+    state.functions.insert("add", Function::from_proc1(|s: &mut EState<Vec<String>>, x: LogoValue| -> Result<(), String> {
+        s.state.push(x.to_string());
+        Ok(())
+    }));
+    let proc_source = "to fact :n if :n = 0 [output 1] output :n * fact :n - 1 end";
+    execute_str(&mut state, proc_source, "add fact 25 add int 3000000000.7 add power 2 100 add power 2 0.5 \
+        add remainder -7 2 add (sum 1 2 0.5) add equal? power 2 60 1152921504606846976.0").unwrap();
+    assert_eq!(state.state, vec!["15511210043330985984000000", "3000000000", "1267650600228229401496703205376",
+                                 "1.4142135623730951", "-1", "3.5", "true"]);
+    assert!(execute_str(&mut state, "", "add remainder 1 0").is_err());
+    state.state.clear();
+    execute_str(&mut state, "", "add greater? 9007199254740993 9007199254740992 add less? 9007199254740992 9007199254740993 \
+        add greater? 9007199254740993 9007199254740992.0 add less? 2.5 3 add power -1 4000000001 add power 0 0").unwrap();
+    assert_eq!(state.state, vec!["true", "true", "true", "true", "-1", "1"]);
+    assert!(execute_str(&mut state, "", "add power 10 4000000000").is_err());
+    assert!(execute_str(&mut state, "", "add power 3 100000000000000000000").is_err());
+}
@@ -0,0 +1,365 @@
+use std::collections::{HashMap, HashSet};
+use std::iter::Peekable;
+use std::ops::Range;
+use std::vec::IntoIter;
+use crate::executor::{is_flexible, math_transform};
+use crate::executor_state::EState;
+use crate::parser::{parse_with_comments_in_dialect, SpannedValue};
+
+/// Inputs of primitives that are instruction lists, so their contents are checked as code.
+pub(crate) const INSTRUCTION_LIST_INPUTS: &[(&str, &[usize])] = &[
+    ("repeat", &[1]),
+    ("if", &[1]),
+    ("ifelse", &[1, 2]),
+    ("profile", &[0]),
+    ("launch", &[0]),
+    ("forever", &[0]),
+    ("ontimer", &[1]),
+    ("when", &[1]),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning
+}
+
+/// Which of the two sources given to `check` a diagnostic refers to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Origin {
+    Procedures,
+    Commands
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub origin: Origin,
+    pub span: Range<usize>,
+    pub message: String
+}
+
+struct ProcedureDef {
+    name: String,
+    name_span: Range<usize>,
+    args: Vec<(String, Range<usize>)>,
+    code: Vec<SpannedValue>
+}
+
+enum Expr {
+    Missing,
+    Literal,
+    Call,
+    Undefined
+}
+
+struct Checker<'a, S> {
+    state: &'a EState<S>,
+    procedures: HashMap<String, usize>,
+    assigned: HashSet<String>,
+    origin: Origin,
+    diagnostics: Vec<Diagnostic>
+}
+
+/// Checks procedure definitions and commands without running them,
+/// the arguments have the same meaning as in `executor::execute_str`.
+pub fn check<S>(state: &EState<S>, proc_source: &str, source: &str) -> Vec<Diagnostic> {
+    let mut checker = Checker {
+        state,
+        procedures: HashMap::new(),
+        assigned: state.vars.keys().map(|x| x.to_string()).collect(),
+        origin: Origin::Procedures,
+        diagnostics: Vec::new()
+    };
+    for (name, logo_proc) in &state.logo_procedures {
+        checker.procedures.insert(name.to_string(), logo_proc.arg_names.len());
+    }
+
+    let proc_values = checker.parse(proc_source);
+    checker.origin = Origin::Commands;
+    let values = checker.parse(source);
+
+    checker.origin = Origin::Procedures;
+    let procedures = checker.split_procedures(proc_values);
+    for def in &procedures {
+        checker.procedures.insert(def.name.clone(), def.args.len());
+        for (arg_name, _) in &def.args {
+            checker.assigned.insert(arg_name.clone());
+        }
+        collect_assigned(&def.code, &mut checker.assigned);
+    }
+    collect_assigned(&values, &mut checker.assigned);
+
+    for def in procedures {
+        checker.check_procedure(def);
+    }
+    checker.origin = Origin::Commands;
+    checker.check_block(values, 0..source.len());
+
+    checker.diagnostics
+}
+
+fn collect_assigned(values: &[SpannedValue], assigned: &mut HashSet<String>) {
+    let mut prev_make = false;
+    for value in values {
+        match value {
+            SpannedValue::Word(word, _) => {
+                prev_make = word.0.to_lowercase() == "make";
+                continue;
+            },
+            SpannedValue::String(name, _) => {
+                if prev_make {
+                    assigned.insert(name.to_lowercase());
+                }
+            },
+            SpannedValue::List(list, _) => collect_assigned(list, assigned)
+        }
+        prev_make = false;
+    }
+}
+
+fn uses_var(values: &[SpannedValue], name: &str) -> bool {
+    values.iter().any(|value| match value {
+        SpannedValue::Word(word, _) => word.0.strip_prefix(':').is_some_and(|x| x.to_lowercase() == name),
+        SpannedValue::String(str, _) => str.to_lowercase() == name,
+        SpannedValue::List(list, _) => uses_var(list, name)
+    })
+}
+
+fn word_of(value: &SpannedValue) -> Option<String> {
+    match value {
+        SpannedValue::Word(word, _) => Some(word.0.to_lowercase()),
+        _ => None
+    }
+}
+
+impl<'a, S> Checker<'a, S> {
+    fn report(&mut self, severity: Severity, span: Range<usize>, message: String) {
+        self.diagnostics.push(Diagnostic {severity, origin: self.origin, span, message});
+    }
+
+    fn parse(&mut self, source: &str) -> Vec<SpannedValue> {
+        match parse_with_comments_in_dialect(source, self.state.dialect) {
+            Ok((values, _)) => values,
+            Err(err) => {
+                self.report(Severity::Error, err.span, err.message);
+                Vec::new()
+            }
+        }
+    }
+
+    fn split_procedures(&mut self, values: Vec<SpannedValue>) -> Vec<ProcedureDef> {
+        let mut result = Vec::new();
+        let mut it = values.into_iter().peekable();
+        while let Some(value) = it.next() {
+            let span = value.span();
+            match word_of(&value).as_deref() {
+                Some(word) if self.state.locale.is_to(word) => {},
+                Some(word) if self.state.locale.is_end(word) => {
+                    self.report(Severity::Error, span, "'end' without matching 'to'".to_string());
+                    continue;
+                },
+                _ => {
+                    self.report(Severity::Error, span, "Instruction outside of a procedure".to_string());
+                    continue;
+                }
+            }
+            let (name, name_span) = match it.next() {
+                Some(SpannedValue::Word(word, name_span)) => (word.0.to_lowercase(), name_span),
+                _ => {
+                    self.report(Severity::Error, span, "Missing procedure name after 'to'".to_string());
+                    continue;
+                }
+            };
+            let mut args = Vec::new();
+            while let Some(SpannedValue::Word(word, arg_span)) = it.peek() {
+                match word.0.strip_prefix(':') {
+                    Some(arg_name) => args.push((arg_name.to_lowercase(), arg_span.clone())),
+                    None => break
+                }
+                it.next();
+            }
+            let mut code = Vec::new();
+            let mut closed = false;
+            for value in it.by_ref() {
+                if word_of(&value).is_some_and(|x| self.state.locale.is_end(&x)) {
+                    closed = true;
+                    break;
+                }
+                code.push(value);
+            }
+            if !closed {
+                self.report(Severity::Error, name_span.clone(), format!("Missing 'end' for procedure {}", name));
+            }
+            if self.state.get_function(&name).is_some() {
+                self.report(Severity::Warning, name_span.clone(), format!("Procedure {} shadows a primitive", name));
+            }
+            result.push(ProcedureDef {name, name_span, args, code});
+        }
+        result
+    }
+
+    fn check_procedure(&mut self, def: ProcedureDef) {
+        for (arg_name, arg_span) in &def.args {
+            if !uses_var(&def.code, arg_name) {
+                self.report(Severity::Warning, arg_span.clone(),
+                            format!("Input {} of {} is never used", arg_name, def.name));
+            }
+        }
+        self.check_block(def.code, def.name_span);
+    }
+
+    fn check_block(&mut self, list: Vec<SpannedValue>, span: Range<usize>) {
+        let list = match math_transform(list, &|word| is_flexible(self.state, word)) {
+            Ok(list) => list,
+            Err(err) => {
+                self.report(Severity::Error, span, err);
+                return;
+            }
+        };
+        let mut it = list.into_iter().peekable();
+        let mut stopped_by: Option<String> = None;
+        let mut after_undefined = false;
+        while let Some(next) = it.peek() {
+            let span = next.span();
+            if let Some(word) = stopped_by {
+                self.report(Severity::Warning, span, format!("Unreachable code after {}", word));
+                return;
+            }
+            let word = word_of(next).map(|x| self.state.locale.primitive_name(&x).to_string());
+            match self.check_expr(&mut it, false) {
+                // Inputs of an undefined procedure can't be told apart from stray values
+                Expr::Literal if !after_undefined => {
+                    self.report(Severity::Error, span, "Don't know what to do with this value".to_string());
+                },
+                Expr::Undefined => after_undefined = true,
+                Expr::Call => after_undefined = false,
+                _ => {}
+            }
+            if word.as_deref() == Some("output") || word.as_deref() == Some("stop") {
+                stopped_by = word;
+            }
+        }
+    }
+
+    fn check_expr(&mut self, it: &mut Peekable<IntoIter<SpannedValue>>, instruction_list: bool) -> Expr {
+        let value = match it.next() {
+            Some(value) => value,
+            None => return Expr::Missing
+        };
+        let (word, span) = match value {
+            SpannedValue::Word(word, span) => (word.0, span),
+            SpannedValue::List(list, span) => {
+                if instruction_list {
+                    self.check_block(list, span);
+                }
+                return Expr::Literal;
+            },
+            SpannedValue::String(_, _) => return Expr::Literal
+        };
+        if word.parse::<f64>().is_ok() {
+            return Expr::Literal;
+        }
+        let word = word.to_lowercase();
+        if word == "(" {
+            return self.check_paren_call(it);
+        }
+        if let Some(var_name) = word.strip_prefix(':') {
+            if !self.assigned.contains(var_name) {
+                self.report(Severity::Warning, span, format!("Variable {} is never set", var_name));
+            }
+            return Expr::Literal;
+        }
+
+        let arity = match self.state.get_function(&word) {
+            Some(fun) => {
+                if !self.state.is_allowed(&word, fun) {
+                    self.report(Severity::Error, span.clone(), format!("{} is not available at this level", word));
+                }
+                fun.args as usize
+            },
+            None => match self.procedures.get(&word) {
+                Some(arity) => *arity,
+                None => {
+                    self.report(Severity::Error, span, format!("Undefined procedure {}", word));
+                    return Expr::Undefined;
+                }
+            }
+        };
+        let list_inputs = self.list_inputs(&word);
+        for idx in 0..arity {
+            if let Expr::Missing = self.check_expr(it, list_inputs.contains(&idx)) {
+                self.report(Severity::Error, span,
+                            format!("{} expects {} inputs, got {}", word, arity, idx));
+                break;
+            }
+        }
+        Expr::Call
+    }
+
+    fn list_inputs(&self, word: &str) -> &'static [usize] {
+        let primitive = self.state.locale.primitive_name(word);
+        INSTRUCTION_LIST_INPUTS.iter()
+            .find(|(name, _)| *name == primitive && !self.procedures.contains_key(word))
+            .map(|(_, inputs)| *inputs)
+            .unwrap_or(&[])
+    }
+
+    /// Checks a call of a flexible primitive in parentheses, the opening one is already taken.
+    fn check_paren_call(&mut self, it: &mut Peekable<IntoIter<SpannedValue>>) -> Expr {
+        let (word, span) = match it.next() {
+            Some(SpannedValue::Word(word, span)) => (word.0.to_lowercase(), span),
+            _ => return Expr::Missing
+        };
+        let list_inputs = self.list_inputs(&word);
+        let mut count = 0;
+        while it.peek().is_some_and(|x| word_of(x).as_deref() != Some(")")) {
+            self.check_expr(it, list_inputs.contains(&count));
+            count += 1;
+        }
+        it.next();
+        if let Some(fun) = self.state.get_function(&word) {
+            if count < fun.min_args() {
+                self.report(Severity::Error, span.clone(),
+                            format!("{} expects at least {} inputs, got {}", word, fun.min_args(), count));
+            }
+            if let Some(max_args) = fun.max_args().filter(|x| count > *x) {
+                self.report(Severity::Error, span,
+                            format!("{} expects at most {} inputs, got {}", word, max_args, count));
+            }
+        }
+        Expr::Call
+    }
+}
+
+#[test]
+fn test_checker() {
+    use crate::stdlib::add_stdlib;
+
+    let mut state = EState::new(());
+    add_stdlib(&mut state);
+
+    let procs = "to square :size :unused\nrepeat 4 [fwd :size]\nend\n\
+                 to half :x\noutput :x / 2\nshow :x\nend\n\
+                 to sum :a :b\noutput :a\nend\nend";
+    let diagnostics = check(&state, procs, "show :y square 10");
+    let messages: Vec<(Origin, &str, &str)> = diagnostics.iter().map(|d| {
+        let source = if d.origin == Origin::Procedures { procs } else { "show :y square 10" };
+        (d.origin, &source[d.span.clone()], d.message.as_str())
+    }).collect();
+    assert_eq!(messages, vec![
+        (Origin::Procedures, "sum", "Procedure sum shadows a primitive"),
+        (Origin::Procedures, "end", "'end' without matching 'to'"),
+        (Origin::Procedures, ":unused", "Input unused of square is never used"),
+        (Origin::Procedures, "fwd", "Undefined procedure fwd"),
+        (Origin::Procedures, "show", "Unreachable code after output"),
+        (Origin::Procedures, ":b", "Input b of sum is never used"),
+        (Origin::Commands, ":y", "Variable y is never set"),
+        (Origin::Commands, "square", "square expects 2 inputs, got 1"),
+    ]);
+
+    assert!(check(&state, "to tri :n repeat 3 [fd :n rt 120] end", "make \"n 5 tri :n").iter()
+        .all(|d| d.message.starts_with("Undefined procedure")));
+    let messages: Vec<String> = check(&state, "", "show (sum 1 2 3) show (sum 1)").into_iter().map(|d| d.message).collect();
+    assert_eq!(messages, vec!["sum expects at least 2 inputs, got 1"]);
+}
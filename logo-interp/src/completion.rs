@@ -0,0 +1,353 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use crate::docs::syntax;
+use crate::executor_state::{EState, Param, ParamKind};
+use crate::lexer::{Token, TokenKind, tokenize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionKind {
+    Primitive,
+    Procedure,
+    Variable
+}
+
+/// Name which can be typed at the cursor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Completion {
+    pub name: String,
+    pub kind: CompletionKind,
+    /// Number of inputs taken when the call isn't enclosed in parentheses, 0 for variables.
+    pub args: usize,
+    pub params: Vec<Param>,
+    /// Help of a primitive, title line of a procedure or value of a variable.
+    pub description: String
+}
+
+impl Completion {
+    /// Call like `setpos :pos`, see `docs::syntax`.
+    pub fn label(&self) -> String {
+        syntax(&self.name, &self.params)
+    }
+
+    fn max_args(&self) -> Option<usize> {
+        match self.params.iter().any(|param| param.kind == ParamKind::Rest) {
+            true => None,
+            false => Some(self.params.len())
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Completions {
+    /// Part of the source a chosen name replaces, the word at the cursor without its `:` or `"`.
+    pub span: Range<usize>,
+    pub items: Vec<Completion>
+}
+
+/// Call the cursor is in, with the input being typed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignatureHelp {
+    pub signature: Completion,
+    /// Index in the params, inputs past the last param are given to it.
+    pub active_param: usize
+}
+
+/// Procedure defined in the source, which may not have been run yet.
+struct SourceProcedure {
+    name: String,
+    inputs: Vec<String>,
+    /// From `to` to `end`, or to the end of the source if it's missing.
+    span: Range<usize>
+}
+
+fn source_procedures<S>(state: &EState<S>, source: &str, tokens: &[Token]) -> Vec<SourceProcedure> {
+    let mut result: Vec<SourceProcedure> = Vec::new();
+    let mut idx = 0;
+    while idx < tokens.len() {
+        let word = source[tokens[idx].span.clone()].to_lowercase();
+        if tokens[idx].kind == TokenKind::Word && state.locale.is_to(&word) {
+            let start = tokens[idx].span.start;
+            if let Some(name) = tokens.get(idx + 1) {
+                idx += 2;
+                let mut inputs = Vec::new();
+                while let Some(token) = tokens.get(idx).filter(|token| token.kind == TokenKind::Variable) {
+                    inputs.push(source[token.span.start + 1..token.span.end].to_lowercase());
+                    idx += 1;
+                }
+                result.push(SourceProcedure {
+                    name: source[name.span.clone()].to_lowercase(),
+                    inputs,
+                    span: start..source.len()
+                });
+                continue;
+            }
+        }
+        else if state.locale.is_end(&word) {
+            if let Some(procedure) = result.last_mut().filter(|procedure| procedure.span.end == source.len()) {
+                procedure.span.end = tokens[idx].span.end;
+            }
+        }
+        idx += 1;
+    }
+    result
+}
+
+fn procedure_completion(name: &str, inputs: &[String]) -> Completion {
+    let mut title = format!("to {}", name);
+    for input in inputs {
+        title += &format!(" :{}", input);
+    }
+    Completion {
+        name: name.to_string(),
+        kind: CompletionKind::Procedure,
+        args: inputs.len(),
+        params: inputs.iter().map(|input| Param {name: input.clone(), kind: ParamKind::Required}).collect(),
+        description: title
+    }
+}
+
+struct Call {
+    signature: Completion,
+    /// `None` for calls taking any number of inputs.
+    limit: Option<usize>,
+    given: usize,
+    /// Set after an infix operator, the next value is part of the last input.
+    merge: bool
+}
+
+/// Calls waiting for inputs at the top level or inside brackets or parentheses.
+#[derive(Default)]
+struct Level {
+    calls: Vec<Call>,
+    paren: bool,
+    first: bool
+}
+
+impl Level {
+    fn value(&mut self) {
+        if let Some(call) = self.calls.last_mut() {
+            match call.merge {
+                true => call.merge = false,
+                false => call.given += 1
+            }
+        }
+    }
+
+    /// Removes calls which got all their inputs, each of them is an input of the one before.
+    fn settle(&mut self) {
+        while let Some(call) = self.calls.last() {
+            if call.merge || call.limit.is_none_or(|limit| call.given < limit) {
+                break;
+            }
+            self.calls.pop();
+            self.value();
+        }
+    }
+}
+
+impl<S> EState<S> {
+    /// Primitives, localized names of them and procedures, in the order they are looked up.
+    fn callables(&self, procedures: &[SourceProcedure]) -> Vec<Completion> {
+        let mut result = Vec::new();
+        for (name, function) in &self.functions {
+            if !self.policy.allows(name, function.category) {
+                continue;
+            }
+            let completion = Completion {
+                name: name.to_string(),
+                kind: CompletionKind::Primitive,
+                args: function.args as usize,
+                params: function.params.clone(),
+                description: function.help.clone()
+            };
+            for alias in self.locale.aliases_of(name) {
+                result.push(Completion {name: alias.to_string(), ..completion.clone()});
+            }
+            result.push(completion);
+        }
+        result.extend(procedures.iter().map(|procedure| procedure_completion(&procedure.name, &procedure.inputs)));
+        result.extend(self.logo_procedures.iter().map(|(name, procedure)| procedure_completion(name, &procedure.arg_names)));
+        result.extend(self.loaded_procedures.iter().map(|(name, procedure)| procedure_completion(name, &procedure.arg_names)));
+        result.extend(self.library_procedures().iter().map(|(name, procedure)| procedure_completion(name, &procedure.arg_names)));
+        result
+    }
+
+    /// Global variables, inputs of the procedure the cursor is in and variables the source makes.
+    fn variables(&self, source: &str, tokens: &[Token], procedures: &[SourceProcedure], cursor: usize) -> Vec<Completion> {
+        let variable = |name: &str, description: String| Completion {
+            name: name.to_string(),
+            kind: CompletionKind::Variable,
+            args: 0,
+            params: Vec::new(),
+            description
+        };
+        let mut result = Vec::new();
+        for procedure in procedures.iter().filter(|procedure| procedure.span.contains(&cursor)) {
+            for input in &procedure.inputs {
+                result.push(variable(input, format!("Input of {}", procedure.name)));
+            }
+        }
+        for (name, value) in &self.vars {
+            result.push(variable(name, value.to_string()));
+        }
+        for pair in tokens.windows(2) {
+            let word = source[pair[0].span.clone()].to_lowercase();
+            if pair[1].kind == TokenKind::QuotedWord
+                && ["make", "local", "localmake"].contains(&self.locale.primitive_name(&word)) {
+                let name = source[pair[1].span.start + 1..pair[1].span.end].to_lowercase();
+                result.push(variable(&name, "Variable".to_string()));
+            }
+        }
+        result
+    }
+
+    /// Names which complete the word at the cursor, a byte offset in the source. Primitives and procedures
+    /// are completed for plain words, variables after `:` and `"`.
+    pub fn complete(&self, source: &str, cursor: usize) -> Completions {
+        let cursor = cursor.min(source.len());
+        let empty = Completions {span: cursor..cursor, items: Vec::new()};
+        if !source.is_char_boundary(cursor) {
+            return empty;
+        }
+        let tokens = tokenize(source, self.dialect);
+        let at_cursor = tokens.iter()
+            .find(|token| token.span.start < cursor && cursor <= token.span.end)
+            .filter(|token| !matches!(token.kind, TokenKind::Bracket | TokenKind::Operator));
+        let (span, variables) = match at_cursor {
+            None => (cursor..cursor, false),
+            Some(token) => match token.kind {
+                TokenKind::Word => (token.span.clone(), false),
+                TokenKind::Variable | TokenKind::QuotedWord => (token.span.start + 1..token.span.end, true),
+                _ => return empty
+            }
+        };
+        let prefix = source[span.start..cursor].to_lowercase();
+        let procedures = source_procedures(self, source, &tokens);
+        let mut items = match variables {
+            true => self.variables(source, &tokens, &procedures, cursor),
+            false => self.callables(&procedures)
+        };
+        items.retain(|item| item.name.starts_with(&prefix));
+        // Stable, so the first of the same names is the one that is looked up
+        items.sort_by(|a, b| a.name.cmp(&b.name));
+        items.dedup_by(|a, b| a.name == b.name);
+        Completions {span, items}
+    }
+
+    /// Signature of the call whose inputs are being typed at the cursor. Inside a list it's the call
+    /// the list is an input of, unless the list has a call of its own.
+    pub fn signature_help(&self, source: &str, cursor: usize) -> Option<SignatureHelp> {
+        let cursor = cursor.min(source.len());
+        let tokens = tokenize(source, self.dialect);
+        if tokens.iter().any(|token| token.kind == TokenKind::Comment && token.span.start < cursor && cursor <= token.span.end) {
+            return None;
+        }
+        let procedures = source_procedures(self, source, &tokens);
+        let mut callables: HashMap<String, Completion> = HashMap::new();
+        for callable in self.callables(&procedures) {
+            callables.entry(callable.name.clone()).or_insert(callable);
+        }
+
+        // The word being typed isn't an input yet
+        let before: Vec<&Token> = tokens.iter()
+            .filter(|token| token.span.end < cursor
+                || (token.span.end == cursor && matches!(token.kind, TokenKind::Bracket | TokenKind::Operator)))
+            .collect();
+        let mut levels = vec![Level::default()];
+        let mut idx = 0;
+        while idx < before.len() {
+            let token = before[idx];
+            idx += 1;
+            let text = &source[token.span.clone()];
+            let level = levels.last_mut().unwrap();
+            match token.kind {
+                TokenKind::Comment => continue,
+                TokenKind::Operator => {
+                    if let Some(call) = level.calls.last_mut() {
+                        call.merge = true;
+                    }
+                    continue;
+                },
+                _ => level.settle()
+            }
+            let first = std::mem::replace(&mut level.first, false);
+            match token.kind {
+                TokenKind::Bracket if text == "[" || text == "(" => {
+                    levels.push(Level {paren: text == "(", first: true, ..Level::default()});
+                },
+                TokenKind::Bracket => {
+                    if levels.len() > 1 {
+                        levels.pop();
+                        levels.last_mut().unwrap().value();
+                    }
+                },
+                TokenKind::Word => {
+                    let word = text.to_lowercase();
+                    if self.locale.is_to(&word) {
+                        // Skip the title line
+                        idx += 1;
+                        while before.get(idx).is_some_and(|token| token.kind == TokenKind::Variable) {
+                            idx += 1;
+                        }
+                        levels = vec![Level::default()];
+                        continue;
+                    }
+                    if self.locale.is_end(&word) {
+                        levels = vec![Level::default()];
+                        continue;
+                    }
+                    match callables.get(&word) {
+                        Some(signature) if signature.args > 0 || (level.paren && first) => {
+                            let limit = match level.paren && first {
+                                true => signature.max_args(),
+                                false => Some(signature.args)
+                            };
+                            level.calls.push(Call {signature: signature.clone(), limit, given: 0, merge: false});
+                        },
+                        _ => level.value()
+                    }
+                },
+                _ => level.value()
+            }
+        }
+        levels.last_mut().unwrap().settle();
+
+        let call = levels.iter().rev().find_map(|level| level.calls.last())?;
+        Some(SignatureHelp {
+            active_param: call.given.min(call.signature.params.len().saturating_sub(1)),
+            signature: call.signature.clone()
+        })
+    }
+}
+
+#[test]
+fn test_completion() {
+    use crate::stdlib::add_stdlib;
+
+    let mut state = EState::new(());
+    add_stdlib(&mut state);
+    state.vars.insert("count", crate::core::LogoValue::Word(crate::core::Word("3".to_string())));
+    let source = "to square :size :colour\nrepeat 4 [fd :s rt 90]\nend\nsqu";
+    let completions = state.complete(source, source.len());
+    assert_eq!(completions.span, source.len() - 3..source.len());
+    assert_eq!(completions.items.len(), 1);
+    assert_eq!(completions.items[0].label(), "square :size :colour");
+
+    let cursor = source.find(":s ").unwrap() + 2;
+    let names: Vec<String> = state.complete(source, cursor).items.into_iter().map(|item| item.name).collect();
+    assert_eq!(names, vec!["size"]);
+    let names: Vec<String> = state.complete("make \"c", 7).items.into_iter().map(|item| item.name).collect();
+    assert_eq!(names, vec!["c", "count"]);
+    assert!(state.complete("rep", 3).items.iter().any(|item| item.name == "repeat" && item.args == 2));
+
+    let help = |source: &str| state.signature_help(source, source.len())
+        .map(|help| (help.signature.name, help.active_param));
+    assert_eq!(help("item "), Some(("item".to_string(), 0)));
+    assert_eq!(help("item 2 "), Some(("item".to_string(), 1)));
+    assert_eq!(help("item 2 [a b] "), None);
+    assert_eq!(help("print first [10 "), Some(("first".to_string(), 0)));
+    assert_eq!(help("repeat 4 [print sum 1 + 2 "), Some(("sum".to_string(), 1)));
+    assert_eq!(help("repeat 4 [print 1 "), Some(("repeat".to_string(), 1)));
+    assert_eq!(help("(sum 1 2 3 "), Some(("sum".to_string(), 2)));
+    assert_eq!(help("print 1 ; item "), None);
+}
@@ -1,13 +1,19 @@
 use std::fmt::{Display, Formatter};
+pub use crate::list::LogoList;
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(transparent))]
 pub struct Word(pub String);
 
+/// With the `serde` feature values are represented as `{"type": "word", "value": "abc"}`,
+/// where the type is one of `word`, `string` and `list`.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize),
+           serde(tag = "type", content = "value", rename_all = "lowercase"))]
 pub enum LogoValue {
     Word(Word),
     String(String),
-    List(Vec<LogoValue>)
+    List(LogoList)
 }
 
 impl Display for LogoValue {
@@ -27,9 +33,16 @@ impl Display for LogoValue {
 }
 
 #[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LogoProcedure {
     pub arg_names: Vec<String>,
-    pub code: Vec<LogoValue>
+    pub code: LogoList,
+    /// Defined with `.macro`, the list it outputs is run in place of the call.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub is_macro: bool,
+    /// Text of the `;` comment lines right above the title line.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub doc: String
 }
 
 pub trait LogoConvertible {
@@ -39,17 +52,30 @@ pub trait LogoConvertible {
 
 impl LogoConvertible for LogoValue {
     fn to_logo(&self) -> LogoValue {
-        return self.clone();
+        self.clone()
     }
 
     fn from_logo(value: LogoValue) -> Result<Self, String> {
-        return Ok(value);
+        Ok(value)
+    }
+}
+
+impl LogoConvertible for LogoList {
+    fn to_logo(&self) -> LogoValue {
+        LogoValue::List(self.clone())
+    }
+
+    fn from_logo(value: LogoValue) -> Result<Self, String> {
+        match value {
+            LogoValue::List(list) => Ok(list),
+            _ => Err("Type mismatch".to_string())
+        }
     }
 }
 
 impl LogoConvertible for String {
     fn to_logo(&self) -> LogoValue {
-        return LogoValue::String(self.clone());
+        LogoValue::String(self.clone())
     }
 
     fn from_logo(value: LogoValue) -> Result<Self, String> {
@@ -62,7 +88,7 @@ impl LogoConvertible for String {
 
 impl LogoConvertible for Word {
     fn to_logo(&self) -> LogoValue {
-        return LogoValue::Word(self.clone());
+        LogoValue::Word(self.clone())
     }
 
     fn from_logo(value: LogoValue) -> Result<Self, String> {
@@ -75,7 +101,7 @@ impl LogoConvertible for Word {
 
 impl LogoConvertible for f64 {
     fn to_logo(&self) -> LogoValue {
-        return LogoValue::Word(Word(self.to_string()));
+        LogoValue::Word(Word(self.to_string()))
     }
 
     fn from_logo(value: LogoValue) -> Result<Self, String> {
@@ -93,7 +119,7 @@ impl LogoConvertible for f64 {
 
 impl LogoConvertible for i32 {
     fn to_logo(&self) -> LogoValue {
-        return LogoValue::Word(Word(self.to_string()));
+        LogoValue::Word(Word(self.to_string()))
     }
 
     fn from_logo(value: LogoValue) -> Result<Self, String> {
@@ -111,7 +137,7 @@ impl LogoConvertible for i32 {
 
 impl LogoConvertible for bool {
     fn to_logo(&self) -> LogoValue {
-        return LogoValue::Word(Word(self.to_string()));
+        LogoValue::Word(Word(self.to_string()))
     }
 
     fn from_logo(value: LogoValue) -> Result<Self, String> {
@@ -129,11 +155,7 @@ impl LogoConvertible for bool {
 
 impl<T: LogoConvertible> LogoConvertible for Vec<T> {
     fn to_logo(&self) -> LogoValue {
-        let mut res = Vec::with_capacity(self.len());
-        for value in self {
-            res.push(value.to_logo());
-        }
-        return LogoValue::List(res);
+        LogoValue::List(self.iter().map(|value| value.to_logo()).collect())
     }
 
     fn from_logo(value: LogoValue) -> Result<Self, String> {
@@ -149,3 +171,81 @@ impl<T: LogoConvertible> LogoConvertible for Vec<T> {
         }
     }
 }
+
+/// JSON objects become lists of `[key value]` pairs, numbers, `true`, `false` and `null` become words.
+/// A non-empty list where every item is such a pair with a string key converts back to an object.
+#[cfg(feature = "json")]
+impl LogoConvertible for serde_json::Value {
+    fn to_logo(&self) -> LogoValue {
+        use serde_json::Value;
+        match self {
+            Value::Null => LogoValue::Word(Word("null".to_string())),
+            Value::Bool(val) => val.to_logo(),
+            Value::Number(val) => match val.as_i64() {
+                Some(val) => LogoValue::Word(Word(val.to_string())),
+                None => val.as_f64().unwrap_or(f64::NAN).to_logo()
+            },
+            Value::String(val) => LogoValue::String(val.clone()),
+            Value::Array(list) => list.to_logo(),
+            Value::Object(map) => {
+                let pairs = map.iter()
+                    .map(|(key, val)| LogoValue::List(vec![LogoValue::String(key.clone()), val.to_logo()].into()))
+                    .collect();
+                LogoValue::List(pairs)
+            }
+        }
+    }
+
+    fn from_logo(value: LogoValue) -> Result<Self, String> {
+        use serde_json::{Map, Number, Value};
+        match value {
+            LogoValue::Word(Word(word)) => {
+                if let Ok(val) = word.parse::<i64>() {
+                    return Ok(Value::Number(val.into()));
+                }
+                if let Some(val) = word.parse::<f64>().ok().and_then(Number::from_f64) {
+                    return Ok(Value::Number(val));
+                }
+                Ok(match word.as_str() {
+                    "true" => Value::Bool(true),
+                    "false" => Value::Bool(false),
+                    "null" => Value::Null,
+                    _ => Value::String(word)
+                })
+            },
+            LogoValue::String(str) => Ok(Value::String(str)),
+            LogoValue::List(list) => {
+                let is_object = !list.is_empty() && list.iter().all(|item| matches!(item,
+                    LogoValue::List(pair) if pair.len() == 2 && matches!(pair.first(), Some(LogoValue::String(_)))));
+                if !is_object {
+                    return Ok(Value::Array(Vec::<Value>::from_logo(LogoValue::List(list))?));
+                }
+                let mut map = Map::new();
+                for item in list {
+                    if let LogoValue::List(pair) = item {
+                        let mut pair = pair.into_iter();
+                        if let (Some(LogoValue::String(key)), Some(val)) = (pair.next(), pair.next()) {
+                            map.insert(key, Value::from_logo(val)?);
+                        }
+                    }
+                }
+                Ok(Value::Object(map))
+            }
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde() {
+    use serde_json::json;
+
+    let json = json!({"name": "turtle", "pos": [1, 2.5], "visible": true, "tags": [], "owner": null});
+    let value = json.to_logo();
+    assert_eq!(serde_json::Value::from_logo(value.clone()), Ok(json));
+
+    let text = serde_json::to_string(&LogoValue::List(vec![LogoValue::Word(Word("5".to_string())),
+                                                            LogoValue::String("a b".to_string())].into())).unwrap();
+    assert_eq!(text, r#"{"type":"list","value":[{"type":"word","value":"5"},{"type":"string","value":"a b"}]}"#);
+    assert_eq!(serde_json::from_str::<LogoValue>(&serde_json::to_string(&value).unwrap()).unwrap(), value);
+}
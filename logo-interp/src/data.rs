@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+use crate::core::{LogoValue, Word};
+use crate::executor_state::{EState, Function};
+use crate::policy::{Category, set_category};
+
+/// Primitives for getting datasets into programs: reading files, JSON and CSV.
+pub(crate) fn add_data_primitives<S: 'static>(functions: &mut HashMap<String, Function<S>>) {
+    functions.insert("readfile".to_string(), Function::from_fn1(readfile));
+    functions.insert("writefile".to_string(), Function::from_proc2(writefile));
+    functions.insert("parsecsv".to_string(), Function::from_fn1(parsecsv));
+    #[cfg(feature = "json")]
+    {
+        functions.insert("parsejson".to_string(), Function::from_fn1(parsejson));
+        functions.insert("tojson".to_string(), Function::from_fn1(tojson));
+    }
+    set_category(functions, Category::Files, &["readfile", "writefile"]);
+    set_category(functions, Category::Data, &["parsecsv", "parsejson", "tojson"]);
+}
+
+fn readfile<S>(state: &mut EState<S>, path: String) -> Result<String, String> {
+    state.files.read(&path)
+}
+
+fn writefile<S>(state: &mut EState<S>, path: String, text: String) -> Result<(), String> {
+    state.files.write(&path, &text)
+}
+
+#[cfg(feature = "json")]
+fn parsejson<S>(state: &mut EState<S>, text: String) -> Result<LogoValue, String> {
+    use crate::core::LogoConvertible;
+    match serde_json::from_str::<serde_json::Value>(&text) {
+        Ok(value) => {
+            let value = value.to_logo();
+            state.limits.check_items(&value)?;
+            Ok(value)
+        },
+        Err(err) => Err(format!("Invalid JSON: {}", err))
+    }
+}
+
+#[cfg(feature = "json")]
+fn tojson<S>(_: &mut EState<S>, value: LogoValue) -> Result<String, String> {
+    use crate::core::LogoConvertible;
+    Ok(serde_json::Value::from_logo(value)?.to_string())
+}
+
+/// Outputs a list of rows, numbers become words and other fields strings.
+fn parsecsv<S>(state: &mut EState<S>, text: String) -> Result<Vec<LogoValue>, String> {
+    let rows = parse_csv(&text)?;
+    let rows: Vec<LogoValue> = rows.into_iter().map(|row| {
+        LogoValue::List(row.into_iter().map(|field| {
+            match field.trim().parse::<f64>() {
+                Ok(_) => LogoValue::Word(Word(field.trim().to_string())),
+                Err(_) => LogoValue::String(field)
+            }
+        }).collect())
+    }).collect();
+    for row in &rows {
+        state.limits.check_items(row)?;
+    }
+    Ok(rows)
+}
+
+/// Splits CSV text into rows of fields, fields may be quoted with `"` and contain `""`, commas and newlines.
+pub fn parse_csv(text: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut it = text.chars().peekable();
+    while let Some(ch) = it.next() {
+        if quoted {
+            match ch {
+                '"' if it.peek() == Some(&'"') => {
+                    it.next();
+                    field.push('"');
+                },
+                '"' => quoted = false,
+                _ => field.push(ch)
+            }
+            continue;
+        }
+        match ch {
+            '"' if field.is_empty() => quoted = true,
+            ',' => row.push(std::mem::take(&mut field)),
+            '\r' => {},
+            '\n' => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            },
+            _ => field.push(ch)
+        }
+    }
+    if quoted {
+        return Err("Missing closing quote in CSV".to_string());
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+#[test]
+fn test_data() {
+    use crate::executor::execute_str;
+    use crate::stdlib::add_stdlib;
+    use crate::vfs::{FileSystem, MemoryFileSystem};
+
+    let mut files = MemoryFileSystem::new();
+    files.write("sales.csv", "month,amount\nJan,10\n\"Feb, \"\"short\"\"\",2.5\n").unwrap();
+    let mut state = EState::new(());
+    add_stdlib(&mut state);
+    state.files = Box::new(files);
+
+    execute_str(&mut state, "", "make 'rows' bf parsecsv readfile 'sales.csv'").unwrap();
+    assert_eq!(state.vars["rows"], LogoValue::List(vec![
+        LogoValue::List(vec![LogoValue::String("Jan".to_string()), LogoValue::Word(Word("10".to_string()))].into()),
+        LogoValue::List(vec![LogoValue::String("Feb, \"short\"".to_string()), LogoValue::Word(Word("2.5".to_string()))].into()),
+    ].into()));
+
+    #[cfg(feature = "json")]
+    {
+        execute_str(&mut state, "", "make 'data' parsejson '{\"values\": [3, 1.5], \"label\": \"a b\"}' \
+                                     writefile 'out.json' tojson :data").unwrap();
+        assert_eq!(state.files.read("out.json"), Ok("{\"label\":\"a b\",\"values\":[3,1.5]}".to_string()));
+        assert!(execute_str(&mut state, "", "show parsejson '[1,'").is_err());
+    }
+}
@@ -0,0 +1,121 @@
+use crate::core::LogoValue;
+
+/// Logo implementation whose quoting rules, primitives and printing are followed.
+/// It's chosen when `EState` is created, as libraries register primitives according to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dialect {
+    #[default]
+    Native,
+    UCBLogo,
+    FMSLogo,
+    AppleLogo
+}
+
+impl Dialect {
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "native" => Some(Dialect::Native),
+            "ucblogo" | "ucb" => Some(Dialect::UCBLogo),
+            "fmslogo" | "mswlogo" | "fms" => Some(Dialect::FMSLogo),
+            "applelogo" | "apple" => Some(Dialect::AppleLogo),
+            _ => None
+        }
+    }
+
+    /// Whether `'long string'` is a string, otherwise `'` is an ordinary character of a word.
+    pub fn single_quote_strings(self) -> bool {
+        self == Dialect::Native
+    }
+
+    /// Whether characters between `|` bars are taken literally, as in `"|hello world|`.
+    pub fn bar_quotes(self) -> bool {
+        self == Dialect::UCBLogo || self == Dialect::FMSLogo
+    }
+
+    /// Whether `\` makes the next character a part of the word, as in `"hello\ world`.
+    pub fn backslash_escapes(self) -> bool {
+        self != Dialect::Native
+    }
+
+    /// Significant digits shown when printing fractional numbers.
+    fn print_precision(self) -> Option<usize> {
+        match self {
+            Dialect::Native => None,
+            Dialect::UCBLogo | Dialect::FMSLogo => Some(15),
+            Dialect::AppleLogo => Some(6)
+        }
+    }
+
+    pub fn format_word(self, word: &str) -> String {
+        let precision = match self.print_precision() {
+            Some(precision) => precision,
+            None => return word.to_string()
+        };
+        if !word.contains(['.', 'e']) {
+            return word.to_string();
+        }
+        match word.parse::<f64>() {
+            Ok(number) if number.is_finite() => format_significant(number, precision),
+            _ => word.to_string()
+        }
+    }
+
+    /// Text shown by `show`, lists keep their brackets.
+    pub fn format_show(self, value: &LogoValue) -> String {
+        match value {
+            LogoValue::Word(word) => self.format_word(&word.0),
+            LogoValue::String(str) => str.clone(),
+            LogoValue::List(list) => {
+                let items: Vec<String> = list.iter().map(|x| self.format_show(x)).collect();
+                format!("[{}]", items.join(" "))
+            }
+        }
+    }
+
+    /// Text shown by `print`, the outer brackets of a list are omitted.
+    pub fn format_print(self, value: &LogoValue) -> String {
+        match value {
+            LogoValue::List(list) => {
+                let items: Vec<String> = list.iter().map(|x| self.format_show(x)).collect();
+                items.join(" ")
+            },
+            _ => self.format_show(value)
+        }
+    }
+}
+
+fn format_significant(number: f64, digits: usize) -> String {
+    if number == 0.0 {
+        return "0".to_string();
+    }
+    let exponent = number.abs().log10().floor() as i32;
+    if exponent < -5 || exponent >= digits as i32 {
+        let text = format!("{:.*e}", digits - 1, number);
+        let (mantissa, exponent) = text.split_once('e').unwrap();
+        return format!("{}e{}", trim_zeros(mantissa), exponent);
+    }
+    let decimals = (digits as i32 - 1 - exponent).max(0) as usize;
+    trim_zeros(&format!("{:.*}", decimals, number)).to_string()
+}
+
+fn trim_zeros(text: &str) -> &str {
+    if !text.contains('.') {
+        return text;
+    }
+    text.trim_end_matches('0').trim_end_matches('.')
+}
+
+#[test]
+fn test_dialect_printing() {
+    use crate::core::Word;
+
+    let value = LogoValue::List(vec![
+        LogoValue::Word(Word("0.3333333333333333".to_string())),
+        LogoValue::List(vec![LogoValue::Word(Word("5".to_string()))].into()),
+    ].into());
+    assert_eq!(Dialect::Native.format_print(&value), "0.3333333333333333 [5]");
+    assert_eq!(Dialect::UCBLogo.format_print(&value), "0.333333333333333 [5]");
+    assert_eq!(Dialect::AppleLogo.format_show(&value), "[0.333333 [5]]");
+    assert_eq!(Dialect::UCBLogo.format_word("2.5"), "2.5");
+    assert_eq!(Dialect::UCBLogo.format_word("1e-7"), "1e-7");
+}
@@ -0,0 +1,437 @@
+use std::collections::HashMap;
+use crate::core::LogoProcedure;
+use crate::executor_state::{EState, Function, Param, ParamKind};
+use crate::policy::Category;
+
+/// Documentation of a primitive. Libraries keep tables of them, `attach_docs` gives them to the primitives.
+#[derive(Debug, PartialEq)]
+pub struct Doc {
+    /// Names of the primitive, the first one is the main one and the others are aliases.
+    pub names: &'static [&'static str],
+    pub description: &'static str,
+    /// Names and meanings of the inputs.
+    pub inputs: &'static [(&'static str, &'static str)],
+    /// What the primitive outputs, empty for commands.
+    pub output: &'static str,
+    pub example: &'static str
+}
+
+/// Gives the primitives their docs. The description becomes the help of primitives which have none,
+/// and inputs without names take the documented ones.
+pub fn attach_docs<S>(functions: &mut HashMap<String, Function<S>>, docs: &'static [Doc]) {
+    for doc in docs {
+        for name in doc.names {
+            let function = match functions.get_mut(*name) {
+                Some(function) => function,
+                None => continue
+            };
+            function.doc = Some(doc);
+            if function.help.is_empty() {
+                function.help = doc.description.to_string();
+            }
+            if function.params.len() == doc.inputs.len() {
+                for (idx, (param, (input, _))) in function.params.iter_mut().zip(doc.inputs).enumerate() {
+                    if param.name == format!("input{}", idx + 1) {
+                        param.name = input.to_string();
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Call like `setpos :pos`, optional inputs are in brackets and rest ones are followed by `...`.
+pub fn syntax(name: &str, params: &[Param]) -> String {
+    let mut syntax = name.to_string();
+    for param in params {
+        match param.kind {
+            ParamKind::Required => syntax += &format!(" :{}", param.name),
+            ParamKind::Optional => syntax += &format!(" [:{}]", param.name),
+            ParamKind::Rest => syntax += &format!(" [:{}]...", param.name)
+        }
+    }
+    syntax
+}
+
+/// Help of a primitive or a procedure, put together for showing it as text or Markdown.
+struct Entry {
+    name: String,
+    syntax: String,
+    description: String,
+    inputs: Vec<(String, String)>,
+    output: String,
+    example: String,
+    aliases: Vec<String>
+}
+
+impl Entry {
+    fn procedure(name: &str, procedure: &LogoProcedure) -> Self {
+        let params: Vec<Param> = procedure.arg_names.iter()
+            .map(|arg_name| Param {name: arg_name.clone(), kind: ParamKind::Required})
+            .collect();
+        Entry {
+            name: name.to_string(),
+            syntax: syntax(name, &params),
+            description: procedure.doc.clone(),
+            inputs: Vec::new(),
+            output: String::new(),
+            example: String::new(),
+            aliases: Vec::new()
+        }
+    }
+
+    fn to_text(&self) -> String {
+        let mut lines = vec![self.syntax.clone()];
+        if !self.description.is_empty() {
+            lines.push(self.description.clone());
+        }
+        for (input, meaning) in &self.inputs {
+            lines.push(format!("  :{} - {}", input, meaning));
+        }
+        if !self.output.is_empty() {
+            lines.push(format!("Outputs {}", self.output));
+        }
+        if !self.example.is_empty() {
+            lines.push(format!("Example: {}", self.example));
+        }
+        if !self.aliases.is_empty() {
+            lines.push(format!("Also: {}", self.aliases.join(", ")));
+        }
+        lines.join("\n")
+    }
+
+    fn to_markdown(&self) -> String {
+        let mut text = format!("### {}\n\n`{}`\n\n", self.name, self.syntax);
+        if !self.description.is_empty() {
+            text += &format!("{}\n\n", self.description);
+        }
+        if !self.inputs.is_empty() {
+            for (input, meaning) in &self.inputs {
+                text += &format!("- `{}`: {}\n", input, meaning);
+            }
+            text += "\n";
+        }
+        if !self.output.is_empty() {
+            text += &format!("Outputs {}\n\n", self.output);
+        }
+        if !self.example.is_empty() {
+            text += &format!("```logo\n{}\n```\n\n", self.example);
+        }
+        if !self.aliases.is_empty() {
+            let aliases: Vec<String> = self.aliases.iter().map(|alias| format!("`{}`", alias)).collect();
+            text += &format!("Also: {}\n\n", aliases.join(", "));
+        }
+        text
+    }
+}
+
+/// Order of sections in the Markdown docs.
+const CATEGORIES: [Category; 11] = [
+    Category::Turtle, Category::Control, Category::Math, Category::Logic, Category::Data, Category::Variables,
+    Category::Text, Category::Files, Category::Time, Category::System, Category::Other
+];
+
+impl<S> EState<S> {
+    /// Main name of the primitive, which may be given by an alias.
+    fn main_name(&self, name: &str) -> Option<String> {
+        let name = name.to_lowercase();
+        let primitive = self.locale.primitive_name(&name);
+        let function = self.functions.get(primitive)?;
+        match function.doc.map(|doc| doc.names[0]) {
+            Some(main) if self.functions.contains_key(main) => Some(main.to_string()),
+            _ => Some(primitive.to_string())
+        }
+    }
+
+    fn primitive_entry(&self, name: &str) -> Option<Entry> {
+        let function = self.functions.get(name)?;
+        let mut entry = Entry {
+            name: name.to_string(),
+            syntax: syntax(name, &function.params),
+            description: function.help.clone(),
+            inputs: Vec::new(),
+            output: String::new(),
+            example: String::new(),
+            aliases: self.locale.aliases_of(name).into_iter().map(|alias| alias.to_string()).collect()
+        };
+        if let Some(doc) = function.doc {
+            entry.inputs = doc.inputs.iter().map(|(input, meaning)| (input.to_string(), meaning.to_string())).collect();
+            entry.output = doc.output.to_string();
+            entry.example = doc.example.to_string();
+            for alias in doc.names.iter().filter(|alias| **alias != name && self.functions.contains_key(alias)) {
+                entry.aliases.push(alias.to_string());
+                entry.aliases.extend(self.locale.aliases_of(alias).into_iter().map(|alias| alias.to_string()));
+            }
+        }
+        Some(entry)
+    }
+
+    /// Help of a primitive or a procedure as text, `None` if there's no such name.
+    pub fn help_text(&self, name: &str) -> Option<String> {
+        if let Some(main) = self.main_name(name) {
+            return self.primitive_entry(&main).map(|entry| entry.to_text());
+        }
+        let name = name.to_lowercase();
+        let procedure = self.logo_procedures.get(&name).cloned()
+            .or_else(|| self.library_procedures().remove(&name))?;
+        Some(Entry::procedure(&name, &procedure).to_text())
+    }
+
+    /// Docs of the primitives programs may call, by category, followed by docs of own procedures.
+    pub fn docs_markdown(&self) -> String {
+        let mut sections: HashMap<Category, Vec<Entry>> = HashMap::new();
+        for (name, function) in &self.functions {
+            if !self.policy.allows(name, function.category) || self.main_name(name).as_deref() != Some(name) {
+                continue;
+            }
+            if let Some(entry) = self.primitive_entry(name) {
+                sections.entry(function.category).or_default().push(entry);
+            }
+        }
+
+        let mut text = "# Primitives\n\n".to_string();
+        for category in CATEGORIES {
+            let mut entries = match sections.remove(&category) {
+                Some(entries) => entries,
+                None => continue
+            };
+            entries.sort_by(|a, b| a.name.cmp(&b.name));
+            text += &format!("## {:?}\n\n", category);
+            for entry in entries {
+                text += &entry.to_markdown();
+            }
+        }
+        let procedures = self.own_procedures();
+        if !procedures.is_empty() {
+            text += "# Procedures\n\n";
+            for (name, procedure) in procedures {
+                text += &Entry::procedure(&name, &procedure).to_markdown();
+            }
+        }
+        text.trim_end().to_string() + "\n"
+    }
+}
+
+/// Docs of the primitives `add_stdlib` installs.
+pub static STDLIB_DOCS: &[Doc] = &[
+    Doc {names: &["repeat"], description: "Runs the instructions the number of times",
+        inputs: &[("times", "how many times to run them"), ("instructions", "list of instructions")],
+        output: "", example: "repeat 4 [fd 100 rt 90]"},
+    Doc {names: &["print", "pr"], description: "Prints the input, lists are printed without their outer brackets",
+        inputs: &[("thing", "word or list to print")], output: "", example: "print [Hello world]"},
+    Doc {names: &["show"], description: "Prints the input, lists are printed with brackets",
+        inputs: &[("thing", "word or list to print")], output: "", example: "show [1 2 3]"},
+    Doc {names: &["profile"], description: "Runs the instructions measuring how long each procedure takes, then prints the report",
+        inputs: &[("instructions", "list of instructions")], output: "", example: "profile [repeat 100 [square 10]]"},
+    Doc {names: &["help"], description: "Prints the documentation of a primitive or a procedure",
+        inputs: &[("name", "name of the primitive or procedure")], output: "", example: "help \"repeat"},
+
+    Doc {names: &["abs"], description: "Outputs the number without its sign",
+        inputs: &[("number", "any number")], output: "the absolute value", example: "print abs -5"},
+    Doc {names: &["arctan"], description: "Outputs the angle whose tangent is the number",
+        inputs: &[("number", "tangent of the angle")], output: "the angle in degrees", example: "print arctan 1"},
+    Doc {names: &["cos"], description: "Outputs the cosine of the angle",
+        inputs: &[("angle", "angle in degrees")], output: "a number from -1 to 1", example: "print cos 60"},
+    Doc {names: &["difference"], description: "Outputs the first number minus the second one, same as a - b",
+        inputs: &[("a", "number to subtract from"), ("b", "number to subtract")], output: "a number", example: "print difference 10 3"},
+    Doc {names: &["exp"], description: "Outputs e to the power of the number",
+        inputs: &[("number", "the power")], output: "a number", example: "print exp 1"},
+    Doc {names: &["greater?"], description: "Tells whether the first number is greater than the second one, same as a > b",
+        inputs: &[("a", "first number"), ("b", "second number")], output: "true or false", example: "print greater? 5 3"},
+    Doc {names: &["less?"], description: "Tells whether the first number is less than the second one, same as a < b",
+        inputs: &[("a", "first number"), ("b", "second number")], output: "true or false", example: "print less? 5 3"},
+    Doc {names: &["int"], description: "Outputs the number without its fractional part",
+        inputs: &[("number", "any number")], output: "a whole number", example: "print int 3.7"},
+    Doc {names: &["log"], description: "Outputs the logarithm of the number to base 10",
+        inputs: &[("number", "a positive number")], output: "a number", example: "print log 1000"},
+    Doc {names: &["ln"], description: "Outputs the natural logarithm of the number",
+        inputs: &[("number", "a positive number")], output: "a number", example: "print ln 10"},
+    Doc {names: &["minus"], description: "Outputs the number with the opposite sign",
+        inputs: &[("number", "any number")], output: "a number", example: "print minus 5"},
+    Doc {names: &["pi"], description: "Outputs the number pi",
+        inputs: &[], output: "3.141592653589793", example: "print 2 * pi"},
+    Doc {names: &["power"], description: "Outputs the first number raised to the power of the second one",
+        inputs: &[("base", "number to raise"), ("exponent", "the power")], output: "a number", example: "print power 2 10"},
+    Doc {names: &["product"], description: "Outputs the product of the inputs, same as a * b",
+        inputs: &[("a", "first number"), ("b", "second number"), ("rest", "more numbers, given in parentheses")],
+        output: "a number", example: "print (product 2 3 4)"},
+    Doc {names: &["quotient"], description: "Outputs the first number divided by the second one, same as a / b",
+        inputs: &[("a", "number to divide"), ("b", "number to divide by")], output: "a number", example: "print quotient 7 2"},
+    Doc {names: &["remainder"], description: "Outputs the remainder of dividing the first number by the second one",
+        inputs: &[("a", "number to divide"), ("b", "number to divide by")], output: "a whole number", example: "print remainder 7 3"},
+    Doc {names: &["random"], description: "Outputs a random whole number from 0 to the input minus 1",
+        inputs: &[("limit", "number greater than 0")], output: "a whole number", example: "setc random 140"},
+    Doc {names: &["round"], description: "Outputs the whole number nearest to the input",
+        inputs: &[("number", "any number")], output: "a whole number", example: "print round 2.5"},
+    Doc {names: &["sin"], description: "Outputs the sine of the angle",
+        inputs: &[("angle", "angle in degrees")], output: "a number from -1 to 1", example: "print sin 30"},
+    Doc {names: &["sqrt"], description: "Outputs the square root of the number",
+        inputs: &[("number", "a number which isn't negative")], output: "a number", example: "print sqrt 16"},
+    Doc {names: &["sum"], description: "Outputs the sum of the inputs, same as a + b",
+        inputs: &[("a", "first number"), ("b", "second number"), ("rest", "more numbers, given in parentheses")],
+        output: "a number", example: "print (sum 1 2 3)"},
+    Doc {names: &["tan"], description: "Outputs the tangent of the angle",
+        inputs: &[("angle", "angle in degrees")], output: "a number", example: "print tan 45"},
+
+    Doc {names: &["butfirst", "bf"], description: "Outputs the list without its first item",
+        inputs: &[("list", "a list which isn't empty")], output: "a list", example: "print butfirst [a b c]"},
+    Doc {names: &["butlast", "bl"], description: "Outputs the list without its last item",
+        inputs: &[("list", "a list which isn't empty")], output: "a list", example: "print butlast [a b c]"},
+    Doc {names: &["count"], description: "Outputs the number of items in the list",
+        inputs: &[("list", "any list")], output: "a whole number", example: "print count [a b c]"},
+    Doc {names: &["empty?", "emptyp"], description: "Tells whether the list has no items",
+        inputs: &[("list", "any list")], output: "true or false", example: "print empty? []"},
+    Doc {names: &["equal?", "equalp", "identical?"], description: "Tells whether the inputs are the same, same as a = b",
+        inputs: &[("a", "word or list"), ("b", "word or list")], output: "true or false", example: "print equal? [1 2] [1 2]"},
+    Doc {names: &["first"], description: "Outputs the first item of the list",
+        inputs: &[("list", "a list which isn't empty")], output: "the item", example: "print first [a b c]"},
+    Doc {names: &["fput"], description: "Outputs the list with the thing added at the start",
+        inputs: &[("thing", "word or list to add"), ("list", "any list")], output: "a list", example: "print fput \"a [b c]"},
+    Doc {names: &["item"], description: "Outputs the item of the list at the index, the first item has index 0",
+        inputs: &[("index", "position of the item, from 0"), ("list", "any list")], output: "the item", example: "print item 1 [a b c]"},
+    Doc {names: &["last"], description: "Outputs the last item of the list",
+        inputs: &[("list", "a list which isn't empty")], output: "the item", example: "print last [a b c]"},
+    Doc {names: &["list"], description: "Outputs a list joining the input lists",
+        inputs: &[("a", "first list"), ("b", "second list"), ("rest", "more lists, given in parentheses")],
+        output: "a list", example: "print list [a b] [c]"},
+    Doc {names: &["list?", "listp"], description: "Tells whether the input is a list",
+        inputs: &[("thing", "any value")], output: "true or false", example: "print list? [a]"},
+    Doc {names: &["lput"], description: "Outputs the list with the thing added at the end",
+        inputs: &[("thing", "word or list to add"), ("list", "any list")], output: "a list", example: "print lput \"c [a b]"},
+    Doc {names: &["member?", "memberp"], description: "Tells whether the thing is an item of the list",
+        inputs: &[("thing", "word or list to look for"), ("list", "any list")], output: "true or false", example: "print member? \"b [a b c]"},
+    Doc {names: &["number?", "numberp"], description: "Tells whether the input is a number",
+        inputs: &[("thing", "any value")], output: "true or false", example: "print number? 42"},
+    Doc {names: &["pick"], description: "Outputs a random item of the list",
+        inputs: &[("list", "a list which isn't empty")], output: "the item", example: "print pick [red green blue]"},
+    Doc {names: &["word?", "wordp"], description: "Tells whether the input is a word",
+        inputs: &[("thing", "any value")], output: "true or false", example: "print word? \"hello"},
+
+    Doc {names: &["and"], description: "Tells whether all inputs are true",
+        inputs: &[("a", "true or false"), ("b", "true or false"), ("rest", "more conditions, given in parentheses")],
+        output: "true or false", example: "print and 1 < 2 2 < 3"},
+    Doc {names: &["or"], description: "Tells whether any input is true",
+        inputs: &[("a", "true or false"), ("b", "true or false"), ("rest", "more conditions, given in parentheses")],
+        output: "true or false", example: "print or 1 > 2 2 < 3"},
+    Doc {names: &["not"], description: "Outputs true for false and false for true",
+        inputs: &[("condition", "true or false")], output: "true or false", example: "print not 1 > 2"},
+    Doc {names: &["if"], description: "Runs the instructions if the condition is true",
+        inputs: &[("condition", "true or false"), ("instructions", "list of instructions")],
+        output: "", example: "if :size > 100 [print \"big]"},
+    Doc {names: &["ifelse"], description: "Runs the first instructions if the condition is true, the second ones otherwise",
+        inputs: &[("condition", "true or false"), ("then", "instructions for true"), ("else", "instructions for false")],
+        output: "", example: "ifelse :x > 0 [print \"positive] [print \"negative]"},
+
+    Doc {names: &["make"], description: "Gives the variable a value, :name outputs it",
+        inputs: &[("name", "name of the variable"), ("value", "any value")], output: "", example: "make \"size 100"},
+    Doc {names: &["clearname"], description: "Removes the variable",
+        inputs: &[("name", "name of the variable")], output: "", example: "clearname \"size"},
+    Doc {names: &["clearnames"], description: "Removes all variables",
+        inputs: &[], output: "", example: "clearnames"},
+    Doc {names: &["name?", "namep"], description: "Tells whether there is a variable with the name",
+        inputs: &[("name", "name of the variable")], output: "true or false", example: "print name? \"size"},
+    Doc {names: &["names"], description: "Outputs names of all variables",
+        inputs: &[], output: "a list", example: "print names"},
+    Doc {names: &["thing"], description: "Outputs the value of the variable, same as :name",
+        inputs: &[("name", "name of the variable")], output: "the value", example: "print thing \"size"},
+    Doc {names: &["pprop"], description: "Puts the property into the property list with the name",
+        inputs: &[("name", "name of the property list"), ("property", "name of the property"), ("value", "any value")],
+        output: "", example: "pprop \"rex \"kind \"dog"},
+    Doc {names: &["gprop"], description: "Outputs the property of the property list, or an empty list if there's none",
+        inputs: &[("name", "name of the property list"), ("property", "name of the property")],
+        output: "the value", example: "print gprop \"rex \"kind"},
+    Doc {names: &["remprop"], description: "Removes the property from the property list",
+        inputs: &[("name", "name of the property list"), ("property", "name of the property")],
+        output: "", example: "remprop \"rex \"kind"},
+    Doc {names: &["plist"], description: "Outputs the property list as names of properties followed by their values",
+        inputs: &[("name", "name of the property list")], output: "a list", example: "print plist \"rex"},
+
+    Doc {names: &["po"], description: "Prints the definitions of the procedures",
+        inputs: &[("names", "name or list of names of procedures")], output: "", example: "po \"square"},
+    Doc {names: &["pots"], description: "Prints the title lines of all procedures",
+        inputs: &[], output: "", example: "pots"},
+    Doc {names: &["pons"], description: "Prints all variables with their values",
+        inputs: &[], output: "", example: "pons"},
+    Doc {names: &["pops"], description: "Prints all property lists",
+        inputs: &[], output: "", example: "pops"},
+    Doc {names: &["save"], description: "Saves procedures, variables, property lists and the turtle to the file",
+        inputs: &[("path", "name of the file")], output: "", example: "save \"drawing.lgw"},
+    Doc {names: &["load"], description: "Runs the file, with a namespace its procedures are called like namespace.name",
+        inputs: &[("path", "name of the file"), ("namespace", "prefix of the procedure names, given in parentheses")],
+        output: "", example: "(load \"shapes.logo \"shapes)"},
+
+    Doc {names: &["output", "op"], description: "Ends the procedure, which outputs the input",
+        inputs: &[("value", "what the procedure outputs")], output: "", example: "to double :x output :x * 2 end"},
+    Doc {names: &["stop"], description: "Ends the procedure",
+        inputs: &[], output: "", example: "if :size < 1 [stop]"},
+    Doc {names: &["apply"], description: "Runs the procedure or primitive named by the template, or a list like [[x y] output :x + :y], with the inputs",
+        inputs: &[("template", "name or list with inputs and instructions"), ("inputs", "list of inputs")],
+        output: "what the template outputs", example: "print apply \"sum [1 2]"},
+    Doc {names: &[".defmacro"], description: "Defines a macro, the list it outputs is run in place of the call",
+        inputs: &[("name", "name of the macro"), ("text", "list of inputs followed by lists of instructions")],
+        output: "", example: ".defmacro \"twice [[x] [output list :x :x]]"},
+    Doc {names: &["procedures"], description: "Outputs names of procedures, without buried ones of libraries",
+        inputs: &[], output: "a list", example: "print procedures"},
+
+    Doc {names: &["readfile"], description: "Outputs the text of the file",
+        inputs: &[("path", "name of the file")], output: "the text", example: "make \"text readfile \"data.txt"},
+    Doc {names: &["writefile"], description: "Writes the text to the file, replacing what was there",
+        inputs: &[("path", "name of the file"), ("text", "text to write")], output: "", example: "writefile \"notes.txt \"hello"},
+    Doc {names: &["parsecsv"], description: "Outputs a list of rows of the CSV text, each row is a list of fields",
+        inputs: &[("text", "comma separated values")], output: "a list of lists", example: "print parsecsv readfile \"data.csv"},
+    Doc {names: &["parsejson"], description: "Outputs the JSON text as Logo values, objects become lists of name and value pairs",
+        inputs: &[("text", "JSON text")], output: "a word or list", example: "print parsejson \"[1,2,3]"},
+    Doc {names: &["tojson"], description: "Outputs the value as JSON text",
+        inputs: &[("value", "any value")], output: "the text", example: "print tojson [1 2 3]"},
+
+    Doc {names: &["launch"], description: "Starts a process running the instructions, it takes turns with the other processes",
+        inputs: &[("instructions", "list of instructions"), ("turtle", "number of the turtle it drives, given in parentheses, a new one if omitted")],
+        output: "", example: "launch [repeat 36 [fd 10 rt 10 wait 1]]"},
+    Doc {names: &["forever"], description: "Starts a process running the instructions over and over until it's cancelled",
+        inputs: &[("instructions", "list of instructions"), ("turtle", "number of the turtle it drives, given in parentheses, a new one if omitted")],
+        output: "", example: "forever [fd 1 rt 1]"},
+    Doc {names: &["wait"], description: "Pauses for the number of ticks while other processes run, a tick is a sixtieth of a second",
+        inputs: &[("ticks", "how long to wait")], output: "", example: "wait 60"},
+    Doc {names: &["cancel"], description: "Stops processes launched with the instructions",
+        inputs: &[("instructions", "list the processes were launched with")], output: "", example: "cancel [fd 1 rt 1]"},
+    Doc {names: &["stopall"], description: "Stops all processes, timers and conditions of when",
+        inputs: &[], output: "", example: "stopall"},
+    Doc {names: &["processes"], description: "Outputs the instructions of running processes",
+        inputs: &[], output: "a list of lists", example: "show processes"},
+
+    Doc {names: &["onkey"], description: "Runs the handler when a key is pressed, an empty list removes it",
+        inputs: &[("handler", "list like [[key] print :key]")], output: "", example: "onkey [[key] if :key = \"left [lt 15]]"},
+    Doc {names: &["onclick"], description: "Runs the handler when the canvas is clicked, an empty list removes it",
+        inputs: &[("handler", "list like [[x y] setpos list :x :y]")], output: "", example: "onclick [[x y] setpos list :x :y]"},
+    Doc {names: &["ontimer"], description: "Runs the instructions every number of ticks, a tick is a sixtieth of a second",
+        inputs: &[("ticks", "how often to run them"), ("instructions", "list of instructions")], output: "", example: "ontimer 60 [rt 6]"},
+    Doc {names: &["when"], description: "Runs the instructions each time the condition becomes true, it's checked every tick",
+        inputs: &[("condition", "list outputting true or false"), ("instructions", "list of instructions")],
+        output: "", example: "when [xcoor > 200] [home]"},
+    Doc {names: &["clearevents"], description: "Removes all event handlers, timers and conditions",
+        inputs: &[], output: "", example: "clearevents"}
+];
+
+#[test]
+fn test_docs() {
+    use crate::executor::execute_str;
+    use crate::locale::Locale;
+    use crate::stdlib::add_stdlib;
+
+    let mut state = EState::new(());
+    add_stdlib(&mut state);
+    state.locale = Locale::ukrainian();
+    let help = state.help_text("BF").unwrap();
+    assert!(help.starts_with("butfirst :list\nOutputs the list without its first item\n  :list - "), "{}", help);
+    assert!(help.contains("Example: print butfirst [a b c]") && help.contains("bf"));
+    assert_eq!(state.get_function("item").unwrap().params[0].name, "index");
+
+    execute_str(&mut state, "; Draws a square\n; of any size\nto square :size\nrepeat 4 [fd :size rt 90]\nend", "").unwrap();
+    assert_eq!(state.help_text("square").unwrap(), "square :size\nDraws a square\nof any size");
+    assert!(state.help_text("nosuch").is_none());
+
+    let markdown = state.docs_markdown();
+    assert!(markdown.starts_with("# Primitives\n\n## Control\n\n"));
+    assert!(markdown.contains("### butfirst\n\n`butfirst :list`\n\n") && !markdown.contains("### bf\n"));
+    assert!(markdown.ends_with("# Procedures\n\n### square\n\n`square :size`\n\nDraws a square\nof any size\n"));
+}
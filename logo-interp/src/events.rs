@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+use crate::core::{LogoConvertible, LogoList, LogoValue, Word};
+use crate::executor::{execute, run_expansion};
+use crate::executor_state::{EState, Function};
+use crate::policy::{Category, set_category};
+use crate::stdlib::apply;
+
+/// Primitives registering handlers of events: `onkey`, `onclick`, `ontimer`, `when` and `clearevents`.
+pub(crate) fn add_event_primitives<S: 'static>(functions: &mut HashMap<String, Function<S>>) {
+    functions.insert("onkey".to_string(), Function::new("onkey", &["handler"], onkey)
+        .with_help("Runs the handler when a key is pressed, like [[key] print :key], an empty list removes it"));
+    functions.insert("onclick".to_string(), Function::new("onclick", &["handler"], onclick)
+        .with_help("Runs the handler when the canvas is clicked, like [[x y] setpos list :x :y], an empty list removes it"));
+    functions.insert("ontimer".to_string(), Function::new("ontimer", &["ticks", "instructions"], ontimer)
+        .with_help("Runs the instructions every number of ticks, sixtieths of a second"));
+    functions.insert("when".to_string(), Function::new("when", &["condition", "instructions"], when)
+        .with_help("Runs the instructions each time the condition becomes true, it's checked every tick"));
+    functions.insert("clearevents".to_string(), Function::new("clearevents", &[], clearevents)
+        .with_help("Removes all event handlers, timers and conditions"));
+    set_category(functions, Category::Control, &["onkey", "onclick", "when", "clearevents"]);
+    set_category(functions, Category::Time, &["ontimer"]);
+}
+
+/// Input from the host, clicks are in turtle coordinates.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// Key like `a`, `A` or `left`, see `Context` of logo-renderer for names of the keys front-ends send.
+    Key(String),
+    Click {x: f64, y: f64}
+}
+
+struct Timer {
+    id: u32,
+    interval: u64,
+    next_tick: u64,
+    code: LogoList
+}
+
+struct Watcher {
+    id: u32,
+    condition: LogoList,
+    code: LogoList,
+    was_true: bool
+}
+
+/// Handlers registered by programs. Timers and conditions are run by the scheduler every tick,
+/// keys and clicks when the host calls `dispatch_event`.
+#[derive(Default)]
+pub struct Events {
+    key_handler: Option<LogoValue>,
+    click_handler: Option<LogoValue>,
+    timers: Vec<Timer>,
+    watchers: Vec<Watcher>,
+    next_id: u32
+}
+
+impl Events {
+    pub fn new() -> Self {
+        Events::default()
+    }
+
+    /// Whether there are timers or conditions, which need ticks to run.
+    pub fn has_timers(&self) -> bool {
+        !self.timers.is_empty() || !self.watchers.is_empty()
+    }
+
+    pub fn clear_timers(&mut self) {
+        self.timers.clear();
+        self.watchers.clear();
+    }
+
+    pub fn clear(&mut self) {
+        *self = Events {next_id: self.next_id, ..Events::default()};
+    }
+}
+
+/// Runs the handler of the event, if there is one.
+pub fn dispatch_event<S>(state: &mut EState<S>, event: &Event) -> Result<(), String> {
+    let (handler, inputs) = match event {
+        Event::Key(key) => (state.events.key_handler.clone(), vec![LogoValue::Word(Word(key.clone()))]),
+        Event::Click {x, y} => (state.events.click_handler.clone(), vec![x.to_logo(), y.to_logo()])
+    };
+    match handler {
+        Some(handler) => run_handler(state, handler, inputs).map_err(|err| state.locale.translate(&err)),
+        None => Ok(())
+    }
+}
+
+/// Handlers are templates like the ones of `apply`, or lists of instructions which don't take the inputs.
+fn run_handler<S>(state: &mut EState<S>, handler: LogoValue, inputs: Vec<LogoValue>) -> Result<(), String> {
+    let result = match handler {
+        LogoValue::List(list) if !matches!(list.first(), Some(LogoValue::List(_))) => execute(state, &list),
+        _ => apply(state, handler, inputs).map(|_| ())
+    };
+    match result {
+        Err(err) if err == "Output" => Ok(()),
+        result => result
+    }
+}
+
+/// Runs timers which are due and conditions which became true, called by the scheduler every tick.
+pub(crate) fn run_timers<S>(state: &mut EState<S>, tick: u64) -> Result<(), String> {
+    let due: Vec<(u32, LogoList)> = state.events.timers.iter()
+        .filter(|timer| timer.next_tick <= tick)
+        .map(|timer| (timer.id, timer.code.clone()))
+        .collect();
+    for (id, code) in due {
+        // Handlers which ran before may have removed it
+        match state.events.timers.iter_mut().find(|timer| timer.id == id) {
+            Some(timer) => timer.next_tick = tick + timer.interval,
+            None => continue
+        }
+        run_handler(state, LogoValue::List(code), Vec::new())?;
+    }
+
+    let watchers: Vec<(u32, LogoList, LogoList)> = state.events.watchers.iter()
+        .map(|watcher| (watcher.id, watcher.condition.clone(), watcher.code.clone()))
+        .collect();
+    for (id, condition, code) in watchers {
+        let value = match run_expansion(state, &condition)? {
+            Some(value) => value,
+            None => return Err(format!("when doesn't like {} as input condition", LogoValue::List(condition)))
+        };
+        let is_true = bool::from_logo(value.clone())
+            .map_err(|_| format!("when doesn't like {} as input condition", value))?;
+        let became_true = match state.events.watchers.iter_mut().find(|watcher| watcher.id == id) {
+            Some(watcher) => {
+                let became_true = is_true && !watcher.was_true;
+                watcher.was_true = is_true;
+                became_true
+            },
+            None => continue
+        };
+        if became_true {
+            run_handler(state, LogoValue::List(code), Vec::new())?;
+        }
+    }
+    Ok(())
+}
+
+fn handler_of(handler: LogoValue) -> Option<LogoValue> {
+    match &handler {
+        LogoValue::List(list) if list.is_empty() => None,
+        _ => Some(handler)
+    }
+}
+
+fn onkey<S>(state: &mut EState<S>, handler: LogoValue) -> Result<(), String> {
+    state.events.key_handler = handler_of(handler);
+    Ok(())
+}
+
+fn onclick<S>(state: &mut EState<S>, handler: LogoValue) -> Result<(), String> {
+    state.events.click_handler = handler_of(handler);
+    Ok(())
+}
+
+fn ontimer<S>(state: &mut EState<S>, ticks: i32, code: LogoList) -> Result<(), String> {
+    if ticks < 1 {
+        return Err(format!("ontimer doesn't like {} as input ticks", ticks));
+    }
+    let events = &mut state.events;
+    events.next_id += 1;
+    events.timers.push(Timer {
+        id: events.next_id,
+        interval: ticks as u64,
+        next_tick: state.scheduler.tick() + ticks as u64,
+        code
+    });
+    Ok(())
+}
+
+fn when<S>(state: &mut EState<S>, condition: LogoList, code: LogoList) -> Result<(), String> {
+    let events = &mut state.events;
+    events.next_id += 1;
+    events.watchers.push(Watcher {id: events.next_id, condition, code, was_true: false});
+    Ok(())
+}
+
+fn clearevents<S>(state: &mut EState<S>) -> Result<(), String> {
+    state.events.clear();
+    Ok(())
+}
+
+#[test]
+fn test_events() {
+    use crate::executor::execute_str;
+    use crate::processes::run_ticks;
+    use crate::stdlib::add_stdlib;
+
+    let mut state = EState::new(Vec::<String>::new());
+    add_stdlib(&mut state);
+    state.functions.insert("add", Function::from_proc1(|s: &mut EState<Vec<String>>, x: LogoValue| -> Result<(), String> {
+        s.state.push(x.to_string());
+        Ok(())
+    }));
+    let proc_source = "to clicked :x :y add :x + :y end";
+    execute_str(&mut state, proc_source,
+                "onkey [[key] add :key] onclick 'clicked' make 'n' 0 \
+                 ontimer 2 [make 'n' :n + 1] when [:n = 2] [add 'two']").unwrap();
+    dispatch_event(&mut state, &Event::Key("left".to_string())).unwrap();
+    dispatch_event(&mut state, &Event::Click {x: 10.0, y: -3.0}).unwrap();
+    run_ticks(&mut state, 6).unwrap();
+    assert_eq!(state.state, vec!["left", "7", "two"]);
+    assert_eq!(state.vars["n"], LogoValue::Word(Word("3".to_string())));
+
+    execute_str(&mut state, proc_source, "onkey [] stopall").unwrap();
+    assert!(!state.events.has_timers());
+    dispatch_event(&mut state, &Event::Key("a".to_string())).unwrap();
+    dispatch_event(&mut state, &Event::Click {x: 1.0, y: 1.0}).unwrap();
+    assert_eq!(state.state.len(), 4);
+}
@@ -1,123 +1,363 @@
 use std::collections::HashMap;
+use std::iter::Peekable;
 use std::mem::swap;
-use crate::core::{LogoValue, Word};
+use std::slice::Iter;
+use crate::core::{LogoList, LogoProcedure, LogoValue, Word};
 use crate::executor_state::*;
 use crate::parser;
+use crate::parser::SpannedValue;
+use crate::shared::Shared;
+use crate::symbols::Symbol;
 
 pub fn execute_str<S>(state: &mut EState<S>, proc_source: &str, source: &str) -> Result<(), String> {
-    state.logo_procedures = parser::parse_procedures(proc_source)?;
-    execute(state, parser::parse(source)?)
+    let result = parse_and_execute(state, proc_source, source);
+    result.map_err(|err| state.locale.translate(&err))
 }
 
-pub fn execute<S>(state: &mut EState<S>, source: Vec<LogoValue>) -> Result<(), String> {
-    let transformed_source = math_transform(source)?;
-    let mut it = transformed_source.iter();
-    while it.len() > 0 {
-        match execute_expr(state, &mut it)? {
-            Some(val) => return Err(format!("Don't know what to do with {}", val)),
-            None => {}
+fn parse_and_execute<S>(state: &mut EState<S>, proc_source: &str, source: &str) -> Result<(), String> {
+    let mut procedures = state.library_procedures();
+    procedures.extend(state.loaded_procedures.clone());
+    procedures.extend(parser::parse_procedures_with(proc_source, &state.locale, state.dialect)?);
+    let source = parser::parse_in_dialect(source, state.dialect)?;
+    state.logo_procedures.clear();
+    state.logo_procedures.extend(procedures);
+    execute(state, &source.into())
+}
+
+/// Instruction of a compiled list, names of calls and variables are looked up in the interner once,
+/// when the list is compiled.
+#[derive(Debug)]
+pub(crate) enum Op {
+    /// Number, quoted word or list, they are their own values.
+    Value(LogoValue),
+    /// Call of a primitive or a procedure, with the word as it was written for errors.
+    Call(Symbol, Word),
+    /// Reading of a variable.
+    Var(Symbol),
+    /// Parentheses around a call of a primitive taking any number of inputs.
+    Open,
+    Close
+}
+
+/// Instructions of a list transformed by `math_transform`. The list keeps them, so it's compiled once
+/// however many times it runs, until the primitives change.
+#[derive(Clone)]
+pub(crate) struct Code {
+    pub(crate) ops: Shared<[Op]>,
+    /// Inputs of the procedure the list is the body of, with their symbols.
+    pub(crate) params: Shared<[(String, Symbol)]>,
+    generation: u64
+}
+
+pub(crate) fn compile<S>(state: &EState<S>, list: &LogoList) -> Result<Code, String> {
+    let generation = state.functions.generation();
+    if let Some(code) = list.code() {
+        if code.generation == generation {
+            return Ok(code);
+        }
+    }
+    let values = math_transform(list.to_vec(), &|word| is_flexible(state, word))?;
+    let mut symbols = state.symbols.borrow_mut();
+    let ops = values.into_iter().map(|value| match value {
+        LogoValue::Word(word) if word.0.parse::<f64>().is_err() => match word.0.as_str() {
+            "(" => Op::Open,
+            ")" => Op::Close,
+            spelling => match spelling.strip_prefix(':') {
+                Some(var_name) => Op::Var(symbols.intern(var_name)),
+                None => Op::Call(symbols.intern(spelling), word)
+            }
+        },
+        value => Op::Value(value)
+    }).collect();
+    let code = Code {ops, params: Shared::new([]), generation};
+    list.set_code(code.clone());
+    Ok(code)
+}
+
+/// Compiles the body of a procedure, interning names of its inputs along with it.
+pub(crate) fn compile_procedure<S>(state: &EState<S>, logo_proc: &LogoProcedure) -> Result<Code, String> {
+    let mut code = compile(state, &logo_proc.code)?;
+    let same_params = code.params.len() == logo_proc.arg_names.len() &&
+        code.params.iter().zip(&logo_proc.arg_names).all(|((name, _), arg_name)| name == arg_name);
+    if !same_params {
+        let mut symbols = state.symbols.borrow_mut();
+        code.params = logo_proc.arg_names.iter().map(|name| (name.clone(), symbols.intern(name))).collect();
+        logo_proc.code.set_code(code.clone());
+    }
+    Ok(code)
+}
+
+pub fn execute<S>(state: &mut EState<S>, code: &LogoList) -> Result<(), String> {
+    let code = compile(state, code)?;
+    execute_ops(state, &code.ops)
+}
+
+fn execute_ops<S>(state: &mut EState<S>, ops: &[Op]) -> Result<(), String> {
+    let mut it = ops.iter().peekable();
+    while it.peek().is_some() {
+        if let Some(val) = execute_expr(state, &mut it)? {
+            return Err(format!("Don't know what to do with {}", val));
         }
     }
     Ok(())
 }
 
-fn execute_expr<'a, S>(state: &mut EState<S>, it: &mut impl Iterator<Item = &'a LogoValue>) -> Result<Option<LogoValue>, String>
+/// Whether a call of the primitive in parentheses is kept by `math_transform` to pass any number of inputs.
+pub(crate) fn is_flexible<S>(state: &EState<S>, word: &str) -> bool {
+    state.get_function(&word.to_lowercase()).is_some_and(|fun| fun.is_flexible())
+}
+
+pub(crate) fn execute_expr<S>(state: &mut EState<S>, it: &mut Peekable<Iter<Op>>) -> Result<Option<LogoValue>, String>
 {
-    let cmd = it.next();
-    if cmd.is_none() {
-        return Ok(None)
-    }
-    let cmd = cmd.unwrap();
-    if let LogoValue::Word(word) = cmd {
-        let word = &word.0;
-        if let Ok(_) = word.parse::<f64>() {
-            return Ok(Some(LogoValue::Word(Word(word.clone()))))
+    let (symbol, cmd) = match it.next() {
+        None => return Ok(None),
+        Some(Op::Value(value)) => return Ok(Some(value.clone())),
+        Some(Op::Open) => return execute_paren_call(state, it),
+        Some(Op::Close) => return Err("Don't know what to do with )".to_string()),
+        Some(Op::Var(symbol)) => {
+            return match state.vars.get_symbol(*symbol) {
+                Some(val) => Ok(Some(val.clone())),
+                None => Err("No such variable".to_string())
+            };
+        },
+        Some(Op::Call(symbol, cmd)) => (*symbol, cmd)
+    };
+
+    let word = state.symbols.borrow().name(symbol);
+    let fun = state.get_function_symbol(symbol);
+    if let Some(fun) = fun {
+        if !state.is_allowed(&word, fun) {
+            return Err(format!("{} is not available at this level", word));
         }
-
-        let word = word.to_lowercase();
-        if let Some(var_name) = word.strip_prefix(":") {
-            if !state.vars.contains_key(var_name) {
-                return Err("No such variable".to_string());
+        let f = fun.f.clone();
+        let mut args = Vec::with_capacity(fun.args as usize);
+        for _ in 0..fun.args {
+            let arg = execute_expr(state, it)?;
+            match arg {
+                Some(arg) => args.push(arg),
+                None => return Err(format!("Missing argument for {}", word))
             }
-            return Ok(Some(state.vars[var_name].clone()));
         }
+        return call_primitive(state, &word, f, args);
+    }
 
-        let fun = state.functions.get(word.as_str());
-        if let Some(fun) = fun {
-            let f = fun.f.clone();
-            let mut args = Vec::with_capacity(fun.args as usize);
-            for _ in 0..fun.args {
-                let arg = execute_expr(state, it)?;
-                match arg {
-                    Some(arg) => args.push(arg),
-                    None => return Err(format!("Missing argument for {}", word))
+    if let Some(logo_proc) = state.logo_procedures.get_symbol(symbol) {
+        let is_macro = logo_proc.is_macro;
+        let code = compile_procedure(state, logo_proc)?;
+        let backup = backup_vars(state, &code.params);
+        for idx in 0..backup.len() {
+            let expr_result = match execute_expr(state, it) {
+                Ok(Some(value)) => value,
+                Ok(None) => {
+                    restore_vars(state, backup);
+                    return Err(format!("Missing argument for {}", word));
+                },
+                Err(err) => {
+                    restore_vars(state, backup);
+                    return Err(err);
                 }
+            };
+            if let Err(err) = state.set_input(backup[idx].0, expr_result) {
+                restore_vars(state, backup);
+                return Err(err);
             }
-            return (f)(state, args);
         }
+        return run_procedure(state, &word, &code, is_macro, backup);
+    }
+    Err(format!("Don't know what to do with {}", cmd.0))
+}
 
-        if let Some(logo_proc) = state.logo_procedures.get(word.as_str()) {
-            let logo_proc = logo_proc.clone();
-            let backup = backup_vars(state, &logo_proc.arg_names);
-            for arg_name in &logo_proc.arg_names {
-                let expr_result = execute_expr(state, it);
-                if let Err(_) = &expr_result {
-                    restore_vars(state, backup);
-                    return Err(expr_result.err().unwrap());
-                }
-                let expr_result = expr_result.unwrap();
-                if expr_result.is_none() {
-                    restore_vars(state, backup);
-                    return Err(format!("Missing argument for {}", word));
-                }
-                state.vars.insert(arg_name.clone(), expr_result.unwrap());
+/// Runs the code of a procedure whose inputs are already set, then restores the variables from `backup`.
+/// Macros run the list they output afterwards, with variables of the caller.
+fn run_procedure<S>(state: &mut EState<S>, word: &str, code: &Code, is_macro: bool,
+                    backup: Vec<(Symbol, Option<LogoValue>)>) -> Result<Option<LogoValue>, String> {
+    let profiling = state.profiler.is_enabled();
+    if profiling {
+        state.profiler.enter(word, false);
+    }
+    let proc_result = execute_ops(state, &code.ops);
+    if profiling {
+        state.profiler.exit();
+    }
+    restore_vars(state, backup);
+    let output = match proc_result {
+        Err(err) => {
+            if err != "Output" {
+                return Err(locate_error(state, word, err));
             }
-            let proc_result = execute(state, logo_proc.code);
-            restore_vars(state, backup);
-            return match proc_result {
-                Err(err) => {
-                    if err == "Output" {
-                        let output = state.output.clone();
-                        state.output = None;
-                        return Ok(output);
-                    }
-                    Err(err)
-                },
-                Ok(()) => Ok(None)
+            state.output.take()
+        },
+        Ok(()) => None
+    };
+    if !is_macro {
+        return Ok(output);
+    }
+    match output {
+        Some(LogoValue::List(expansion)) => run_expansion(state, &expansion),
+        _ => Err(format!("Macro {} didn't output a list", word))
+    }
+}
+
+/// Tells the file and line of a loaded procedure the error happened in, errors located in a procedure
+/// it called are kept as they are.
+fn locate_error<S>(state: &EState<S>, word: &str, err: String) -> String {
+    match state.procedure_lines.get(&word.to_lowercase()) {
+        Some((path, line)) if !err.starts_with("In ") => {
+            format!("In {}, line {}, {}: {}", path, line, word.to_lowercase(), state.locale.translate(&err))
+        },
+        _ => err
+    }
+}
+
+/// Runs the output of a macro in place of its call, the last expression gives the value of the call.
+pub(crate) fn run_expansion<S>(state: &mut EState<S>, expansion: &LogoList) -> Result<Option<LogoValue>, String> {
+    let code = compile(state, expansion)?;
+    let mut it = code.ops.iter().peekable();
+    while it.peek().is_some() {
+        if let Some(val) = execute_expr(state, &mut it)? {
+            if it.peek().is_some() {
+                return Err(format!("Don't know what to do with {}", val));
             }
+            return Ok(Some(val));
+        }
+    }
+    Ok(None)
+}
+
+/// Calls a procedure with inputs that are already evaluated, the name is used for errors and profiling.
+pub fn call_procedure<S>(state: &mut EState<S>, word: &str, logo_proc: &LogoProcedure,
+                         args: Vec<LogoValue>) -> Result<Option<LogoValue>, String> {
+    if args.len() < logo_proc.arg_names.len() {
+        return Err(format!("Not enough inputs to {}", word));
+    }
+    if args.len() > logo_proc.arg_names.len() {
+        return Err(format!("Too many inputs to {}", word));
+    }
+    let code = compile_procedure(state, logo_proc)?;
+    let backup = backup_vars(state, &code.params);
+    for ((symbol, _), arg) in backup.iter().zip(args) {
+        if let Err(err) = state.set_input(*symbol, arg) {
+            restore_vars(state, backup);
+            return Err(err);
+        }
+    }
+    run_procedure(state, word, &code, logo_proc.is_macro, backup)
+}
+
+/// Calls a primitive or a procedure by name with inputs that are already evaluated.
+pub fn call<S>(state: &mut EState<S>, word: &str, args: Vec<LogoValue>) -> Result<Option<LogoValue>, String> {
+    let word = word.to_lowercase();
+    if let Some(fun) = state.get_function(&word) {
+        if !state.is_allowed(&word, fun) {
+            return Err(format!("{} is not available at this level", word));
         }
-        return Err(format!("Don't know what to do with {}", cmd))
+        if args.len() < fun.min_args() {
+            return Err(format!("Not enough inputs to {}", word));
+        }
+        if fun.max_args().is_some_and(|max_args| args.len() > max_args) {
+            return Err(format!("Too many inputs to {}", word));
+        }
+        let f = fun.f.clone();
+        return call_primitive(state, &word, f, args);
+    }
+    if let Some(logo_proc) = state.logo_procedures.get(&word) {
+        let logo_proc = logo_proc.clone();
+        return call_procedure(state, &word, &logo_proc, args);
     }
+    Err(format!("Don't know what to do with {}", word))
+}
 
-    return Ok(Some(cmd.clone()));
+fn execute_paren_call<S>(state: &mut EState<S>, it: &mut Peekable<Iter<Op>>) -> Result<Option<LogoValue>, String> {
+    let symbol = match it.next() {
+        Some(Op::Call(symbol, _)) => *symbol,
+        _ => return Err("Missing corresponding closing bracket for '('".to_string())
+    };
+    let word = state.symbols.borrow().name(symbol);
+    let (f, min_args, max_args) = match state.get_function_symbol(symbol) {
+        Some(fun) if !state.is_allowed(&word, fun) => return Err(format!("{} is not available at this level", word)),
+        Some(fun) => (fun.f.clone(), fun.min_args(), fun.max_args()),
+        None => return Err(format!("Don't know what to do with {}", word))
+    };
+    let mut args = Vec::new();
+    while it.peek().is_some_and(|op| !matches!(op, Op::Close)) {
+        match execute_expr(state, it)? {
+            Some(arg) => args.push(arg),
+            None => return Err(format!("Missing argument for {}", word))
+        }
+    }
+    it.next();
+    if args.len() < min_args {
+        return Err(format!("Not enough inputs to {}", word));
+    }
+    if max_args.is_some_and(|max_args| args.len() > max_args) {
+        return Err(format!("Too many inputs to {}", word));
+    }
+    call_primitive(state, &word, f, args)
 }
 
-fn backup_vars<S>(state: &EState<S>, var_names: &Vec<String>) -> Vec<(String, Option<LogoValue>)> {
-    let mut result = Vec::with_capacity(var_names.len());
-    for var_name in var_names {
-        let val = state.vars.get(var_name);
-        match val {
-            Some(val) => result.push((var_name.clone(), Some(val.clone()))),
-            None => result.push((var_name.clone(), None))
+fn call_primitive<S>(state: &mut EState<S>, word: &str, f: PrimitiveFn<S>, args: Vec<LogoValue>) -> Result<Option<LogoValue>, String> {
+    let result = match state.profiler.is_enabled() {
+        false => (f)(state, args),
+        true => {
+            state.profiler.enter(word, true);
+            let result = (f)(state, args);
+            state.profiler.exit();
+            result
+        }
+    };
+    if let Ok(Some(value)) = &result {
+        if !state.limits.is_unlimited() {
+            state.limits.check_value(value)?;
         }
     }
     result
 }
 
-fn restore_vars<S>(state: &mut EState<S>, backup: Vec<(String, Option<LogoValue>)>) {
-    for (var_name, val) in backup {
-        match val {
-            Some(val) => {
-                state.vars.insert(var_name, val);
-            },
-            None => {
-                state.vars.remove(var_name.as_str());
-            }
+fn backup_vars<S>(state: &EState<S>, params: &[(String, Symbol)]) -> Vec<(Symbol, Option<LogoValue>)> {
+    params.iter().map(|(_, symbol)| (*symbol, state.vars.get_symbol(*symbol).cloned())).collect()
+}
+
+fn restore_vars<S>(state: &mut EState<S>, backup: Vec<(Symbol, Option<LogoValue>)>) {
+    for (symbol, val) in backup {
+        state.restore_var(symbol, val);
+    }
+}
+
+/// A value that can take part in infix math processing, implemented both for plain and for spanned values.
+pub(crate) trait MathToken: Sized {
+    fn math_word(&self) -> Option<&str>;
+    fn math_operator(&self, name: &str) -> Self;
+}
+
+impl MathToken for LogoValue {
+    fn math_word(&self) -> Option<&str> {
+        match self {
+            LogoValue::Word(word) => Some(word.0.as_str()),
+            _ => None
+        }
+    }
+
+    fn math_operator(&self, name: &str) -> Self {
+        LogoValue::Word(Word(name.to_string()))
+    }
+}
+
+impl MathToken for SpannedValue {
+    fn math_word(&self) -> Option<&str> {
+        match self {
+            SpannedValue::Word(word, _) => Some(word.0.as_str()),
+            _ => None
         }
     }
+
+    fn math_operator(&self, name: &str) -> Self {
+        SpannedValue::Word(Word(name.to_string()), self.span())
+    }
 }
 
-fn math_transform(source: Vec<LogoValue>) -> Result<Vec<LogoValue>, String> {
+/// Turns infix operators into prefix calls and removes parentheses, except the ones around calls
+/// of flexible primitives, which are left as `(` and `)` words.
+pub(crate) fn math_transform<T: MathToken>(source: Vec<T>, flexible: &dyn Fn(&str) -> bool) -> Result<Vec<T>, String> {
     let mut tree = BracketTree::parse(source)?;
     process_math_signs(&mut tree, &HashMap::from([
         ("*".to_string(), "product".to_string()),
@@ -132,28 +372,29 @@ fn math_transform(source: Vec<LogoValue>) -> Result<Vec<LogoValue>, String> {
         ("<".to_string(), "less?".to_string()),
         ("=".to_string(), "equal?".to_string()),
     ]))?;
-    Ok(tree.to_list())
+    Ok(tree.flatten(flexible))
 }
 
-fn process_math_signs(tree: &mut BracketTree, signs: &HashMap<String, String>) -> Result<(), String> {
-    tree.process(&|nodes: Vec<BracketTreeChild>| -> Result<Vec<BracketTreeChild>, String> {
+fn process_math_signs<T: MathToken>(tree: &mut BracketTree<T>, signs: &HashMap<String, String>) -> Result<(), String> {
+    tree.process(&|nodes: Vec<BracketTreeChild<T>>| -> Result<Vec<BracketTreeChild<T>>, String> {
         let mut result = Vec::new();
         let mut it = nodes.into_iter();
         while let Some(node) = it.next() {
             if let BracketTreeChild::Value(val) = &node {
-                if let LogoValue::Word(word) = val {
-                    if let Some(sing_op) = signs.get(word.0.as_str()) {
+                if let Some(word) = val.math_word() {
+                    if let Some(sing_op) = signs.get(word) {
                         let prev = match result.pop() {
                             Some(val) => val,
-                            None => return Err(format!("Missing first argument for {}", word.0))
+                            None => return Err(format!("Missing first argument for {}", word))
                         };
                         let next = match it.next() {
                             Some(val) => val,
-                            None => return Err(format!("Missing second argument for {}", word.0))
+                            None => return Err(format!("Missing second argument for {}", word))
                         };
                         let subtree = BracketTree{
+                            paren: false,
                             children: vec![
-                                BracketTreeChild::Value(LogoValue::Word(Word(sing_op.clone()))),
+                                BracketTreeChild::Value(val.math_operator(sing_op)),
                                 prev,
                                 next
                             ]
@@ -169,30 +410,31 @@ fn process_math_signs(tree: &mut BracketTree, signs: &HashMap<String, String>) -
     })
 }
 
-struct BracketTree {
-    children: Vec<BracketTreeChild>
+struct BracketTree<T> {
+    paren: bool,
+    children: Vec<BracketTreeChild<T>>
 }
 
-enum BracketTreeChild {
-    Value(LogoValue),
-    Tree(Box<BracketTree>)
+enum BracketTreeChild<T> {
+    Value(T),
+    Tree(Box<BracketTree<T>>)
 }
 
-impl BracketTree {
-    fn new() -> Self {
-        BracketTree {children: Vec::new()}
+impl<T: MathToken> BracketTree<T> {
+    fn new(paren: bool) -> Self {
+        BracketTree {paren, children: Vec::new()}
     }
 
-    fn parse(list: Vec<LogoValue>) -> Result<Self, String> {
+    fn parse(list: Vec<T>) -> Result<Self, String> {
         let mut stack = Vec::new();
-        stack.push(BracketTree::new());
+        stack.push(BracketTree::new(false));
         for el in list {
-            if let LogoValue::Word(word) = &el {
-                if word.0 == "(" {
-                    stack.push(BracketTree::new());
+            if let Some(word) = el.math_word() {
+                if word == "(" {
+                    stack.push(BracketTree::new(true));
                     continue;
                 }
-                else if word.0 == ")" {
+                else if word == ")" {
                     if stack.len() == 1 {
                         return Err("Missing corresponding opening bracket for ')'".to_string());
                     }
@@ -209,26 +451,43 @@ impl BracketTree {
         Ok(stack.pop().unwrap())
     }
 
-    fn into_list(self, list: &mut Vec<LogoValue>) {
+    fn into_list(self, list: &mut Vec<T>, flexible: &dyn Fn(&str) -> bool) {
+        let call = match self.children.first() {
+            Some(BracketTreeChild::Value(val)) if self.paren => val.math_word().is_some_and(flexible),
+            _ => false
+        };
+        let closing = match call {
+            true => match self.children.first() {
+                Some(BracketTreeChild::Value(val)) => {
+                    list.push(val.math_operator("("));
+                    Some(val.math_operator(")"))
+                },
+                _ => None
+            },
+            false => None
+        };
         for child in self.children {
             match child {
                 BracketTreeChild::Value(val) => {
                     list.push(val)
                 },
                 BracketTreeChild::Tree(tree) => {
-                    tree.into_list(list);
+                    tree.into_list(list, flexible);
                 }
             }
         }
+        if let Some(closing) = closing {
+            list.push(closing);
+        }
     }
 
-    fn to_list(self) -> Vec<LogoValue> {
+    fn flatten(self, flexible: &dyn Fn(&str) -> bool) -> Vec<T> {
         let mut result = Vec::new();
-        self.into_list(&mut result);
+        self.into_list(&mut result, flexible);
         result
     }
 
-    fn process(&mut self, f: &impl Fn(Vec<BracketTreeChild>) -> Result<Vec<BracketTreeChild>, String>) -> Result<(), String> {
+    fn process(&mut self, f: &impl Fn(Vec<BracketTreeChild<T>>) -> Result<Vec<BracketTreeChild<T>>, String>) -> Result<(), String> {
         let mut tmp = Vec::new();
         swap(&mut tmp, &mut self.children);
         self.children = f(tmp)?;
@@ -250,7 +509,7 @@ fn test_execution() {
     }
     let mut state = EState::new(S{total: 0});
     add_stdlib(&mut state);
-    state.functions.insert("add".to_string(), Function::from_proc1(|s: &mut EState<S>, x: i32| -> Result<(), String> {
+    state.functions.insert("add", Function::from_proc1(|s: &mut EState<S>, x: i32| -> Result<(), String> {
         s.state.total += x;
         Ok(())
     }));
@@ -268,6 +527,27 @@ fn test_execution() {
         to double :x output sum :x :x end",
  "add4 add_double 6 add double 3").unwrap();
     assert_eq!(state.state.total, 22);
+
+    // Lists keep their transformed instructions until primitives change
+    state.state.total = 0;
+    let body: LogoList = parser::parse("add 1 + 2").unwrap().into();
+    execute(&mut state, &body).unwrap();
+    let code = body.code().unwrap();
+    execute(&mut state, &body).unwrap();
+    assert!(Shared::ptr_eq(&code.ops, &body.code().unwrap().ops));
+    state.functions.insert("sub", Function::from_proc1(|s: &mut EState<S>, x: i32| -> Result<(), String> {
+        s.state.total -= x;
+        Ok(())
+    }));
+    execute(&mut state, &body).unwrap();
+    assert!(!Shared::ptr_eq(&code.ops, &body.code().unwrap().ops));
+    assert_eq!(state.state.total, 9);
+
+    // Only names in code that runs are interned, words of data lists aren't
+    execute_str(&mut state, "", "make 'data' [alpha [beta]] add count :data").unwrap();
+    assert!(state.symbols.borrow().get("alpha").is_none());
+    assert!(state.symbols.borrow().get("beta").is_none());
+    assert!(state.symbols.borrow().get("data").is_some());
 }
 
 #[test]
@@ -279,7 +559,7 @@ fn test_execution_math() {
     }
     let mut state = EState::new(S{result: 0});
     add_stdlib(&mut state);
-    state.functions.insert("return".to_string(), Function::from_proc1(|s: &mut EState<S>, x: i32| -> Result<(), String> {
+    state.functions.insert("return", Function::from_proc1(|s: &mut EState<S>, x: i32| -> Result<(), String> {
         s.state.result = x;
         Ok(())
     }));
@@ -318,26 +598,45 @@ fn test_execution_comparison() {
     }
     let mut state = EState::new(S{result: false});
     add_stdlib(&mut state);
-    state.functions.insert("return".to_string(), Function::from_proc1(|s: &mut EState<S>, x: bool| -> Result<(), String> {
+    state.functions.insert("return", Function::from_proc1(|s: &mut EState<S>, x: bool| -> Result<(), String> {
         s.state.result = x;
         Ok(())
     }));
 
     execute_str(&mut state, "", "return 2 = 2").unwrap();
-    assert_eq!(state.state.result, true);
+    assert!(state.state.result);
 
     execute_str(&mut state, "", "return 2 = 4 / 2").unwrap();
-    assert_eq!(state.state.result, true);
+    assert!(state.state.result);
 
     execute_str(&mut state, "", "return 1 = pi / pi").unwrap();
-    assert_eq!(state.state.result, true);
+    assert!(state.state.result);
 
     execute_str(&mut state, "", "return 1/3 = 2/6").unwrap();
-    assert_eq!(state.state.result, true);
+    assert!(state.state.result);
 
     execute_str(&mut state, "", "return 1/4 < 1/5").unwrap();
-    assert_eq!(state.state.result, false);
+    assert!(!state.state.result);
 
     execute_str(&mut state, "", "return (ln 1) > 0").unwrap();
-    assert_eq!(state.state.result, false);
+    assert!(!state.state.result);
+}
+
+#[test]
+fn test_macros() {
+    use crate::stdlib::add_stdlib;
+
+    let mut state = EState::new(());
+    add_stdlib(&mut state);
+    let procedures = ".macro while :cond :body\n\
+                      output (list [if] :cond fput (list :body [while] fput :cond [] fput :body []) [])\nend\n\
+                      .macro plus.one :expr\noutput list :expr [+ 1]\nend\n\
+                      to count.up :limit\nmake 'body' 0 while [:body < :limit] [make 'body' :body + 1] output :body\nend";
+    execute_str(&mut state, procedures, "make 'a' count.up 5 make 'b' plus.one [2 * 3] \
+                                        .defmacro 'twice' [[x] [output list :x :x]] make 'n' 1 twice [make 'n' :n * 10]").unwrap();
+    assert_eq!(state.vars["a"], LogoValue::Word(Word("5".to_string())));
+    assert_eq!(state.vars["b"], LogoValue::Word(Word("7".to_string())));
+    assert_eq!(state.vars["n"], LogoValue::Word(Word("100".to_string())));
+    assert!(state.logo_procedures["while"].is_macro);
+    assert_eq!(execute_str(&mut state, ".macro bad\noutput 1\nend", "bad"), Err("Macro bad didn't output a list".to_string()));
 }
@@ -1,100 +1,431 @@
 use std::collections::HashMap;
-use std::rc::Rc;
+use std::vec::IntoIter;
 use crate::core::*;
+use crate::dialect::Dialect;
+use crate::docs::Doc;
+use crate::library::InstalledLibrary;
+use crate::limits::{Limits, Usage};
+use crate::locale::Locale;
+use crate::policy::{Category, Policy};
+use crate::vfs::{FileSystem, MemoryFileSystem};
+use crate::workspace::StateSection;
+use crate::processes::{Scheduler, Turtles};
+use crate::events::Events;
+use crate::profiler::Profiler;
+use crate::symbols::{SharedInterner, Symbol, SymbolTable};
+use crate::shared::{MaybeSync, Shared};
 
 
+#[cfg(not(feature = "sync"))]
+pub type PrimitiveFn<S> = Shared<dyn Fn(&mut EState<S>, Vec<LogoValue>) -> Result<Option<LogoValue>, String>>;
+#[cfg(feature = "sync")]
+pub type PrimitiveFn<S> = Shared<dyn Fn(&mut EState<S>, Vec<LogoValue>) -> Result<Option<LogoValue>, String> + Send + Sync>;
+
 #[derive(Clone)]
 pub struct Function<S> {
-    pub f: Rc<dyn Fn(&mut EState<S>, Vec<LogoValue>) -> Result<Option<LogoValue>, String>>,
-    pub args: i32
+    pub f: PrimitiveFn<S>,
+    /// Number of inputs taken when the call isn't enclosed in parentheses.
+    pub args: i32,
+    pub name: String,
+    pub params: Vec<Param>,
+    pub help: String,
+    pub category: Category,
+    /// Documentation of the library, see `attach_docs`.
+    pub doc: Option<&'static Doc>
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParamKind {
+    Required,
+    Optional,
+    Rest
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    pub name: String,
+    pub kind: ParamKind
+}
+
+/// All inputs left after the other ones, they're given in a call like `(sum 1 2 3 4)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rest<T>(pub Vec<T>);
+
+/// Type of a host function input. `Option<T>` inputs may be omitted and `Rest<T>` collects the remaining ones,
+/// both only can be given when the call is enclosed in parentheses.
+pub trait LogoParam: Sized {
+    const KIND: ParamKind;
+    /// Takes the input from the call arguments, on failure returns the value that doesn't fit.
+    fn take(args: &mut IntoIter<LogoValue>) -> Result<Self, LogoValue>;
+}
+
+impl<T: LogoConvertible> LogoParam for T {
+    const KIND: ParamKind = ParamKind::Required;
+    fn take(args: &mut IntoIter<LogoValue>) -> Result<Self, LogoValue> {
+        let value = args.next().unwrap();
+        T::from_logo(value.clone()).map_err(|_| value)
+    }
+}
+
+impl<T: LogoConvertible> LogoParam for Option<T> {
+    const KIND: ParamKind = ParamKind::Optional;
+    fn take(args: &mut IntoIter<LogoValue>) -> Result<Self, LogoValue> {
+        match args.next() {
+            Some(value) => T::from_logo(value.clone()).map(Some).map_err(|_| value),
+            None => Ok(None)
+        }
+    }
+}
+
+impl<T: LogoConvertible> LogoParam for Rest<T> {
+    const KIND: ParamKind = ParamKind::Rest;
+    fn take(args: &mut IntoIter<LogoValue>) -> Result<Self, LogoValue> {
+        let mut result = Vec::new();
+        for value in args {
+            result.push(T::from_logo(value.clone()).map_err(|_| value)?);
+        }
+        Ok(Rest(result))
+    }
+}
+
+/// Result of a host function, `()` for commands that output nothing.
+pub trait LogoOutput {
+    fn into_output(self) -> Option<LogoValue>;
 }
 
+impl LogoOutput for () {
+    fn into_output(self) -> Option<LogoValue> {
+        None
+    }
+}
+
+/// For primitives which output a value only sometimes, like `apply`.
+impl LogoOutput for Option<LogoValue> {
+    fn into_output(self) -> Option<LogoValue> {
+        self
+    }
+}
+
+impl<T: LogoConvertible> LogoOutput for T {
+    fn into_output(self) -> Option<LogoValue> {
+        Some(self.to_logo())
+    }
+}
+
+/// Implemented for functions and closures taking `&mut EState<S>` and any number of `LogoParam` inputs.
+/// `Marker` is a tuple of the output and input types, it only tells implementations apart.
+pub trait IntoFunction<S, Marker> {
+    fn param_kinds() -> Vec<ParamKind>;
+    fn into_callable(self, name: String, params: Vec<String>)
+        -> PrimitiveFn<S>;
+}
+
+macro_rules! impl_into_function {
+    ($($t:ident $idx:tt),*) => {
+        impl<S, F, Out, $($t),*> IntoFunction<S, (Out, $($t,)*)> for F
+        where F: Fn(&mut EState<S>, $($t),*) -> Result<Out, String> + MaybeSync + 'static,
+              Out: LogoOutput, $($t: LogoParam),*
+        {
+            fn param_kinds() -> Vec<ParamKind> {
+                vec![$($t::KIND),*]
+            }
+
+            #[allow(unused_variables, unused_mut)]
+            fn into_callable(self, name: String, params: Vec<String>)
+                -> PrimitiveFn<S> {
+                return Shared::new(move |state: &mut EState<S>, args: Vec<LogoValue>| -> Result<Option<LogoValue>, String> {
+                    let mut args = args.into_iter();
+                    let inputs = ($(
+                        $t::take(&mut args).map_err(|value| {
+                            format!("{} doesn't like {} as input {}", name, value, params[$idx])
+                        })?,
+                    )*);
+                    return Ok(self(state, $(inputs.$idx),*)?.into_output());
+                });
+            }
+        }
+    };
+}
+
+impl_into_function!();
+impl_into_function!(T1 0);
+impl_into_function!(T1 0, T2 1);
+impl_into_function!(T1 0, T2 1, T3 2);
+impl_into_function!(T1 0, T2 1, T3 2, T4 3);
+impl_into_function!(T1 0, T2 1, T3 2, T4 3, T5 4);
+impl_into_function!(T1 0, T2 1, T3 2, T4 3, T5 4, T6 5);
+impl_into_function!(T1 0, T2 1, T3 2, T4 3, T5 4, T6 5, T7 6);
+impl_into_function!(T1 0, T2 1, T3 2, T4 3, T5 4, T6 5, T7 6, T8 7);
+
 pub struct EState<S> {
-    pub functions: HashMap<String, Function<S>>,
-    pub logo_procedures: HashMap<String, LogoProcedure>,
-    pub vars: HashMap<String, LogoValue>,
+    /// Names of primitives, procedures and variables, shared with the tables below.
+    pub symbols: SharedInterner,
+    // The tables were `HashMap`s, they have the same methods and `to_map` gives a `HashMap` copy
+    pub functions: SymbolTable<Function<S>>,
+    pub logo_procedures: SymbolTable<LogoProcedure>,
+    pub vars: SymbolTable<LogoValue>,
     pub output: Option<LogoValue>,
+    pub profiler: Profiler,
+    pub locale: Locale,
+    pub dialect: Dialect,
+    pub policy: Policy,
+    pub limits: Limits,
+    /// Memory variables hold, checked against `limits` whenever one is set.
+    pub(crate) usage: Usage,
+    /// Property lists by name, properties keep the order they were added in.
+    pub plists: HashMap<String, Vec<(String, LogoValue)>>,
+    pub files: Box<dyn FileSystem>,
+    /// Procedures loaded from workspace files, programs are run with them like with library procedures.
+    pub loaded_procedures: HashMap<String, LogoProcedure>,
+    /// Text of files loaded by `load_file`, by path and namespace.
+    pub(crate) loaded_files: HashMap<(String, Option<String>), String>,
+    /// File and line of the title of procedures loaded by `load_file`, runtime errors in them tell it.
+    pub(crate) procedure_lines: HashMap<String, (String, usize)>,
+    /// Processes started by `launch` and `forever`, they keep running between programs.
+    pub scheduler: Scheduler,
+    /// Turtles of the host processes drive, set with `set_turtles`.
+    pub(crate) turtles: Option<Turtles<S>>,
+    /// Handlers of keys and clicks, timers and conditions of `when`.
+    pub events: Events,
+    pub(crate) libraries: Vec<InstalledLibrary<S>>,
+    pub(crate) state_sections: Vec<StateSection<S>>,
     pub state: S
 }
 
+fn unnamed_params(count: usize) -> Vec<Param> {
+    (1..=count).map(|idx| Param {name: format!("input{}", idx), kind: ParamKind::Required}).collect()
+}
+
 impl<S: 'static> Function<S> {
+    /// Creates a function from a closure with typed inputs, `params` are names of the inputs.
+    /// Inputs after the required ones are only given in parentheses, like `(name 1 2 3)`.
+    pub fn new<F: IntoFunction<S, Marker>, Marker>(name: &str, params: &[&str], f: F) -> Self {
+        let kinds = F::param_kinds();
+        assert_eq!(kinds.len(), params.len(), "{} takes {} inputs", name, kinds.len());
+        let params: Vec<Param> = params.iter().zip(kinds)
+            .map(|(param, kind)| Param {name: param.to_string(), kind})
+            .collect();
+        let args = params.iter().filter(|x| x.kind == ParamKind::Required).count() as i32;
+        let f = f.into_callable(name.to_string(), params.iter().map(|x| x.name.clone()).collect());
+        Function{f, args, name: name.to_string(), params, help: String::new(), category: Category::Other, doc: None}
+    }
+
+    /// Stands for a primitive of Native Logo that means something else in the dialect, a call reports that.
+    pub fn unavailable(name: &str, params: &[&str], dialect: Dialect) -> Self {
+        let message = format!("{} is not available in {:?}", name, dialect);
+        let params: Vec<Param> = params.iter()
+            .map(|param| Param {name: param.to_string(), kind: ParamKind::Required})
+            .collect();
+        Function{f: Shared::new(move |_: &mut EState<S>, _: Vec<LogoValue>| -> Result<Option<LogoValue>, String> {
+            Err(message.clone())
+        }), args: params.len() as i32, name: name.to_string(), params, help: String::new(), category: Category::Other, doc: None}
+    }
+
+    pub fn with_help(mut self, help: &str) -> Self {
+        self.help = help.to_string();
+        self
+    }
+
+    pub fn with_category(mut self, category: Category) -> Self {
+        self.category = category;
+        self
+    }
+
     pub fn from_proc(f: fn(&mut EState<S>) -> Result<(), String>) -> Self {
-        return Function{f: Rc::new(
+        Function{f: Shared::new(
             move |state: &mut EState<S>, _: Vec<LogoValue>| -> Result<Option<LogoValue>, String> {
                 f(state)?;
-                return Ok(None);
-        }), args: 0};
+                Ok(None)
+        }), args: 0, name: String::new(), params: unnamed_params(0), help: String::new(), category: Category::Other, doc: None}
     }
     pub fn from_fn<Out: LogoConvertible + 'static>(f: fn(&mut EState<S>) -> Result<Out, String>) -> Self {
-        return Function{f: Rc::new(move |state: &mut EState<S>, _: Vec<LogoValue>| -> Result<Option<LogoValue>, String> {
-            return Ok(Some(f(state)?.to_logo()));
-        }), args: 0};
+        Function{f: Shared::new(move |state: &mut EState<S>, _: Vec<LogoValue>| -> Result<Option<LogoValue>, String> {
+            Ok(Some(f(state)?.to_logo()))
+        }), args: 0, name: String::new(), params: unnamed_params(0), help: String::new(), category: Category::Other, doc: None}
     }
 
     pub fn from_proc1<T1: LogoConvertible + 'static>
     (f: fn(&mut EState<S>, T1) -> Result<(), String>) -> Self {
-        return Function{f: Rc::new(move |state: &mut EState<S>, mut args: Vec<LogoValue>| -> Result<Option<LogoValue>, String> {
+        Function{f: Shared::new(move |state: &mut EState<S>, mut args: Vec<LogoValue>| -> Result<Option<LogoValue>, String> {
             let arg1 = T1::from_logo(args.pop().unwrap())?;
             f(state, arg1)?;
-            return Ok(None);
-        }), args: 1};
+            Ok(None)
+        }), args: 1, name: String::new(), params: unnamed_params(1), help: String::new(), category: Category::Other, doc: None}
     }
     pub fn from_fn1<T1: LogoConvertible + 'static, Out: LogoConvertible + 'static>
     (f: fn(&mut EState<S>, T1) -> Result<Out, String>) -> Self {
-        return Function{f: Rc::new(move |state: &mut EState<S>, mut args: Vec<LogoValue>| -> Result<Option<LogoValue>, String> {
+        Function{f: Shared::new(move |state: &mut EState<S>, mut args: Vec<LogoValue>| -> Result<Option<LogoValue>, String> {
             let arg1 = T1::from_logo(args.pop().unwrap())?;
-            return Ok(Some(f(state, arg1)?.to_logo()));
-        }), args: 1};
+            Ok(Some(f(state, arg1)?.to_logo()))
+        }), args: 1, name: String::new(), params: unnamed_params(1), help: String::new(), category: Category::Other, doc: None}
     }
 
     pub fn from_proc2<T1: LogoConvertible + 'static, T2: LogoConvertible + 'static>
     (f: fn(&mut EState<S>, T1, T2) -> Result<(), String>) -> Self {
-        return Function{f: Rc::new(move |state: &mut EState<S>, mut args: Vec<LogoValue>| -> Result<Option<LogoValue>, String> {
+        Function{f: Shared::new(move |state: &mut EState<S>, mut args: Vec<LogoValue>| -> Result<Option<LogoValue>, String> {
             let arg2 = T2::from_logo(args.pop().unwrap())?;
             let arg1 = T1::from_logo(args.pop().unwrap())?;
             f(state, arg1, arg2)?;
-            return Ok(None);
-        }), args: 2};
+            Ok(None)
+        }), args: 2, name: String::new(), params: unnamed_params(2), help: String::new(), category: Category::Other, doc: None}
     }
     pub fn from_fn2<T1: LogoConvertible + 'static, T2: LogoConvertible + 'static, Out: LogoConvertible + 'static>
     (f: fn(&mut EState<S>, T1, T2) -> Result<Out, String>) -> Self {
-        return Function{f: Rc::new(move |state: &mut EState<S>, mut args: Vec<LogoValue>| -> Result<Option<LogoValue>, String> {
+        Function{f: Shared::new(move |state: &mut EState<S>, mut args: Vec<LogoValue>| -> Result<Option<LogoValue>, String> {
             let arg2 = T2::from_logo(args.pop().unwrap())?;
             let arg1 = T1::from_logo(args.pop().unwrap())?;
-            return Ok(Some(f(state, arg1, arg2)?.to_logo()));
-        }), args: 2};
+            Ok(Some(f(state, arg1, arg2)?.to_logo()))
+        }), args: 2, name: String::new(), params: unnamed_params(2), help: String::new(), category: Category::Other, doc: None}
     }
 
     pub fn from_proc3<T1: LogoConvertible + 'static, T2: LogoConvertible + 'static, T3: LogoConvertible + 'static>
     (f: fn(&mut EState<S>, T1, T2, T3) -> Result<(), String>) -> Self {
-        return Function{f: Rc::new(move |state: &mut EState<S>, mut args: Vec<LogoValue>| -> Result<Option<LogoValue>, String> {
+        Function{f: Shared::new(move |state: &mut EState<S>, mut args: Vec<LogoValue>| -> Result<Option<LogoValue>, String> {
             let arg3 = T3::from_logo(args.pop().unwrap())?;
             let arg2 = T2::from_logo(args.pop().unwrap())?;
             let arg1 = T1::from_logo(args.pop().unwrap())?;
             f(state, arg1, arg2, arg3)?;
-            return Ok(None);
-        }), args: 3};
+            Ok(None)
+        }), args: 3, name: String::new(), params: unnamed_params(3), help: String::new(), category: Category::Other, doc: None}
     }
     pub fn from_fn3<T1: LogoConvertible + 'static, T2: LogoConvertible + 'static, T3: LogoConvertible + 'static, Out: LogoConvertible + 'static>
     (f: fn(&mut EState<S>, T1, T2, T3) -> Result<Out, String>) -> Self {
-        return Function{f: Rc::new(move |state: &mut EState<S>, mut args: Vec<LogoValue>| -> Result<Option<LogoValue>, String> {
+        Function{f: Shared::new(move |state: &mut EState<S>, mut args: Vec<LogoValue>| -> Result<Option<LogoValue>, String> {
             let arg3 = T3::from_logo(args.pop().unwrap())?;
             let arg2 = T2::from_logo(args.pop().unwrap())?;
             let arg1 = T1::from_logo(args.pop().unwrap())?;
-            return Ok(Some(f(state, arg1, arg2, arg3)?.to_logo()));
-        }), args: 3};
+            Ok(Some(f(state, arg1, arg2, arg3)?.to_logo()))
+        }), args: 3, name: String::new(), params: unnamed_params(3), help: String::new(), category: Category::Other, doc: None}
+    }
+}
+
+impl<S> Function<S> {
+    pub fn min_args(&self) -> usize {
+        self.params.iter().filter(|x| x.kind == ParamKind::Required).count()
+    }
+
+    /// Returns `None` if any number of inputs is accepted.
+    pub fn max_args(&self) -> Option<usize> {
+        match self.params.iter().any(|x| x.kind == ParamKind::Rest) {
+            true => None,
+            false => Some(self.params.len())
+        }
+    }
+
+    /// Whether the inputs count differs when the call is enclosed in parentheses.
+    pub fn is_flexible(&self) -> bool {
+        self.max_args() != Some(self.args as usize)
     }
 }
 
 impl<S> EState<S> {
     pub fn new(state: S) -> Self {
-        return EState {
-            functions: HashMap::new(),
-            logo_procedures: HashMap::new(),
-            vars: HashMap::new(),
+        EState::with_dialect(state, Dialect::Native)
+    }
+
+    pub fn with_dialect(state: S, dialect: Dialect) -> Self {
+        let symbols = SharedInterner::default();
+        EState {
+            functions: SymbolTable::new(symbols.clone()),
+            logo_procedures: SymbolTable::new(symbols.clone()),
+            vars: SymbolTable::new(symbols.clone()),
+            symbols,
             output: None,
+            profiler: Profiler::new(),
+            locale: Locale::english(),
+            dialect,
+            policy: Policy::unrestricted(),
+            limits: Limits::unlimited(),
+            usage: Usage::default(),
+            plists: HashMap::new(),
+            files: Box::new(MemoryFileSystem::new()),
+            loaded_procedures: HashMap::new(),
+            loaded_files: HashMap::new(),
+            procedure_lines: HashMap::new(),
+            scheduler: Scheduler::new(),
+            turtles: None,
+            events: Events::new(),
+            libraries: Vec::new(),
+            state_sections: Vec::new(),
             state
+        }
+    }
+
+    /// Adds a primitive under its name.
+    pub fn add_function(&mut self, function: Function<S>) {
+        self.functions.insert(function.name.clone(), function);
+    }
+
+    /// Sets a variable, freeing the memory of its old value. Fails if variables would take more memory
+    /// than `limits` allow, memory is only counted when they set a limit.
+    pub(crate) fn set_var(&mut self, symbol: Symbol, value: LogoValue) -> Result<(), String> {
+        if self.limits.max_total_size.is_none() {
+            self.vars.insert_symbol(symbol, value);
+            return Ok(());
+        }
+        // The new value is counted first, so nodes it shares with the old one stay counted
+        self.usage.add(&value);
+        if let Some(old) = self.vars.get_symbol(symbol) {
+            self.usage.remove(old);
+        }
+        if let Err(err) = self.limits.check_usage(&self.usage) {
+            if let Some(old) = self.vars.get_symbol(symbol) {
+                self.usage.add(old);
+            }
+            self.usage.remove(&value);
+            return Err(err);
+        }
+        self.vars.insert_symbol(symbol, value);
+        Ok(())
+    }
+
+    /// Sets an input of a procedure. The variable it hides stays counted, the procedure puts it back after.
+    pub(crate) fn set_input(&mut self, symbol: Symbol, value: LogoValue) -> Result<(), String> {
+        if self.limits.max_total_size.is_some() {
+            self.usage.add(&value);
+            if let Err(err) = self.limits.check_usage(&self.usage) {
+                self.usage.remove(&value);
+                return Err(err);
+            }
+        }
+        self.vars.insert_symbol(symbol, value);
+        Ok(())
+    }
+
+    /// Puts back a variable an input hid, or removes the variable for `None`, freeing the memory of
+    /// the current value.
+    pub(crate) fn restore_var(&mut self, symbol: Symbol, value: Option<LogoValue>) {
+        let current = match value {
+            Some(value) => self.vars.insert_symbol(symbol, value),
+            None => self.vars.remove_symbol(symbol)
         };
+        if let Some(current) = current.filter(|_| self.limits.max_total_size.is_some()) {
+            self.usage.remove(&current);
+        }
+    }
+
+    /// Looks up a primitive by its name or by a localized alias of the current locale.
+    pub fn get_function(&self, name: &str) -> Option<&Function<S>> {
+        self.functions.get(self.locale.primitive_name(name))
+    }
+
+    /// Same as `get_function`, the locale is only consulted for names that aren't primitives themselves.
+    pub fn get_function_symbol(&self, symbol: Symbol) -> Option<&Function<S>> {
+        match self.functions.get_symbol(symbol) {
+            Some(function) => Some(function),
+            None => {
+                let name = self.symbols.borrow().name(symbol);
+                match self.locale.primitive_name(&name) {
+                    primitive if primitive != &*name => self.functions.get(primitive),
+                    _ => None
+                }
+            }
+        }
+    }
+
+    /// Whether the policy lets programs call the primitive, `name` may be a localized alias.
+    pub fn is_allowed(&self, name: &str, function: &Function<S>) -> bool {
+        self.policy.allows(self.locale.primitive_name(name), function.category)
     }
 }
 
@@ -105,9 +436,9 @@ fn test_executor_function() {
     let sum = |_: &mut EState<i32>, x: f64, y: f64| -> Result<f64, String> {
         Ok(x + y)
     };
-    state.functions.insert("sum".to_string(), Function::from_fn2(sum));
+    state.functions.insert("sum", Function::from_fn2(sum));
 
-    let sum_fn = state.functions[&"sum".to_string()].clone();
+    let sum_fn = state.functions["sum"].clone();
     assert_eq!(sum_fn.args, 2);
     let res = (sum_fn.f)(&mut state,
                          vec![LogoValue::Word(Word("2".to_string())), LogoValue::Word(Word("3".to_string()))]);
@@ -115,3 +446,35 @@ fn test_executor_function() {
     assert!(res.as_ref().unwrap().is_some());
     assert_eq!(res.unwrap().unwrap(), LogoValue::Word(Word("5".to_string())));
 }
+
+#[test]
+fn test_function_registration() {
+    use crate::executor::execute_str;
+    use crate::shared::SharedCell;
+    use crate::stdlib::add_stdlib;
+
+    let mut state = EState::new(());
+    add_stdlib(&mut state);
+    let log = Shared::new(SharedCell::new(Vec::new()));
+    let log_ref = log.clone();
+    state.add_function(Function::new("rect", &["x", "y", "width", "height", "filled", "color"],
+        move |_: &mut EState<()>, x: f64, y: f64, w: f64, h: f64, filled: Option<bool>, color: Rest<String>| -> Result<(), String> {
+            log_ref.borrow_mut().push(format!("{} {} {} {} {:?} {:?}", x, y, w, h, filled, color.0));
+            Ok(())
+        }).with_help("Draws a rectangle"));
+
+    let rect = &state.functions["rect"];
+    assert_eq!((rect.args, rect.min_args(), rect.max_args()), (4, 4, None));
+    assert_eq!(rect.params[4], Param {name: "filled".to_string(), kind: ParamKind::Optional});
+    assert_eq!(rect.help, "Draws a rectangle");
+
+    execute_str(&mut state, "", "rect 1 2 3 4 (rect 1 2 3 4 1 = 1 'red' 'blue') (rect 1 2 (sum 1 2 3) 4)").unwrap();
+    assert_eq!(*log.borrow(), vec![
+        "1 2 3 4 None []",
+        "1 2 3 4 Some(true) [\"red\", \"blue\"]",
+        "1 2 6 4 None []",
+    ]);
+    assert_eq!(execute_str(&mut state, "", "rect 1 [2] 3 4"), Err("rect doesn't like [2] as input y".to_string()));
+    assert_eq!(execute_str(&mut state, "", "(rect 1 2 3)"), Err("Not enough inputs to rect".to_string()));
+    assert_eq!(execute_str(&mut state, "", "(sum 1 2 3) * 2 + (product 2 3 4)"), Err("Don't know what to do with 36".to_string()));
+}
@@ -0,0 +1,625 @@
+use std::collections::{HashMap, HashSet};
+use std::iter::Peekable;
+use std::ops::Range;
+use std::slice::Iter;
+use crate::checker::INSTRUCTION_LIST_INPUTS;
+use crate::core::{LogoProcedure, LogoValue, Word};
+use crate::executor::math_transform;
+use crate::executor_state::EState;
+use crate::dialect::Dialect;
+use crate::locale::Locale;
+use crate::parser::{parse_with_comments_in_dialect, ParseError, SpannedValue};
+
+/// Instruction lists up to this width are kept on one line.
+const INLINE_LIST_WIDTH: usize = 50;
+
+/// Turns parsed Logo code back into indented source, so that parsing the result gives the same values.
+/// Instructions are split into lines using the inputs count of known primitives and procedures.
+pub struct Formatter {
+    arities: HashMap<String, usize>,
+    /// Primitives which take any number of inputs in parentheses.
+    flexible: HashSet<String>,
+    locale: Locale,
+    dialect: Dialect,
+    pub indent: usize,
+    /// Lowercases `to`/`end`, variables and names of known procedures,
+    /// this doesn't change the meaning as the interpreter ignores their case.
+    pub normalize_case: bool
+}
+
+#[derive(PartialEq)]
+enum Kind {
+    Missing,
+    Literal,
+    Call
+}
+
+struct Printer<'a> {
+    formatter: &'a Formatter,
+    arities: &'a HashMap<String, usize>,
+    source: &'a str,
+    comments: Vec<Range<usize>>,
+    next_comment: usize,
+    lines: Vec<String>,
+    line: String,
+    level: usize,
+    inline: bool
+}
+
+impl Formatter {
+    pub fn new<S>(state: &EState<S>) -> Self {
+        let mut arities = HashMap::new();
+        for (name, logo_proc) in &state.logo_procedures {
+            arities.insert(name.to_string(), logo_proc.arg_names.len());
+        }
+        // Primitives take precedence over procedures during execution
+        let mut flexible = HashSet::new();
+        for (name, fun) in &state.functions {
+            arities.insert(name.to_string(), fun.args as usize);
+            if fun.is_flexible() {
+                flexible.insert(name.to_string());
+            }
+        }
+        Formatter {arities, flexible, locale: state.locale.clone(), dialect: state.dialect, indent: 2, normalize_case: true}
+    }
+
+    /// Formatter that knows no primitives and keeps the case, for data rather than code.
+    pub fn plain() -> Self {
+        Formatter {
+            arities: HashMap::new(),
+            flexible: HashSet::new(),
+            locale: Locale::english(),
+            dialect: Dialect::Native,
+            indent: 2,
+            normalize_case: false
+        }
+    }
+
+    pub fn with_dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    fn is_to(&self, value: &SpannedValue) -> bool {
+        match value {
+            SpannedValue::Word(word, _) => self.locale.is_to(&word.0.to_lowercase()),
+            _ => false
+        }
+    }
+
+    fn is_end(&self, value: &SpannedValue) -> bool {
+        match value {
+            SpannedValue::Word(word, _) => self.locale.is_end(&word.0.to_lowercase()),
+            _ => false
+        }
+    }
+
+    fn arity(&self, arities: &HashMap<String, usize>, word: &str) -> Option<usize> {
+        match arities.get(word) {
+            Some(arity) => Some(*arity),
+            None => arities.get(self.locale.primitive_name(word)).cloned()
+        }
+    }
+
+    pub fn format_source(&self, source: &str) -> Result<String, ParseError> {
+        let (values, comments) = parse_with_comments_in_dialect(source, self.dialect)?;
+        let mut arities = self.arities.clone();
+        let mut it = values.iter().peekable();
+        while let Some(value) = it.next() {
+            if !self.is_to(value) {
+                continue;
+            }
+            if let Some(SpannedValue::Word(name, _)) = it.next() {
+                let mut args = 0;
+                while it.peek().is_some_and(|x| is_arg(x)) {
+                    it.next();
+                    args += 1;
+                }
+                arities.entry(name.0.to_lowercase()).or_insert(args);
+            }
+        }
+
+        let mut printer = Printer::new(self, &arities, source, comments);
+        printer.top_level(values);
+        Ok(printer.finish() + "\n")
+    }
+
+    pub fn format_values(&self, values: &[LogoValue]) -> String {
+        let mut pos = 0;
+        let values = values.iter().map(|x| with_positions(x, &mut pos)).collect();
+        let mut printer = Printer::new(self, &self.arities, "", Vec::new());
+        printer.block(values, pos);
+        printer.finish()
+    }
+
+    pub fn format_procedure(&self, name: &str, procedure: &LogoProcedure) -> String {
+        let mut arities = self.arities.clone();
+        arities.entry(name.to_lowercase()).or_insert(procedure.arg_names.len());
+        let mut pos = 0;
+        let code = procedure.code.iter().map(|x| with_positions(x, &mut pos)).collect();
+        let mut doc = String::new();
+        for line in procedure.doc.lines() {
+            doc += &format!("; {}\n", line);
+        }
+        let mut printer = Printer::new(self, &arities, "", Vec::new());
+        printer.token(if procedure.is_macro { ".macro" } else { "to" });
+        printer.token(name);
+        for arg_name in &procedure.arg_names {
+            printer.token(format!(":{}", arg_name).as_str());
+        }
+        printer.newline();
+        printer.level += 1;
+        printer.block(code, pos);
+        printer.level -= 1;
+        printer.newline();
+        printer.token("end");
+        doc + &printer.finish() + "\n"
+    }
+}
+
+/// Gives every value a made-up position, so values without source can go through the same code.
+fn with_positions(value: &LogoValue, pos: &mut usize) -> SpannedValue {
+    let start = *pos;
+    *pos += 1;
+    match value {
+        LogoValue::Word(word) => SpannedValue::Word(word.clone(), start..start + 1),
+        LogoValue::String(str) => SpannedValue::String(str.clone(), start..start + 1),
+        LogoValue::List(list) => {
+            let list = list.iter().map(|x| with_positions(x, pos)).collect();
+            *pos += 1;
+            SpannedValue::List(list, start..*pos)
+        }
+    }
+}
+
+fn is_word(value: &SpannedValue, expected: &str) -> bool {
+    match value {
+        SpannedValue::Word(word, _) => word.0.to_lowercase() == expected,
+        _ => false
+    }
+}
+
+fn is_arg(value: &SpannedValue) -> bool {
+    match value {
+        SpannedValue::Word(word, _) => word.0.starts_with(':'),
+        _ => false
+    }
+}
+
+fn needs_quotes(text: &str) -> bool {
+    text.chars().any(|ch| ch.is_whitespace() || ch == ';' || ['[', ']', '(', ')', '*', '/', '='].contains(&ch))
+}
+
+/// Puts `\` before characters the parser of the dialect wouldn't keep in a word, or between bars.
+fn escape(text: &str, dialect: Dialect, in_bars: bool) -> String {
+    let mut result = String::with_capacity(text.len());
+    for ch in text.chars() {
+        let special = ch == '\\' || (ch == '|' && dialect.bar_quotes())
+            || (!in_bars && needs_quotes(ch.encode_utf8(&mut [0; 4])));
+        if special {
+            result.push('\\');
+        }
+        result.push(ch);
+    }
+    result
+}
+
+/// Text the parser of the dialect reads back as the same string.
+pub(crate) fn string_text(str: &str, dialect: Dialect) -> String {
+    if !dialect.backslash_escapes() {
+        match needs_quotes(str) && dialect.single_quote_strings() {
+            true => format!("'{}'", str.replace('\'', "''")),
+            false => format!("\"{}", str)
+        }
+    }
+    else if needs_quotes(str) && dialect.bar_quotes() {
+        format!("\"|{}|", escape(str, dialect, true))
+    }
+    else {
+        format!("\"{}", escape(str, dialect, false))
+    }
+}
+
+/// Words like `|a b|` of UCBLogo are quoted back, parentheses and operators are kept as they are.
+fn word_text(word: &str, dialect: Dialect) -> String {
+    if !dialect.backslash_escapes() || (word.chars().count() == 1 && needs_quotes(word)) {
+        word.to_string()
+    }
+    else if needs_quotes(word) && dialect.bar_quotes() {
+        format!("|{}|", escape(word, dialect, true))
+    }
+    else {
+        escape(word, dialect, false)
+    }
+}
+
+impl<'a> Printer<'a> {
+    fn new(formatter: &'a Formatter, arities: &'a HashMap<String, usize>, source: &'a str,
+           comments: Vec<Range<usize>>) -> Self {
+        Printer {
+            formatter,
+            arities,
+            source,
+            comments,
+            next_comment: 0,
+            lines: Vec::new(),
+            line: String::new(),
+            level: 0,
+            inline: false
+        }
+    }
+
+    fn finish(mut self) -> String {
+        self.comments_before(usize::MAX);
+        self.newline();
+        while self.lines.last().is_some_and(|x| x.is_empty()) {
+            self.lines.pop();
+        }
+        self.lines.join("\n")
+    }
+
+    fn token(&mut self, text: &str) {
+        if self.line.is_empty() {
+            self.line = " ".repeat(self.level * self.formatter.indent);
+        }
+        else if !self.line.ends_with(['(', '[']) && !text.starts_with([')', ']']) {
+            self.line.push(' ');
+        }
+        self.line += text;
+    }
+
+    fn newline(&mut self) {
+        if !self.line.is_empty() && !self.inline {
+            self.lines.push(std::mem::take(&mut self.line));
+        }
+    }
+
+    fn comments_before(&mut self, pos: usize) {
+        while self.next_comment < self.comments.len() && self.comments[self.next_comment].start < pos {
+            let comment = self.source[self.comments[self.next_comment].clone()].trim_end();
+            self.token(comment);
+            self.newline();
+            self.next_comment += 1;
+        }
+    }
+
+    /// Keeps a comment on the same line if it followed the code on the same line in the source.
+    fn trailing_comment(&mut self, pos: usize) {
+        if self.next_comment >= self.comments.len() {
+            return;
+        }
+        let comment = self.comments[self.next_comment].clone();
+        let between = &self.source[pos..comment.start.max(pos)];
+        if comment.start >= pos && !between.contains('\n') && between.trim().is_empty() {
+            self.token(self.source[comment].trim_end());
+            self.next_comment += 1;
+        }
+    }
+
+    fn has_comments(&self, span: &Range<usize>) -> bool {
+        self.comments[self.next_comment..].iter().any(|x| x.start >= span.start && x.start < span.end)
+    }
+
+    fn top_level(&mut self, values: Vec<SpannedValue>) {
+        let mut it = values.into_iter().peekable();
+        let mut commands = Vec::new();
+        while let Some(value) = it.next() {
+            if !self.formatter.is_to(&value) {
+                commands.push(value);
+                continue;
+            }
+            if !commands.is_empty() {
+                let end = value.span().start;
+                self.block(std::mem::take(&mut commands), end);
+                self.separate();
+            }
+
+            self.comments_before(value.span().start);
+            self.keyword(&value);
+            let mut header_end = value.span().end;
+            if let Some(SpannedValue::Word(_, _)) = it.peek() {
+                let name = it.next().unwrap();
+                header_end = name.span().end;
+                self.keyword(&name);
+            }
+            while it.peek().is_some_and(is_arg) {
+                let arg = it.next().unwrap();
+                header_end = arg.span().end;
+                self.keyword(&arg);
+            }
+            self.trailing_comment(header_end);
+            self.newline();
+
+            let mut code = Vec::new();
+            let mut end = None;
+            for value in it.by_ref() {
+                if self.formatter.is_end(&value) {
+                    end = Some(value);
+                    break;
+                }
+                code.push(value);
+            }
+            self.level += 1;
+            let code_end = end.as_ref().map(|x| x.span().start).unwrap_or(self.source.len());
+            self.block(code, code_end);
+            self.level -= 1;
+            if let Some(end) = end {
+                self.keyword(&end);
+                self.trailing_comment(end.span().end);
+                self.newline();
+                self.separate();
+            }
+        }
+        if !commands.is_empty() {
+            self.block(commands, self.source.len());
+        }
+    }
+
+    fn separate(&mut self) {
+        self.newline();
+        self.lines.push(String::new());
+    }
+
+    fn keyword(&mut self, value: &SpannedValue) {
+        if let SpannedValue::Word(word, _) = value {
+            if self.formatter.normalize_case {
+                self.token(word.0.to_lowercase().as_str());
+            }
+            else {
+                self.token(word.0.as_str());
+            }
+        }
+    }
+
+    fn block(&mut self, values: Vec<SpannedValue>, end: usize) {
+        let (instructions, code_lists) = self.split_instructions(values);
+        for instruction in instructions {
+            self.newline();
+            self.comments_before(instruction[0].span().start);
+            let instruction_end = instruction.last().unwrap().span().end;
+            for value in instruction {
+                self.comments_before(value.span().start);
+                self.code_value(value, &code_lists);
+            }
+            self.trailing_comment(instruction_end);
+        }
+        self.newline();
+        self.comments_before(end);
+    }
+
+    fn code_value(&mut self, value: SpannedValue, code_lists: &HashSet<usize>) {
+        match value {
+            SpannedValue::Word(word, _) => {
+                let lowercase = word.0.to_lowercase();
+                let known = self.formatter.arity(self.arities, &lowercase).is_some();
+                if self.formatter.normalize_case && (lowercase.starts_with(':') || known) {
+                    self.token(lowercase.as_str());
+                }
+                else {
+                    self.token(word_text(&word.0, self.formatter.dialect).as_str());
+                }
+            },
+            SpannedValue::String(str, _) => self.token(string_text(&str, self.formatter.dialect).as_str()),
+            SpannedValue::List(list, span) => {
+                if code_lists.contains(&span.start) {
+                    self.code_list(list, span);
+                }
+                else {
+                    self.data_list(list);
+                }
+            }
+        }
+    }
+
+    fn data_list(&mut self, list: Vec<SpannedValue>) {
+        self.token("[");
+        for value in list {
+            self.comments_before(value.span().start);
+            match value {
+                SpannedValue::Word(word, _) => self.token(word_text(&word.0, self.formatter.dialect).as_str()),
+                SpannedValue::String(str, _) => self.token(string_text(&str, self.formatter.dialect).as_str()),
+                SpannedValue::List(list, _) => self.data_list(list)
+            }
+        }
+        self.token("]");
+    }
+
+    fn code_list(&mut self, list: Vec<SpannedValue>, span: Range<usize>) {
+        if !self.has_comments(&span) {
+            let mut inline = Printer::new(self.formatter, self.arities, "", Vec::new());
+            inline.inline = true;
+            inline.block(list.clone(), 0);
+            inline.inline = false;
+            let text = inline.finish();
+            if self.inline || text.len() <= INLINE_LIST_WIDTH {
+                self.token(format!("[{}]", text).as_str());
+                return;
+            }
+        }
+        self.token("[");
+        self.newline();
+        self.level += 1;
+        self.block(list, span.end);
+        self.level -= 1;
+        self.token("]");
+    }
+
+    /// Splits values into instructions, also returns positions of lists that are instruction lists.
+    fn split_instructions(&self, values: Vec<SpannedValue>) -> (Vec<Vec<SpannedValue>>, HashSet<usize>) {
+        let mut code_lists = HashSet::new();
+        if values.is_empty() {
+            return (Vec::new(), code_lists);
+        }
+        let flexible = |word: &str| {
+            let word = word.to_lowercase();
+            self.formatter.flexible.contains(self.formatter.locale.primitive_name(&word))
+        };
+        let transformed = match math_transform(values.clone(), &flexible) {
+            Ok(transformed) => transformed,
+            Err(_) => return (vec![values], code_lists)
+        };
+        let mut boundaries: Vec<usize> = Vec::new();
+        let mut it = transformed.iter().peekable();
+        while it.peek().is_some() {
+            let mut end = 0;
+            let kind = self.walk_expr(&mut it, false, &mut end, &mut code_lists);
+            // Values that nothing takes are most likely inputs of an unknown procedure
+            if kind == Kind::Literal && !boundaries.is_empty() {
+                *boundaries.last_mut().unwrap() = end.max(*boundaries.last().unwrap());
+            }
+            else {
+                boundaries.push(end);
+            }
+        }
+
+        let mut result: Vec<Vec<SpannedValue>> = vec![Vec::new(); boundaries.len()];
+        let mut idx = 0;
+        let mut opening_parens = Vec::new();
+        for value in values {
+            if is_word(&value, ")") {
+                result[idx].push(value);
+                continue;
+            }
+            if is_word(&value, "(") {
+                opening_parens.push(value);
+                continue;
+            }
+            while idx + 1 < boundaries.len() && value.span().start >= boundaries[idx] {
+                idx += 1;
+            }
+            result[idx].append(&mut opening_parens);
+            result[idx].push(value);
+        }
+        result[idx].append(&mut opening_parens);
+        result.retain(|x| !x.is_empty());
+        (result, code_lists)
+    }
+
+    fn walk_expr(&self, it: &mut Peekable<Iter<SpannedValue>>, instruction_list: bool,
+                 end: &mut usize, code_lists: &mut HashSet<usize>) -> Kind {
+        let value = match it.next() {
+            Some(value) => value,
+            None => return Kind::Missing
+        };
+        *end = value.span().end.max(*end);
+        let word = match value {
+            SpannedValue::Word(Word(word), _) => word.to_lowercase(),
+            SpannedValue::List(_, span) => {
+                if instruction_list {
+                    code_lists.insert(span.start);
+                }
+                return Kind::Literal;
+            },
+            SpannedValue::String(_, _) => return Kind::Literal
+        };
+        if word.parse::<f64>().is_ok() || word.starts_with(':') {
+            return Kind::Literal;
+        }
+        if word == "(" {
+            while it.peek().is_some_and(|x| !is_word(x, ")")) {
+                self.walk_expr(it, false, end, code_lists);
+            }
+            if let Some(value) = it.next() {
+                *end = value.span().end.max(*end);
+            }
+            return Kind::Call;
+        }
+        let arity = self.formatter.arity(self.arities, &word).unwrap_or(0);
+        let primitive = self.formatter.locale.primitive_name(&word);
+        let list_inputs = INSTRUCTION_LIST_INPUTS.iter()
+            .find(|(name, _)| *name == primitive)
+            .map(|(_, inputs)| *inputs)
+            .unwrap_or(&[]);
+        for idx in 0..arity {
+            if self.walk_expr(it, list_inputs.contains(&idx), end, code_lists) == Kind::Missing {
+                break;
+            }
+        }
+        Kind::Call
+    }
+}
+
+#[test]
+fn test_format_source() {
+    use crate::parser::{parse, parse_procedures};
+    use crate::stdlib::add_stdlib;
+
+    let mut state = EState::new(());
+    add_stdlib(&mut state);
+    let formatter = Formatter::new(&state);
+
+    let source = "; shapes\nTO Star :Size REPEAT 5 [show :size show (:size * 2) ifelse :size > 10 [show 1] [show 2]] ; star\n\
+                  show [a   B] show 'long string' END\nto tri repeat 3 [show 1\n; inside\nshow 2] end\nstar 10 tri";
+    let expected = "; shapes\n\
+                    to star :size\n\
+                    \x20 repeat 5 [\n\
+                    \x20   show :size\n\
+                    \x20   show (:size * 2)\n\
+                    \x20   ifelse :size > 10 [show 1] [show 2]\n\
+                    \x20 ] ; star\n\
+                    \x20 show [a B]\n\
+                    \x20 show 'long string'\n\
+                    end\n\
+                    \n\
+                    to tri\n\
+                    \x20 repeat 3 [\n\
+                    \x20   show 1\n\
+                    \x20   ; inside\n\
+                    \x20   show 2\n\
+                    \x20 ]\n\
+                    end\n\
+                    \n\
+                    star 10\n\
+                    tri\n";
+    let formatted = formatter.format_source(source).unwrap();
+    assert_eq!(formatted, expected);
+    assert_eq!(formatter.format_source(&formatted).unwrap(), formatted);
+    assert_eq!(parse_procedures(&formatted.replace("star 10\ntri", "")).unwrap().len(), 2);
+
+    let mut exact = Formatter::new(&state);
+    exact.normalize_case = false;
+    let formatted = exact.format_source(source).unwrap();
+    assert_eq!(parse(&formatted), parse(source));
+}
+
+#[test]
+fn test_format_procedure() {
+    use crate::parser::parse_procedures;
+    use crate::stdlib::add_stdlib;
+
+    let mut state = EState::new(());
+    add_stdlib(&mut state);
+    let formatter = Formatter::new(&state);
+
+    let procedures = parse_procedures("to f :x :y if :x < 1 [output \"done] output f :x - 1 'a b' end").unwrap();
+    let text = formatter.format_procedure("f", &procedures["f"]);
+    assert_eq!(text, "to f :x :y\n  if :x < 1 [output \"done]\n  output f :x - 1 'a b'\nend\n");
+    assert_eq!(parse_procedures(&text).unwrap()["f"].code, procedures["f"].code);
+
+    let values = crate::parser::parse("repeat 2 [show 1 show 2] show sum 1 2").unwrap();
+    assert_eq!(formatter.format_values(&values), "repeat 2 [show 1 show 2]\nshow sum 1 2");
+}
+
+#[test]
+fn test_quoting() {
+    use crate::parser::parse_in_dialect;
+
+    let values = vec![
+        LogoValue::String("it's ok".to_string()),
+        LogoValue::String("a'b c".to_string()),
+        LogoValue::String("a|b c\\d".to_string()),
+        LogoValue::String("don't".to_string()),
+        LogoValue::List(vec![LogoValue::String("x;y".to_string()), LogoValue::String(String::new())].into())
+    ];
+    for dialect in [Dialect::Native, Dialect::UCBLogo, Dialect::FMSLogo, Dialect::AppleLogo] {
+        let formatter = Formatter::plain().with_dialect(dialect);
+        let text = formatter.format_values(&values);
+        assert_eq!(parse_in_dialect(&text, dialect), Ok(values.clone()), "{:?}: {}", dialect, text);
+    }
+    assert_eq!(string_text("it's ok", Dialect::Native), "'it''s ok'");
+    assert_eq!(string_text("a|b c", Dialect::UCBLogo), "\"|a\\|b c|");
+
+    let source = "print \"|a b| |c;d| \"e\\ f\n";
+    let formatted = Formatter::plain().with_dialect(Dialect::UCBLogo).format_source(source).unwrap();
+    assert_eq!(parse_in_dialect(&formatted, Dialect::UCBLogo), parse_in_dialect(source, Dialect::UCBLogo));
+}
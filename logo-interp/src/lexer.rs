@@ -0,0 +1,232 @@
+use std::collections::HashSet;
+use std::ops::Range;
+use crate::dialect::Dialect;
+use crate::executor_state::EState;
+
+/// Kind of a token, `tokenize` gives only the lexical kinds, `EState::highlight` also tells words apart
+/// into primitives, procedures and keywords.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    Primitive,
+    Procedure,
+    /// `to`, `.macro` and `end`, including the ones of the locale.
+    Keyword,
+    /// `:name`, and inputs in the title line of a procedure.
+    Variable,
+    Number,
+    /// Word quoted with `"`.
+    QuotedWord,
+    /// Text in single quotes, it runs to the end of the source if the closing quote is missing.
+    String,
+    /// `[`, `]`, `(` and `)`.
+    Bracket,
+    Operator,
+    Comment,
+    /// Word which isn't a known primitive or procedure.
+    Word
+}
+
+impl TokenKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            TokenKind::Primitive => "primitive",
+            TokenKind::Procedure => "procedure",
+            TokenKind::Keyword => "keyword",
+            TokenKind::Variable => "variable",
+            TokenKind::Number => "number",
+            TokenKind::QuotedWord => "quoted",
+            TokenKind::String => "string",
+            TokenKind::Bracket => "bracket",
+            TokenKind::Operator => "operator",
+            TokenKind::Comment => "comment",
+            TokenKind::Word => "word"
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    /// Byte range in the source.
+    pub span: Range<usize>
+}
+
+const OPERATORS: &[&str] = &["+", "-", "*", "/", "=", "<", ">"];
+
+fn is_terminator(ch: char) -> bool {
+    ch.is_whitespace() || matches!(ch, '[' | ']' | '(' | ')' | '*' | '/' | '=' | ';')
+}
+
+/// Splits the source into tokens the way the parser reads it. Never fails, so it works on code
+/// that is still being typed: missing quotes and brackets only end tokens at the end of the source.
+pub fn tokenize(source: &str, dialect: Dialect) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+    while let Some((start, ch)) = chars.next() {
+        let token = |kind: TokenKind, end: usize| Token {kind, span: start..end};
+        if ch.is_whitespace() {
+            continue;
+        }
+        if ch == ';' {
+            let mut end = source.len();
+            while let Some(&(idx, ch)) = chars.peek() {
+                if ch == '\n' {
+                    end = idx;
+                    break;
+                }
+                chars.next();
+            }
+            tokens.push(token(TokenKind::Comment, end));
+            continue;
+        }
+        if matches!(ch, '[' | ']' | '(' | ')') {
+            tokens.push(token(TokenKind::Bracket, start + 1));
+            continue;
+        }
+        if matches!(ch, '*' | '/' | '=') {
+            tokens.push(token(TokenKind::Operator, start + 1));
+            continue;
+        }
+        if ch == '\'' && dialect.single_quote_strings() {
+            let mut end = source.len();
+            while let Some((idx, ch)) = chars.next() {
+                // `''` is a quote inside the string
+                if ch == '\'' && chars.next_if(|(_, next)| *next == '\'').is_none() {
+                    end = idx + 1;
+                    break;
+                }
+            }
+            tokens.push(token(TokenKind::String, end));
+            continue;
+        }
+
+        // Words end at terminators, except between bars of dialects which quote with them
+        let mut in_bars = ch == '|' && dialect.bar_quotes();
+        if ch == '\\' && dialect.backslash_escapes() {
+            chars.next();
+        }
+        let mut end = source.len();
+        while let Some(&(idx, ch)) = chars.peek() {
+            if ch == '\\' && dialect.backslash_escapes() {
+                chars.next();
+            }
+            else if ch == '|' && dialect.bar_quotes() {
+                in_bars = !in_bars;
+            }
+            else if !in_bars && is_terminator(ch) {
+                end = idx;
+                break;
+            }
+            chars.next();
+        }
+        if ch == '"' {
+            tokens.push(token(TokenKind::QuotedWord, end));
+        }
+        else {
+            split_word(source, start..end, &mut tokens);
+        }
+    }
+    tokens
+}
+
+/// Splits a word at `+` and `-` like the parser does, so `10+2` is a number, an operator and a number.
+fn split_word(source: &str, span: Range<usize>, tokens: &mut Vec<Token>) {
+    let mut start = span.start;
+    for (idx, ch) in source[span.clone()].char_indices() {
+        let pos = span.start + idx;
+        if (ch == '+' || ch == '-') && pos > start {
+            tokens.push(word_token(source, start..pos));
+            tokens.push(Token {kind: TokenKind::Operator, span: pos..pos + 1});
+            start = pos + 1;
+        }
+    }
+    if start < span.end {
+        tokens.push(word_token(source, start..span.end));
+    }
+}
+
+fn word_token(source: &str, span: Range<usize>) -> Token {
+    let word = &source[span.clone()];
+    let kind = if OPERATORS.contains(&word) {
+        TokenKind::Operator
+    }
+    else if word.parse::<f64>().is_ok() {
+        TokenKind::Number
+    }
+    else if word.starts_with(':') {
+        TokenKind::Variable
+    }
+    else {
+        TokenKind::Word
+    };
+    Token {kind, span}
+}
+
+impl<S> EState<S> {
+    /// Tokens of the source for highlighting, words are told apart into primitives, keywords and procedures,
+    /// which are the known ones and the ones defined in the source itself.
+    pub fn highlight(&self, source: &str) -> Vec<Token> {
+        let mut tokens = tokenize(source, self.dialect);
+        let word_at = |token: &Token| source[token.span.clone()].to_lowercase();
+        let mut defined = HashSet::new();
+        let mut titles = HashSet::new();
+        for (idx, token) in tokens.iter().enumerate() {
+            if token.kind == TokenKind::Word && self.locale.is_to(&word_at(token)) {
+                if let Some(name) = tokens.get(idx + 1) {
+                    defined.insert(word_at(name));
+                    titles.insert(idx + 1);
+                }
+            }
+        }
+        let library_procedures = self.library_procedures();
+
+        for (idx, token) in tokens.iter_mut().enumerate() {
+            if token.kind != TokenKind::Word {
+                continue;
+            }
+            let word = word_at(token);
+            token.kind = if titles.contains(&idx) {
+                TokenKind::Procedure
+            }
+            else if self.locale.is_to(&word) || self.locale.is_end(&word) {
+                TokenKind::Keyword
+            }
+            else if self.get_function(&word).is_some() {
+                TokenKind::Primitive
+            }
+            else if defined.contains(&word) || self.logo_procedures.contains_key(&word)
+                || self.loaded_procedures.contains_key(&word) || library_procedures.contains_key(&word) {
+                TokenKind::Procedure
+            }
+            else {
+                TokenKind::Word
+            };
+        }
+        tokens
+    }
+}
+
+#[test]
+fn test_lexer() {
+    use crate::stdlib::add_stdlib;
+
+    let mut state = EState::new(());
+    add_stdlib(&mut state);
+    let source = "to square :size repeat 4 [fd :size rt 90] end\nsquare 10+2 ; big\nprint 'unfinished [";
+    let kinds: Vec<(&str, TokenKind)> = state.highlight(source).into_iter()
+        .map(|token| (&source[token.span], token.kind))
+        .collect();
+    assert_eq!(kinds, vec![
+        ("to", TokenKind::Keyword), ("square", TokenKind::Procedure), (":size", TokenKind::Variable),
+        ("repeat", TokenKind::Primitive), ("4", TokenKind::Number), ("[", TokenKind::Bracket),
+        ("fd", TokenKind::Word), (":size", TokenKind::Variable), ("rt", TokenKind::Word), ("90", TokenKind::Number),
+        ("]", TokenKind::Bracket), ("end", TokenKind::Keyword),
+        ("square", TokenKind::Procedure), ("10", TokenKind::Number), ("+", TokenKind::Operator), ("2", TokenKind::Number),
+        ("; big", TokenKind::Comment), ("print", TokenKind::Primitive), ("'unfinished [", TokenKind::String)
+    ]);
+
+    let tokens = tokenize("print \"|a b| -3 \"c\\ d", Dialect::UCBLogo);
+    assert_eq!(tokens.iter().map(|token| token.kind).collect::<Vec<_>>(),
+               vec![TokenKind::Word, TokenKind::QuotedWord, TokenKind::Number, TokenKind::QuotedWord]);
+    assert_eq!(tokenize("'it''s' 1", Dialect::Native).len(), 2);
+}
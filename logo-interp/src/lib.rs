@@ -2,7 +2,30 @@
 extern crate lazy_static;
 
 pub mod core;
+pub mod list;
+pub mod shared;
+pub mod symbols;
 pub mod parser;
+pub mod lexer;
+pub mod completion;
+pub mod docs;
 pub mod executor_state;
 pub mod stdlib;
 pub mod executor;
+pub mod profiler;
+pub mod checker;
+pub mod formatter;
+pub mod locale;
+pub mod dialect;
+pub mod library;
+pub mod policy;
+pub mod limits;
+pub mod vfs;
+pub mod workspace;
+pub mod data;
+pub mod modules;
+pub mod prelude;
+pub mod processes;
+pub mod events;
+#[cfg(feature = "bigint")]
+pub mod bigint;
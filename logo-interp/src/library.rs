@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+use crate::core::LogoProcedure;
+use crate::dialect::Dialect;
+use crate::executor_state::{EState, Function};
+use crate::locale::Locale;
+use crate::parser::parse_procedures_with;
+
+/// A bundle of primitives and Logo procedures that is installed into `EState` as a whole.
+pub trait LogoLibrary<S> {
+    fn name(&self) -> &str;
+    fn version(&self) -> &str;
+    /// Primitives by name, libraries may provide different sets for different dialects.
+    fn primitives(&self, dialect: Dialect) -> HashMap<String, Function<S>>;
+    /// Logo source with procedures available to programs while the library is installed.
+    fn prelude(&self) -> &str {
+        ""
+    }
+    /// Primitives of other libraries which this one replaces on purpose instead of conflicting with them.
+    fn overrides(&self) -> &[&str] {
+        &[]
+    }
+    /// Called after the primitives are installed.
+    fn on_install(&self, _: &mut EState<S>) {}
+}
+
+pub struct InstalledLibrary<S> {
+    pub name: String,
+    pub version: String,
+    pub primitives: Vec<String>,
+    pub procedures: HashMap<String, LogoProcedure>,
+    /// Primitives which were replaced by this library, they're restored when it's uninstalled.
+    replaced: Vec<(String, Function<S>)>
+}
+
+impl<S> EState<S> {
+    /// Installs primitives and prelude procedures of the library,
+    /// nothing is changed if any of them conflicts with an existing one.
+    pub fn install(&mut self, library: &dyn LogoLibrary<S>) -> Result<(), String> {
+        let name = library.name().to_string();
+        if self.libraries.iter().any(|x| x.name == name) {
+            return Err(format!("Library {} is already installed", name));
+        }
+        let primitives = library.primitives(self.dialect);
+        for primitive in primitives.keys() {
+            if self.functions.contains_key(primitive) && !library.overrides().contains(&primitive.as_str()) {
+                return Err(match self.library_of(primitive) {
+                    Some(owner) => format!("Primitive {} of {} conflicts with {}", primitive, name, owner),
+                    None => format!("Primitive {} of {} is already defined", primitive, name)
+                });
+            }
+        }
+        let procedures = parse_procedures_with(library.prelude(), &Locale::english(), self.dialect)?;
+        for procedure in procedures.keys() {
+            if let Some(owner) = self.libraries.iter().find(|x| x.procedures.contains_key(procedure)) {
+                return Err(format!("Procedure {} of {} conflicts with {}", procedure, name, owner.name));
+            }
+        }
+
+        let mut replaced = Vec::new();
+        let mut names: Vec<String> = primitives.keys().cloned().collect();
+        names.sort();
+        for (primitive, function) in primitives {
+            if let Some(old) = self.functions.insert(primitive.clone(), function) {
+                replaced.push((primitive, old));
+            }
+        }
+        self.libraries.push(InstalledLibrary {
+            name,
+            version: library.version().to_string(),
+            primitives: names,
+            procedures,
+            replaced
+        });
+        library.on_install(self);
+        Ok(())
+    }
+
+    /// Removes primitives and procedures of the library and restores the primitives it replaced.
+    pub fn uninstall(&mut self, name: &str) -> Result<(), String> {
+        let idx = match self.libraries.iter().position(|x| x.name == name) {
+            Some(idx) => idx,
+            None => return Err(format!("Library {} is not installed", name))
+        };
+        for other in &self.libraries[idx + 1..] {
+            if other.replaced.iter().any(|(primitive, _)| self.libraries[idx].primitives.contains(primitive)) {
+                return Err(format!("Library {} can't be uninstalled, {} replaces its primitives", name, other.name));
+            }
+        }
+        let library = self.libraries.remove(idx);
+        for primitive in &library.primitives {
+            self.functions.remove(primitive);
+        }
+        for (primitive, function) in library.replaced {
+            self.functions.insert(primitive, function);
+        }
+        for procedure in library.procedures.keys() {
+            self.logo_procedures.remove(procedure);
+        }
+        Ok(())
+    }
+
+    pub fn libraries(&self) -> &[InstalledLibrary<S>] {
+        &self.libraries
+    }
+
+    /// Name of the library that the primitive currently comes from.
+    pub fn library_of(&self, primitive: &str) -> Option<&str> {
+        self.libraries.iter().rev()
+            .find(|x| x.primitives.iter().any(|name| name == primitive))
+            .map(|x| x.name.as_str())
+    }
+
+    /// Procedures of all installed libraries, programs are run with them added to their own procedures.
+    pub fn library_procedures(&self) -> HashMap<String, LogoProcedure> {
+        let mut result = HashMap::new();
+        for library in &self.libraries {
+            result.extend(library.procedures.iter().map(|(name, procedure)| (name.clone(), procedure.clone())));
+        }
+        result
+    }
+}
+
+#[test]
+fn test_libraries() {
+    use crate::executor::execute_str;
+    use crate::executor_state::Rest;
+    use crate::stdlib::add_stdlib;
+
+    struct Music;
+    impl LogoLibrary<Vec<f64>> for Music {
+        fn name(&self) -> &str { "music" }
+        fn version(&self) -> &str { "1.0" }
+        fn primitives(&self, _: Dialect) -> HashMap<String, Function<Vec<f64>>> {
+            HashMap::from([
+                ("tone".to_string(), Function::new("tone", &["frequency", "more"],
+                    |es: &mut EState<Vec<f64>>, freq: f64, more: Rest<f64>| -> Result<(), String> {
+                        es.state.push(freq);
+                        es.state.extend(more.0);
+                        Ok(())
+                    })),
+                ("sum".to_string(), Function::from_fn2(|_: &mut EState<Vec<f64>>, a: f64, b: f64| -> Result<f64, String> {
+                    Ok(a + b + 1000.0)
+                }))
+            ])
+        }
+        fn prelude(&self) -> &str {
+            "to chord :base\n(tone :base :base * 1.25 :base * 1.5)\nend"
+        }
+        fn overrides(&self) -> &[&str] {
+            &["sum"]
+        }
+    }
+
+    let mut state = EState::new(Vec::new());
+    add_stdlib(&mut state);
+    state.install(&Music).unwrap();
+    assert_eq!(state.install(&Music), Err("Library music is already installed".to_string()));
+    assert_eq!(state.libraries().iter().map(|x| (x.name.as_str(), x.version.as_str())).collect::<Vec<_>>(),
+               vec![("stdlib", env!("CARGO_PKG_VERSION")), ("music", "1.0")]);
+    assert_eq!(state.library_of("sum"), Some("music"));
+    assert_eq!(state.library_of("repeat"), Some("stdlib"));
+
+    execute_str(&mut state, "", "chord 200 tone sum 1 2").unwrap();
+    assert_eq!(state.state, vec![200.0, 250.0, 300.0, 1003.0]);
+
+    assert!(state.uninstall("stdlib").is_err());
+    state.uninstall("music").unwrap();
+    assert!(execute_str(&mut state, "", "chord 200").is_err());
+    state.state.clear();
+    state.functions.insert("tone", Function::from_proc1(|es: &mut EState<Vec<f64>>, x: f64| -> Result<(), String> {
+        es.state.push(x);
+        Ok(())
+    }));
+    execute_str(&mut state, "", "tone sum 1 2").unwrap();
+    assert_eq!(state.state, vec![3.0]);
+    assert_eq!(state.install(&Music), Err("Primitive tone of music is already defined".to_string()));
+}
@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+use crate::core::LogoValue;
+
+/// Caps on the size of values programs create, `None` means unlimited.
+/// Values are checked as primitives make them, the memory of variables whenever one is set.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Limits {
+    pub max_list_length: Option<usize>,
+    pub max_string_length: Option<usize>,
+    /// Limit for the memory of all variables, lists they share count once. Variables are only counted
+    /// while it is set, so set it before running programs.
+    pub max_total_size: Option<usize>
+}
+
+impl Limits {
+    pub fn unlimited() -> Self {
+        Limits::default()
+    }
+
+    /// Limits for programs from untrusted sources, they're far above what drawings normally need.
+    pub fn untrusted() -> Self {
+        Limits {
+            max_list_length: Some(100_000),
+            max_string_length: Some(100_000),
+            max_total_size: Some(10_000_000)
+        }
+    }
+
+    pub fn is_unlimited(&self) -> bool {
+        *self == Limits::unlimited()
+    }
+
+    /// Checks the length of a word or a list. Items of lists were checked when they were made,
+    /// so this takes constant time.
+    pub fn check_value(&self, value: &LogoValue) -> Result<(), String> {
+        match value {
+            LogoValue::Word(word) => self.check_string(&word.0),
+            LogoValue::String(str) => self.check_string(str),
+            LogoValue::List(list) => match self.max_list_length {
+                Some(max) if list.len() > max => Err(format!("List length limit of {} exceeded", max)),
+                _ => Ok(())
+            }
+        }
+    }
+
+    /// Same as `check_value` for the value and all its items, for lists made in one go, like parsed data.
+    pub fn check_items(&self, value: &LogoValue) -> Result<(), String> {
+        self.check_value(value)?;
+        if let LogoValue::List(list) = value {
+            for item in list {
+                self.check_items(item)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn check_string(&self, str: &str) -> Result<(), String> {
+        match self.max_string_length {
+            Some(max) if str.len() > max => Err(format!("Word length limit of {} exceeded", max)),
+            _ => Ok(())
+        }
+    }
+
+    pub(crate) fn check_usage(&self, usage: &Usage) -> Result<(), String> {
+        match self.max_total_size {
+            Some(max) if usage.total > max => Err(format!("Memory limit of {} exceeded", max)),
+            _ => Ok(())
+        }
+    }
+}
+
+/// Memory variables hold, by `value_size`. Lists share nodes, so each node is counted once however many
+/// variables and lists hold it, until the last of them lets it go.
+#[derive(Debug, Default)]
+pub struct Usage {
+    total: usize,
+    /// Number of references to each node that's counted.
+    nodes: HashMap<usize, usize>,
+    /// Nodes gone through so far, counting and freeing values only visits nodes they made or freed.
+    visited: usize
+}
+
+impl Usage {
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    pub(crate) fn add(&mut self, value: &LogoValue) {
+        match value {
+            LogoValue::Word(word) => self.total += word.0.len(),
+            LogoValue::String(str) => self.total += str.len(),
+            LogoValue::List(list) => list.visit_nodes(&mut |node, size| {
+                self.visited += 1;
+                let refs = self.nodes.entry(node).or_insert(0);
+                *refs += 1;
+                if *refs == 1 {
+                    self.total += size;
+                }
+                *refs == 1
+            })
+        }
+    }
+
+    pub(crate) fn remove(&mut self, value: &LogoValue) {
+        match value {
+            LogoValue::Word(word) => self.total = self.total.saturating_sub(word.0.len()),
+            LogoValue::String(str) => self.total = self.total.saturating_sub(str.len()),
+            // Hosts may set variables directly, so nodes may be missing or counted already
+            LogoValue::List(list) => list.visit_nodes(&mut |node, size| {
+                self.visited += 1;
+                match self.nodes.get_mut(&node) {
+                    Some(refs) if *refs > 1 => {
+                        *refs -= 1;
+                        false
+                    },
+                    Some(_) => {
+                        self.nodes.remove(&node);
+                        self.total = self.total.saturating_sub(size);
+                        true
+                    },
+                    None => false
+                }
+            })
+        }
+    }
+}
+
+/// Approximate memory a value takes: characters of words plus one unit per list item.
+/// Items of lists shared inside the value count once.
+pub fn value_size(value: &LogoValue) -> usize {
+    let mut usage = Usage::default();
+    usage.add(value);
+    usage.total
+}
+
+#[test]
+fn test_limits() {
+    use crate::executor::execute_str;
+    use crate::executor_state::EState;
+    use crate::stdlib::add_stdlib;
+
+    let mut state = EState::new(());
+    add_stdlib(&mut state);
+    state.limits = Limits {max_list_length: Some(100), max_string_length: None, max_total_size: Some(1000)};
+    execute_str(&mut state, "", "make 'x' [] repeat 100 [make 'x' lput 1 :x]").unwrap();
+    assert_eq!(execute_str(&mut state, "", "make 'x' lput 1 :x"), Err("List length limit of 100 exceeded".to_string()));
+    assert_eq!(execute_str(&mut state, "to grow :n output list :n :n end", "make 'y' [1 2 3 4 5 6 7 8 9 10] repeat 5 [make 'y' grow :y]"),
+               Err("List length limit of 100 exceeded".to_string()));
+
+    // Variables holding the same list share its memory, copies take their own
+    let total = state.usage.total();
+    execute_str(&mut state, "", "make 'a' :x make 'b' :x make 'c' fput 0 bf :x").unwrap();
+    assert_eq!(state.usage.total(), total + 2);
+    assert_eq!(execute_str(&mut state, "", "make 'd' lput 1 bl :x make 'e' lput 1 bl :x make 'f' lput 1 bl :x \
+                                            make 'g' lput 1 bl :x"), Err("Memory limit of 1000 exceeded".to_string()));
+    // Inputs of procedures are variables too, and their memory is freed when the procedure ends
+    let total = state.usage.total();
+    assert_eq!(execute_str(&mut state, "to keep :copy end", "keep lput 1 bl :x"), Err("Memory limit of 1000 exceeded".to_string()));
+    execute_str(&mut state, "to keep :copy end", "keep :x clearname 'd'").unwrap();
+    assert_eq!(state.usage.total(), total - 200);
+
+    let inner = LogoValue::List(vec![LogoValue::Word(crate::core::Word("abc".to_string()))].into());
+    assert_eq!(value_size(&LogoValue::List(vec![inner.clone(), inner].into())), 6);
+}
+
+#[test]
+fn test_usage_visits() {
+    use crate::executor::execute_str;
+    use crate::executor_state::EState;
+    use crate::stdlib::add_stdlib;
+
+    // Without a memory limit variables aren't counted at all
+    let mut state = EState::new(());
+    add_stdlib(&mut state);
+    execute_str(&mut state, "", "make 'x' [] repeat 1000 [make 'x' fput 1 :x]").unwrap();
+    assert_eq!(state.usage.visited, 0);
+
+    // With one, putting an item in front of a list a variable holds only visits the nodes that changed
+    state.limits.max_total_size = Some(1_000_000);
+    execute_str(&mut state, "", "make 'x' [] repeat 1000 [make 'x' fput 1 :x]").unwrap();
+    let visited = state.usage.visited;
+    execute_str(&mut state, "", "make 'x' fput 1 :x").unwrap();
+    assert!(state.usage.visited - visited < 30);
+    assert_eq!(state.usage.total(), 2002);
+}
@@ -0,0 +1,341 @@
+use std::fmt::{Debug, Formatter};
+use crate::core::LogoValue;
+use crate::executor::Code;
+use crate::shared::{Shared, SharedCell};
+
+/// Immutable list of values, a skew binary random access list. Lists made with `fput` and `butfirst` share
+/// items with the original one, so these, `first`, `len` and clones take constant time, `get` and `last`
+/// take logarithmic time, and `lput` copies the list.
+#[derive(Clone, Default)]
+pub struct LogoList(Option<Shared<Spine>>);
+
+/// Complete binary trees holding the items, each of `2^k - 1` items. Trees grow along the list,
+/// only the first two may be of the same size.
+struct Spine {
+    tree: Shared<Tree>,
+    size: usize,
+    rest: LogoList,
+    len: usize,
+    /// Instructions made of the list when it's run, so running it again doesn't transform it again.
+    code: SharedCell<Option<Code>>
+}
+
+/// Items of a tree in order are the value, then the items of the left subtree, then the ones of the right.
+struct Tree {
+    value: LogoValue,
+    children: Option<(Shared<Tree>, Shared<Tree>)>
+}
+
+impl LogoList {
+    pub fn new() -> Self {
+        LogoList(None)
+    }
+
+    fn with_tree(tree: Shared<Tree>, size: usize, rest: LogoList) -> Self {
+        let len = size + rest.len();
+        LogoList(Some(Shared::new(Spine {tree, size, rest, len, code: SharedCell::new(None)})))
+    }
+
+    pub fn len(&self) -> usize {
+        match &self.0 {
+            Some(spine) => spine.len,
+            None => 0
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_none()
+    }
+
+    pub fn first(&self) -> Option<&LogoValue> {
+        self.0.as_ref().map(|spine| &spine.tree.value)
+    }
+
+    /// The list without its first item, empty for an empty list.
+    pub fn butfirst(&self) -> LogoList {
+        let spine = match &self.0 {
+            Some(spine) => spine,
+            None => return LogoList::new()
+        };
+        match &spine.tree.children {
+            Some((left, right)) => {
+                let half = spine.size / 2;
+                LogoList::with_tree(left.clone(), half, LogoList::with_tree(right.clone(), half, spine.rest.clone()))
+            },
+            None => spine.rest.clone()
+        }
+    }
+
+    pub fn fput(&self, value: LogoValue) -> LogoList {
+        if let Some(first) = &self.0 {
+            if let Some(second) = &first.rest.0 {
+                if first.size == second.size {
+                    let tree = Tree {value, children: Some((first.tree.clone(), second.tree.clone()))};
+                    return LogoList::with_tree(Shared::new(tree), first.size * 2 + 1, second.rest.clone());
+                }
+            }
+        }
+        LogoList::with_tree(Shared::new(Tree {value, children: None}), 1, self.clone())
+    }
+
+    pub fn lput(&self, value: LogoValue) -> LogoList {
+        let mut items = self.to_vec();
+        items.push(value);
+        items.into()
+    }
+
+    pub fn last(&self) -> Option<&LogoValue> {
+        self.get(self.len().checked_sub(1)?)
+    }
+
+    pub fn get(&self, idx: usize) -> Option<&LogoValue> {
+        let mut list = self;
+        let mut idx = idx;
+        loop {
+            let spine = list.0.as_ref()?;
+            if idx < spine.size {
+                break Some(tree_item(&spine.tree, spine.size, idx));
+            }
+            idx -= spine.size;
+            list = &spine.rest;
+        }
+    }
+
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {trees: Vec::new(), rest: self, remaining: self.len()}
+    }
+
+    pub fn to_vec(&self) -> Vec<LogoValue> {
+        self.iter().cloned().collect()
+    }
+
+    pub(crate) fn code(&self) -> Option<Code> {
+        self.0.as_ref()?.code.borrow().clone()
+    }
+
+    pub(crate) fn set_code(&self, code: Code) {
+        if let Some(spine) = &self.0 {
+            *spine.code.borrow_mut() = Some(code);
+        }
+    }
+
+    /// Calls `visit` with the address and the own size of each node the list is made of, going into the
+    /// nodes it points to only when `visit` returns true. Lists share nodes, so this lets their memory be
+    /// counted once.
+    pub(crate) fn visit_nodes<F: FnMut(usize, usize) -> bool>(&self, visit: &mut F) {
+        let mut list = self;
+        while let Some(spine) = &list.0 {
+            if !visit(Shared::as_ptr(spine) as usize, 0) {
+                return;
+            }
+            visit_tree(&spine.tree, visit);
+            list = &spine.rest;
+        }
+    }
+
+    /// Takes the first item out, moving it if the list doesn't share it.
+    fn pop_front(&mut self) -> Option<LogoValue> {
+        let spine = self.0.take()?;
+        let (tree, size, rest) = match Shared::try_unwrap(spine) {
+            Ok(spine) => (spine.tree, spine.size, spine.rest),
+            Err(spine) => (spine.tree.clone(), spine.size, spine.rest.clone())
+        };
+        let (value, children) = match Shared::try_unwrap(tree) {
+            Ok(tree) => (tree.value, tree.children),
+            Err(tree) => (tree.value.clone(), tree.children.clone())
+        };
+        *self = match children {
+            Some((left, right)) => LogoList::with_tree(left, size / 2, LogoList::with_tree(right, size / 2, rest)),
+            None => rest
+        };
+        Some(value)
+    }
+}
+
+fn tree_item(tree: &Tree, size: usize, idx: usize) -> &LogoValue {
+    let (mut tree, mut size, mut idx) = (tree, size, idx);
+    while idx > 0 {
+        let (left, right) = tree.children.as_ref().unwrap();
+        size /= 2;
+        if idx <= size {
+            tree = left;
+            idx -= 1;
+        }
+        else {
+            tree = right;
+            idx -= size + 1;
+        }
+    }
+    &tree.value
+}
+
+/// A tree node takes one unit, and the characters of its item if it's a word.
+fn visit_tree<F: FnMut(usize, usize) -> bool>(tree: &Shared<Tree>, visit: &mut F) {
+    let size = 1 + match &tree.value {
+        LogoValue::Word(word) => word.0.len(),
+        LogoValue::String(str) => str.len(),
+        LogoValue::List(_) => 0
+    };
+    if !visit(Shared::as_ptr(tree) as usize, size) {
+        return;
+    }
+    if let LogoValue::List(list) = &tree.value {
+        list.visit_nodes(visit);
+    }
+    if let Some((left, right)) = &tree.children {
+        visit_tree(left, visit);
+        visit_tree(right, visit);
+    }
+}
+
+impl PartialEq for LogoList {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.0, &other.0) {
+            (Some(a), Some(b)) if Shared::ptr_eq(a, b) => true,
+            _ => self.len() == other.len() && self.iter().eq(other.iter())
+        }
+    }
+}
+
+impl Debug for LogoList {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl From<Vec<LogoValue>> for LogoList {
+    fn from(items: Vec<LogoValue>) -> Self {
+        let mut result = LogoList::new();
+        for item in items.into_iter().rev() {
+            result = result.fput(item);
+        }
+        result
+    }
+}
+
+impl FromIterator<LogoValue> for LogoList {
+    fn from_iter<T: IntoIterator<Item = LogoValue>>(iter: T) -> Self {
+        iter.into_iter().collect::<Vec<LogoValue>>().into()
+    }
+}
+
+pub struct Iter<'a> {
+    /// Subtrees left to go through in the current tree, the next one is last.
+    trees: Vec<&'a Tree>,
+    rest: &'a LogoList,
+    remaining: usize
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = &'a LogoValue;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let tree = match self.trees.pop() {
+            Some(tree) => tree,
+            None => {
+                let spine = self.rest.0.as_ref()?;
+                self.rest = &spine.rest;
+                &spine.tree
+            }
+        };
+        if let Some((left, right)) = &tree.children {
+            self.trees.push(right);
+            self.trees.push(left);
+        }
+        self.remaining -= 1;
+        Some(&tree.value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
+
+impl<'a> IntoIterator for &'a LogoList {
+    type Item = &'a LogoValue;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Moves items out of trees which aren't shared, and clones the others.
+pub struct IntoIter {
+    next: LogoList
+}
+
+impl Iterator for IntoIter {
+    type Item = LogoValue;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next.pop_front()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.next.len(), Some(self.next.len()))
+    }
+}
+
+impl IntoIterator for LogoList {
+    type Item = LogoValue;
+    type IntoIter = IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter {next: self}
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for LogoList {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for LogoList {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Vec::<LogoValue>::deserialize(deserializer)?.into())
+    }
+}
+
+#[test]
+fn test_list() {
+    use crate::core::Word;
+
+    let word = |x: &str| LogoValue::Word(Word(x.to_string()));
+    let list: LogoList = vec![word("1"), word("2"), word("3")].into();
+    let rest = list.butfirst();
+    let longer = rest.fput(word("0"));
+    assert_eq!(list.len(), 3);
+    assert_eq!(longer, LogoList::from(vec![word("0"), word("2"), word("3")]));
+    assert_eq!(list.first(), Some(&word("1")));
+    assert_eq!(list.last(), Some(&word("3")));
+    assert_eq!(list.lput(word("4")).get(3), Some(&word("4")));
+    assert_eq!(longer.into_iter().collect::<Vec<_>>(), vec![word("0"), word("2"), word("3")]);
+    assert_eq!(rest.len(), 2);
+
+    assert_eq!(LogoList::new().last(), None);
+
+    let mut long = LogoList::new();
+    for idx in 0..1_000_000 {
+        long = long.fput(word(&idx.to_string()));
+    }
+    assert_eq!(long.len(), 1_000_000);
+    for idx in [0, 1, 2, 500_000, 999_998, 999_999] {
+        assert_eq!(long.get(idx), Some(&word(&(999_999 - idx).to_string())));
+    }
+    assert_eq!(long.get(1_000_000), None);
+    assert_eq!(long.last(), Some(&word("0")));
+    let mut rest = long.clone();
+    for _ in 0..1000 {
+        rest = rest.butfirst();
+    }
+    assert_eq!(rest.first(), Some(&word("998999")));
+    assert_eq!(rest.get(998_999), Some(&word("0")));
+    assert!(long.iter().zip((0..1_000_000).rev()).all(|(item, idx)| *item == word(&idx.to_string())));
+    assert_eq!(rest.into_iter().count(), 999_000);
+}
@@ -0,0 +1,367 @@
+use std::collections::HashMap;
+
+/// Localized names of primitives, `to`/`end` keywords and error messages.
+/// English names always keep working, localized ones are accepted in addition to them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Locale {
+    pub name: String,
+    pub to_keyword: String,
+    pub end_keyword: String,
+    aliases: HashMap<String, String>,
+    messages: Vec<(String, String)>
+}
+
+static UKRAINIAN_ALIASES: &[(&str, &str)] = &[
+    ("повтори", "repeat"),
+    ("покажи", "show"),
+    ("модуль", "abs"),
+    ("різниця", "difference"),
+    ("більше?", "greater?"),
+    ("менше?", "less?"),
+    ("ціле", "int"),
+    ("мінус", "minus"),
+    ("піднеси", "power"),
+    ("добуток", "product"),
+    ("частка", "quotient"),
+    ("остача", "remainder"),
+    ("випадкове", "random"),
+    ("округли", "round"),
+    ("корінь", "sqrt"),
+    ("сума", "sum"),
+    ("безпершого", "bf"),
+    ("безостаннього", "bl"),
+    ("кількість", "count"),
+    ("порожнє?", "empty?"),
+    ("рівні?", "equal?"),
+    ("перший", "first"),
+    ("першим", "fput"),
+    ("елемент", "item"),
+    ("останній", "last"),
+    ("список", "list"),
+    ("список?", "list?"),
+    ("останнім", "lput"),
+    ("член?", "member?"),
+    ("число?", "number?"),
+    ("вибери", "pick"),
+    ("слово?", "word?"),
+    ("і", "and"),
+    ("або", "or"),
+    ("не", "not"),
+    ("якщо", "if"),
+    ("якщоінакше", "ifelse"),
+    ("зроби", "make"),
+    ("значення", "thing"),
+    ("результат", "output"),
+    ("стоп", "stop"),
+    ("сг", "cg"),
+    ("очисти", "clean"),
+    ("залий", "fill"),
+    ("пп", "pu"),
+    ("по", "pd"),
+    ("гумка", "pe"),
+    ("праворуч", "rt"),
+    ("пр", "rt"),
+    ("ліворуч", "lt"),
+    ("лв", "lt"),
+    ("вперед", "fd"),
+    ("вп", "fd"),
+    ("назад", "bk"),
+    ("нд", "bk"),
+    ("курс", "heading"),
+    ("новкурс", "seth"),
+    ("новмісце", "setpos"),
+    ("новх", "setx"),
+    ("нову", "sety"),
+    ("місце", "pos"),
+    ("додому", "home"),
+    ("товщина", "pensize"),
+    ("новтовщина", "setpensize"),
+    ("сч", "ht"),
+    ("пч", "st"),
+    ("новколір", "setc"),
+    ("колір", "color"),
+];
+
+static UKRAINIAN_MESSAGES: &[(&str, &str)] = &[
+    ("Don't know what to do with {}", "Не знаю, що робити з {}"),
+    ("No such variable", "Такої змінної немає"),
+    ("Missing argument for {}", "Не вистачає входу для {}"),
+    ("Type mismatch", "Невідповідність типів"),
+    ("Not matched closing bracket", "Зайва закриваюча дужка"),
+    ("Missing closing bracket", "Не вистачає закриваючої дужки"),
+    ("Missing closing quote", "Не вистачає закриваючих лапок"),
+    ("Invalid procedure syntax", "Неправильний запис процедури"),
+    ("Missing first argument for {}", "Не вистачає першого входу для {}"),
+    ("Missing second argument for {}", "Не вистачає другого входу для {}"),
+    ("Missing corresponding opening bracket for ')'", "Немає відкриваючої дужки для ')'"),
+    ("Missing corresponding closing bracket for '('", "Немає закриваючої дужки для '('"),
+    ("Input to random must be greater than 0", "Вхід для випадкове має бути більшим за 0"),
+    ("Can't remove an element from an empty list", "Не можна вилучити елемент з порожнього списку"),
+    ("Can't get an element from an empty list", "Не можна взяти елемент з порожнього списку"),
+    ("No such item", "Такого елемента немає"),
+    ("Setpos takes exactly 2 coordinates", "Новмісце потребує рівно 2 координати"),
+    ("Invalid color number {}", "Неправильний номер кольору {}"),
+    ("{} doesn't like {} as input {}", "{} не приймає {} як вхід {}"),
+    ("Not enough inputs to {}", "Не вистачає входів для {}"),
+    ("Too many inputs to {}", "Забагато входів для {}"),
+    ("{} is not available at this level", "{} недоступне на цьому рівні"),
+    ("List length limit of {} exceeded", "Перевищено обмеження довжини списку в {}"),
+    ("Word length limit of {} exceeded", "Перевищено обмеження довжини слова в {}"),
+    ("Memory limit of {} exceeded", "Перевищено обмеження пам'яті в {}"),
+    ("Can't divide by zero", "Не можна ділити на нуль"),
+    ("Invalid JSON: {}", "Неправильний JSON: {}"),
+    ("Missing closing quote in CSV", "Немає закриваючих лапок у CSV"),
+    ("In {}, line {}, {}: {}", "У {}, рядок {}, {}: {}"),
+    ("In {}, line {}: {}", "У {}, рядок {}: {}"),
+    ("In {}: {}", "У {}: {}"),
+    ("Invalid namespace {}", "Неправильний простір імен {}"),
+    ("Macro {} didn't output a list", "Макрос {} не видав список"),
+    ("{} didn't output to {}", "{} не видав значення для {}"),
+];
+
+static RUSSIAN_ALIASES: &[(&str, &str)] = &[
+    ("повтори", "repeat"),
+    ("покажи", "show"),
+    ("модуль", "abs"),
+    ("разность", "difference"),
+    ("больше?", "greater?"),
+    ("меньше?", "less?"),
+    ("целое", "int"),
+    ("минус", "minus"),
+    ("степень", "power"),
+    ("произведение", "product"),
+    ("частное", "quotient"),
+    ("остаток", "remainder"),
+    ("случайно", "random"),
+    ("округли", "round"),
+    ("корень", "sqrt"),
+    ("сумма", "sum"),
+    ("кп", "bf"),
+    ("кроме_первого", "bf"),
+    ("кпос", "bl"),
+    ("кроме_последнего", "bl"),
+    ("счёт", "count"),
+    ("пусто?", "empty?"),
+    ("равны?", "equal?"),
+    ("первый", "first"),
+    ("вставь_первым", "fput"),
+    ("элемент", "item"),
+    ("последний", "last"),
+    ("список", "list"),
+    ("список?", "list?"),
+    ("вставь_последним", "lput"),
+    ("член?", "member?"),
+    ("число?", "number?"),
+    ("выбери", "pick"),
+    ("слово?", "word?"),
+    ("и", "and"),
+    ("или", "or"),
+    ("не", "not"),
+    ("если", "if"),
+    ("еслииначе", "ifelse"),
+    ("сделай", "make"),
+    ("значение", "thing"),
+    ("результат", "output"),
+    ("стоп", "stop"),
+    ("сг", "cg"),
+    ("сотри", "clean"),
+    ("крась", "fill"),
+    ("пп", "pu"),
+    ("по", "pd"),
+    ("ластик", "pe"),
+    ("направо", "rt"),
+    ("пр", "rt"),
+    ("налево", "lt"),
+    ("лв", "lt"),
+    ("вперед", "fd"),
+    ("вп", "fd"),
+    ("назад", "bk"),
+    ("нд", "bk"),
+    ("курс", "heading"),
+    ("нов_курс", "seth"),
+    ("нов_место", "setpos"),
+    ("нов_х", "setx"),
+    ("нов_у", "sety"),
+    ("место", "pos"),
+    ("домой", "home"),
+    ("толщина", "pensize"),
+    ("нов_толщина", "setpensize"),
+    ("сч", "ht"),
+    ("пч", "st"),
+    ("нов_цвет", "setc"),
+    ("цвет", "color"),
+];
+
+static RUSSIAN_MESSAGES: &[(&str, &str)] = &[
+    ("Don't know what to do with {}", "Не знаю, что делать с {}"),
+    ("No such variable", "Такой переменной нет"),
+    ("Missing argument for {}", "Не хватает входа для {}"),
+    ("Type mismatch", "Несоответствие типов"),
+    ("Not matched closing bracket", "Лишняя закрывающая скобка"),
+    ("Missing closing bracket", "Не хватает закрывающей скобки"),
+    ("Missing closing quote", "Не хватает закрывающей кавычки"),
+    ("Invalid procedure syntax", "Неправильная запись процедуры"),
+    ("Missing first argument for {}", "Не хватает первого входа для {}"),
+    ("Missing second argument for {}", "Не хватает второго входа для {}"),
+    ("Missing corresponding opening bracket for ')'", "Нет открывающей скобки для ')'"),
+    ("Missing corresponding closing bracket for '('", "Нет закрывающей скобки для '('"),
+    ("Input to random must be greater than 0", "Вход для случайно должен быть больше 0"),
+    ("Can't remove an element from an empty list", "Нельзя удалить элемент из пустого списка"),
+    ("Can't get an element from an empty list", "Нельзя взять элемент из пустого списка"),
+    ("No such item", "Такого элемента нет"),
+    ("Setpos takes exactly 2 coordinates", "Нов_место требует ровно 2 координаты"),
+    ("Invalid color number {}", "Неправильный номер цвета {}"),
+    ("{} doesn't like {} as input {}", "{} не принимает {} как вход {}"),
+    ("Not enough inputs to {}", "Не хватает входов для {}"),
+    ("Too many inputs to {}", "Слишком много входов для {}"),
+    ("{} is not available at this level", "{} недоступно на этом уровне"),
+    ("List length limit of {} exceeded", "Превышено ограничение длины списка в {}"),
+    ("Word length limit of {} exceeded", "Превышено ограничение длины слова в {}"),
+    ("Memory limit of {} exceeded", "Превышено ограничение памяти в {}"),
+    ("Can't divide by zero", "Нельзя делить на ноль"),
+    ("Invalid JSON: {}", "Неправильный JSON: {}"),
+    ("Missing closing quote in CSV", "Нет закрывающих кавычек в CSV"),
+    ("In {}, line {}, {}: {}", "В {}, строка {}, {}: {}"),
+    ("In {}, line {}: {}", "В {}, строка {}: {}"),
+    ("In {}: {}", "В {}: {}"),
+    ("Invalid namespace {}", "Неправильное пространство имён {}"),
+    ("Macro {} didn't output a list", "Макрос {} не вернул список"),
+    ("{} didn't output to {}", "{} не вернул значение для {}"),
+];
+
+impl Default for Locale {
+    fn default() -> Self {
+        Locale::english()
+    }
+}
+
+impl Locale {
+    pub fn new(name: &str, to_keyword: &str, end_keyword: &str,
+               aliases: &[(&str, &str)], messages: &[(&str, &str)]) -> Self {
+        Locale {
+            name: name.to_string(),
+            to_keyword: to_keyword.to_string(),
+            end_keyword: end_keyword.to_string(),
+            aliases: aliases.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            messages: messages.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+        }
+    }
+
+    pub fn english() -> Self {
+        Locale::new("en", "to", "end", &[], &[])
+    }
+
+    pub fn ukrainian() -> Self {
+        Locale::new("uk", "це", "кінець", UKRAINIAN_ALIASES, UKRAINIAN_MESSAGES)
+    }
+
+    pub fn russian() -> Self {
+        Locale::new("ru", "это", "конец", RUSSIAN_ALIASES, RUSSIAN_MESSAGES)
+    }
+
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name {
+            "en" => Some(Locale::english()),
+            "uk" => Some(Locale::ukrainian()),
+            "ru" => Some(Locale::russian()),
+            _ => None
+        }
+    }
+
+    pub fn add_alias(&mut self, alias: &str, primitive: &str) {
+        self.aliases.insert(alias.to_lowercase(), primitive.to_string());
+    }
+
+    /// Whether the word starts a procedure definition, `.macro` ones included. Expects a lowercase word.
+    pub fn is_to(&self, word: &str) -> bool {
+        word == "to" || word == self.to_keyword || self.is_macro(word)
+    }
+
+    /// Expects a lowercase word.
+    pub fn is_macro(&self, word: &str) -> bool {
+        word == ".macro"
+    }
+
+    /// Expects a lowercase word.
+    pub fn is_end(&self, word: &str) -> bool {
+        word == "end" || word == self.end_keyword
+    }
+
+    /// Returns the name of the primitive the localized name stands for, or the name itself.
+    pub fn primitive_name<'a>(&'a self, name: &'a str) -> &'a str {
+        match self.aliases.get(name) {
+            Some(primitive) => primitive.as_str(),
+            None => name
+        }
+    }
+
+    /// Localized names of the primitive.
+    pub fn aliases_of(&self, primitive: &str) -> Vec<&str> {
+        let mut result: Vec<&str> = self.aliases.iter()
+            .filter(|(_, name)| name.as_str() == primitive)
+            .map(|(alias, _)| alias.as_str())
+            .collect();
+        result.sort();
+        result
+    }
+
+    /// Translates an error message, `{}` in message templates match any text.
+    pub fn translate(&self, message: &str) -> String {
+        for (template, translation) in &self.messages {
+            if let Some(values) = match_template(template, message) {
+                let mut result = String::new();
+                let mut values = values.into_iter();
+                for (idx, part) in translation.split("{}").enumerate() {
+                    if idx > 0 {
+                        result += values.next().unwrap_or("");
+                    }
+                    result += part;
+                }
+                return result;
+            }
+        }
+        message.to_string()
+    }
+}
+
+fn match_template<'a>(template: &str, message: &'a str) -> Option<Vec<&'a str>> {
+    let parts: Vec<&str> = template.split("{}").collect();
+    let mut rest = message.strip_prefix(parts[0])?;
+    let mut values = Vec::new();
+    for (idx, part) in parts.iter().enumerate().skip(1) {
+        if idx == parts.len() - 1 {
+            values.push(rest.strip_suffix(part)?);
+            rest = "";
+        }
+        else {
+            let pos = rest.find(part)?;
+            values.push(&rest[..pos]);
+            rest = &rest[pos + part.len()..];
+        }
+    }
+    if rest.is_empty() { Some(values) } else { None }
+}
+
+#[test]
+fn test_locale() {
+    use crate::executor::execute_str;
+    use crate::executor_state::{EState, Function};
+    use crate::stdlib::add_stdlib;
+
+    let mut state = EState::new(0);
+    add_stdlib(&mut state);
+    state.functions.insert("add", Function::from_proc1(|s: &mut EState<i32>, x: i32| -> Result<(), String> {
+        s.state += x;
+        Ok(())
+    }));
+    state.locale = Locale::russian();
+
+    execute_str(&mut state, "это удвой :x результат сумма :x :x конец", "повтори 3 [add удвой 2] ЕСЛИ 1 = 1 [add 1]").unwrap();
+    assert_eq!(state.state, 13);
+    assert_eq!(execute_str(&mut state, "", "add"), Err("Не хватает входа для add".to_string()));
+    assert_eq!(execute_str(&mut state, "", "[1 2]"), Err("Не знаю, что делать с [1 2]".to_string()));
+
+    state.locale = Locale::ukrainian();
+    execute_str(&mut state, "це подвій :x результат добуток :x 2 кінець", "add подвій 5").unwrap();
+    assert_eq!(state.state, 23);
+}
@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+use crate::core::{LogoProcedure, LogoValue, Word};
+use crate::executor_state::EState;
+use crate::checker::INSTRUCTION_LIST_INPUTS;
+use crate::parser::parse_procedures_located;
+use crate::workspace::Workspace;
+
+fn line_of(text: &str, pos: usize) -> usize {
+    text[..pos.min(text.len())].matches('\n').count() + 1
+}
+
+/// Prefixes calls of procedures defined in the same file with the namespace. Only words in call position
+/// are renamed, in the code and in instruction lists of primitives like `repeat`, lists of data stay as they are.
+struct Namespace<'a, S> {
+    state: &'a EState<S>,
+    procedures: &'a HashMap<String, (LogoProcedure, usize)>,
+    name: &'a str
+}
+
+impl<S> Namespace<'_, S> {
+    fn code(&self, mut values: Vec<LogoValue>) -> Vec<LogoValue> {
+        let mut idx = 0;
+        while idx < values.len() {
+            self.expr(&mut values, &mut idx, false);
+        }
+        values
+    }
+
+    fn expr(&self, values: &mut [LogoValue], idx: &mut usize, instruction_list: bool) {
+        self.operand(values, idx, instruction_list);
+        let is_operator = |value: &LogoValue| matches!(value, LogoValue::Word(word) if INFIX_OPERATORS.contains(&word.0.as_str()));
+        while *idx + 1 < values.len() && is_operator(&values[*idx]) {
+            *idx += 1;
+            self.operand(values, idx, false);
+        }
+    }
+
+    fn operand(&self, values: &mut [LogoValue], idx: &mut usize, instruction_list: bool) {
+        let value = match values.get_mut(*idx) {
+            Some(value) => value,
+            None => return
+        };
+        *idx += 1;
+        let word = match value {
+            LogoValue::Word(word) => word.0.to_lowercase(),
+            LogoValue::List(list) => {
+                if instruction_list {
+                    *list = self.code(list.to_vec()).into();
+                }
+                return;
+            },
+            LogoValue::String(_) => return
+        };
+        if word.starts_with(':') || word.parse::<f64>().is_ok() || word == ")" {
+            return;
+        }
+        if word == "(" {
+            while values.get(*idx).is_some_and(|x| !matches!(x, LogoValue::Word(word) if word.0 == ")")) {
+                self.expr(values, idx, false);
+            }
+            *idx += 1;
+            return;
+        }
+        let (arity, list_inputs) = match self.procedures.get(&word) {
+            Some((procedure, _)) => {
+                *value = LogoValue::Word(Word(format!("{}.{}", self.name, word)));
+                (procedure.arg_names.len(), &[][..])
+            },
+            None => self.arity(&word)
+        };
+        for input in 0..arity {
+            if *idx >= values.len() {
+                break;
+            }
+            self.expr(values, idx, list_inputs.contains(&input));
+        }
+    }
+
+    /// Inputs count and instruction list inputs of a primitive or a procedure defined elsewhere.
+    fn arity(&self, word: &str) -> (usize, &'static [usize]) {
+        if let Some(function) = self.state.get_function(word) {
+            let primitive = self.state.locale.primitive_name(word);
+            let list_inputs = INSTRUCTION_LIST_INPUTS.iter()
+                .find(|(name, _)| *name == primitive)
+                .map_or(&[][..], |(_, inputs)| *inputs);
+            return (function.args.max(0) as usize, list_inputs);
+        }
+        let procedure = self.state.logo_procedures.get(word).or_else(|| self.state.loaded_procedures.get(word));
+        (procedure.map_or(0, |procedure| procedure.arg_names.len()), &[])
+    }
+}
+
+const INFIX_OPERATORS: &[&str] = &["+", "-", "*", "/", "=", "<", ">"];
+
+impl<S> EState<S> {
+    /// Loads a workspace file or a file with procedures, procedures get names like `namespace.name` if it's given.
+    /// A file with procedures is loaded once, until its text changes.
+    pub fn load_file(&mut self, path: &str, namespace: Option<&str>) -> Result<(), String> {
+        let text = self.files.read(path)?;
+        if Workspace::is_workspace(&text) {
+            let workspace = Workspace::from_text(&text).map_err(|err| format!("In {}: {}", path, self.locale.translate(&err)))?;
+            return self.load_workspace(workspace);
+        }
+        let namespace = namespace.map(|x| x.to_lowercase());
+        if let Some(namespace) = &namespace {
+            if namespace.is_empty() || namespace.contains(|ch: char| ch.is_whitespace() || ch == '.') {
+                return Err(format!("Invalid namespace {}", namespace));
+            }
+        }
+        let key = (path.to_string(), namespace.clone());
+        if self.loaded_files.get(&key) == Some(&text) {
+            return Ok(());
+        }
+
+        let procedures = parse_procedures_located(&text, &self.locale, self.dialect).map_err(|err| {
+            format!("In {}, line {}: {}", path, line_of(&text, err.span.start), self.locale.translate(&err.message))
+        })?;
+        let procedures: HashMap<String, (LogoProcedure, usize)> = match &namespace {
+            Some(namespace) => {
+                let renamer = Namespace {state: self, procedures: &procedures, name: namespace};
+                procedures.iter().map(|(name, (procedure, start))| {
+                    (format!("{}.{}", namespace, name), (LogoProcedure {
+                        arg_names: procedure.arg_names.clone(),
+                        code: renamer.code(procedure.code.to_vec()).into(),
+                        is_macro: procedure.is_macro,
+                        doc: procedure.doc.clone()
+                    }, *start))
+                }).collect()
+            },
+            None => procedures
+        };
+        for (name, (procedure, start)) in procedures {
+            self.logo_procedures.insert(name.clone(), procedure.clone());
+            self.loaded_procedures.insert(name.clone(), procedure);
+            self.procedure_lines.insert(name, (path.to_string(), line_of(&text, start)));
+        }
+        self.loaded_files.insert(key, text);
+        Ok(())
+    }
+
+    /// Paths of files with procedures that were loaded, sorted.
+    pub fn loaded_files(&self) -> Vec<String> {
+        let mut result: Vec<String> = self.loaded_files.keys().map(|(path, _)| path.clone()).collect();
+        result.sort();
+        result.dedup();
+        result
+    }
+}
+
+#[test]
+fn test_modules() {
+    use crate::executor::execute_str;
+    use crate::stdlib::add_stdlib;
+    use crate::vfs::{FileSystem, MemoryFileSystem};
+
+    let mut files = MemoryFileSystem::new();
+    files.write("twice.lgo", "; doubling helpers\nto twice :x\noutput :x * 2\nend\n\nto quad :x\noutput twice twice :x\nend\n").unwrap();
+    files.write("bad.lgo", "to ok\nend\n\nshow 1\n").unwrap();
+    let mut state = EState::new(());
+    add_stdlib(&mut state);
+    state.files = Box::new(files.clone());
+
+    execute_str(&mut state, "", "(load 'twice.lgo' 'm') make 'a' m.quad 3 load 'twice.lgo' load 'twice.lgo' make 'b' quad 1").unwrap();
+    assert_eq!(state.vars["a"], LogoValue::Word(Word("12".to_string())));
+    assert_eq!(state.vars["b"], LogoValue::Word(Word("4".to_string())));
+    assert_eq!(state.loaded_files(), vec!["twice.lgo"]);
+    assert!(!state.loaded_procedures.contains_key("m.m.twice"));
+
+    execute_str(&mut state, "", "make 'c' M.twice 5").unwrap();
+    assert_eq!(state.vars["c"], LogoValue::Word(Word("10".to_string())));
+    assert_eq!(execute_str(&mut state, "", "load 'bad.lgo'"), Err("In bad.lgo, line 4: Invalid procedure syntax".to_string()));
+    assert_eq!(execute_str(&mut state, "", "(load 'twice.lgo' 'a.b')"), Err("Invalid namespace a.b".to_string()));
+
+    // Data lists keep the names, runtime errors tell where the procedure is
+    files.write("shapes.lgo", "to square :x\noutput :x * :x\nend\n\nto names\noutput [square names]\nend\n\n\
+                               to squares :n\nmake 'total' 0\nrepeat :n [make 'total' :total + square :n]\noutput :total\nend\n\n\
+                               to broken\noutput nosuch 1\nend\n").unwrap();
+    state.files = Box::new(files.clone());
+    execute_str(&mut state, "", "(load 'shapes.lgo' 's') make 'd' s.names make 'e' s.squares 3").unwrap();
+    assert_eq!(state.vars["d"], LogoValue::List(vec![Word("square".to_string()), Word("names".to_string())]
+        .into_iter().map(LogoValue::Word).collect()));
+    assert_eq!(state.vars["e"], LogoValue::Word(Word("27".to_string())));
+    assert_eq!(execute_str(&mut state, "", "show s.broken"), Err("In shapes.lgo, line 15, s.broken: Don't know what to do with nosuch".to_string()));
+}
@@ -1,5 +1,8 @@
 use std::collections::{HashMap, HashSet};
+use std::ops::Range;
 use crate::core::*;
+use crate::dialect::Dialect;
+use crate::locale::Locale;
 
 lazy_static! {
     static ref TERMINATOR_CHARS: HashSet<char>
@@ -7,35 +10,127 @@ lazy_static! {
 }
 
 fn is_terminator_char(ch: char) -> bool {
-    return ch.is_whitespace() || TERMINATOR_CHARS.contains(&ch);
+    ch.is_whitespace() || TERMINATOR_CHARS.contains(&ch)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpannedValue {
+    Word(Word, Range<usize>),
+    String(String, Range<usize>),
+    List(Vec<SpannedValue>, Range<usize>)
+}
+
+impl SpannedValue {
+    pub fn span(&self) -> Range<usize> {
+        match self {
+            SpannedValue::Word(_, span) => span.clone(),
+            SpannedValue::String(_, span) => span.clone(),
+            SpannedValue::List(_, span) => span.clone()
+        }
+    }
+
+    pub fn to_value(&self) -> LogoValue {
+        match self {
+            SpannedValue::Word(word, _) => LogoValue::Word(word.clone()),
+            SpannedValue::String(str, _) => LogoValue::String(str.clone()),
+            SpannedValue::List(list, _) => LogoValue::List(list.iter().map(|x| x.to_value()).collect())
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub message: String,
+    pub span: Range<usize>
 }
 
 pub fn parse(source: &str) -> Result<Vec<LogoValue>, String> {
+    parse_in_dialect(source, Dialect::Native)
+}
+
+/// Same as `parse`, but follows quoting rules of the dialect.
+pub fn parse_in_dialect(source: &str, dialect: Dialect) -> Result<Vec<LogoValue>, String> {
+    match parse_with_comments_in_dialect(source, dialect) {
+        Ok((list, _)) => Ok(list.iter().map(|x| x.to_value()).collect()),
+        Err(err) => Err(err.message)
+    }
+}
+
+/// Same as `parse`, but keeps the byte range of every parsed value in the source.
+pub fn parse_spanned(source: &str) -> Result<Vec<SpannedValue>, ParseError> {
+    Ok(parse_with_comments(source)?.0)
+}
+
+/// Same as `parse_spanned`, additionally returns the byte ranges of `;` comments.
+pub fn parse_with_comments(source: &str) -> Result<(Vec<SpannedValue>, Vec<Range<usize>>), ParseError> {
+    parse_with_comments_in_dialect(source, Dialect::Native)
+}
+
+pub fn parse_with_comments_in_dialect(source: &str, dialect: Dialect)
+    -> Result<(Vec<SpannedValue>, Vec<Range<usize>>), ParseError> {
     #[derive(PartialEq)]
     enum Mode {
         None,
         Word,
         DoubleQuoteString,
         SingleQuoteString,
+        Comment,
     }
+    let mut comments = Vec::new();
     let mut mode = Mode::None;
+    let mut in_bars = false;
     let mut pending_word = String::new();
+    let mut pending_start = 0;
 
-    let mut list_stack: Vec<Vec<LogoValue>> = Vec::new();
-    list_stack.push(Vec::new());
-    for ch in source.chars() {
-        if (mode == Mode::Word || mode == Mode::DoubleQuoteString) && is_terminator_char(ch) {
+    let mut list_stack: Vec<(Vec<SpannedValue>, usize)> = Vec::new();
+    list_stack.push((Vec::new(), 0));
+    let mut chars = source.char_indices().peekable();
+    while let Some((idx, ch)) = chars.next() {
+        if mode == Mode::Comment {
+            if ch == '\n' {
+                comments.push(pending_start..idx);
+                mode = Mode::None;
+            }
+            continue;
+        }
+        // `''` in a single quoted string is a quote, `\` in dialects which escape takes the next character as is
+        if mode == Mode::SingleQuoteString && ch == '\'' && chars.next_if(|(_, next)| *next == '\'').is_some() {
+            pending_word.push(ch);
+            continue;
+        }
+        if ch == '\\' && dialect.backslash_escapes() {
+            if mode == Mode::None {
+                mode = Mode::Word;
+                pending_start = idx;
+                pending_word = String::new();
+            }
+            if let Some((_, next)) = chars.next() {
+                pending_word.push(next);
+            }
+            continue;
+        }
+        if (mode == Mode::Word || mode == Mode::DoubleQuoteString) && dialect.bar_quotes() && ch == '|' {
+            in_bars = !in_bars;
+            continue;
+        }
+        if in_bars {
+            pending_word.push(ch);
+            continue;
+        }
+        if (mode == Mode::Word || mode == Mode::DoubleQuoteString) && (is_terminator_char(ch) || ch == ';') {
+            let span = pending_start..idx;
             if mode == Mode::Word {
-                list_stack.last_mut().unwrap().push(LogoValue::Word(Word(pending_word)));
+                list_stack.last_mut().unwrap().0.push(SpannedValue::Word(Word(pending_word), span));
             }
             else {
-                list_stack.last_mut().unwrap().push(LogoValue::String(pending_word));
+                list_stack.last_mut().unwrap().0.push(SpannedValue::String(pending_word, span));
             }
             pending_word = String::new();
             mode = Mode::None;
         }
         if mode == Mode::SingleQuoteString && ch == '\'' {
-            list_stack.last_mut().unwrap().push(LogoValue::String(pending_word));
+            let span = pending_start..idx + 1;
+            list_stack.last_mut().unwrap().0.push(SpannedValue::String(pending_word, span));
             pending_word = String::new();
             mode = Mode::None;
             continue;
@@ -46,54 +141,71 @@ pub fn parse(source: &str) -> Result<Vec<LogoValue>, String> {
             continue;
         }
 
+        pending_start = idx;
         if ch.is_whitespace() {}
         else if ch == '[' {
-            list_stack.push(Vec::new());
+            list_stack.push((Vec::new(), idx));
         }
         else if ch == ']' {
-            let last_list = list_stack.pop().unwrap();
+            let (last_list, list_start) = list_stack.pop().unwrap();
             match list_stack.last_mut() {
-                Some(stack) => stack.push(LogoValue::List(last_list)),
-                None => return Err("Not matched closing bracket".to_string())
+                Some(stack) => stack.0.push(SpannedValue::List(last_list, list_start..idx + 1)),
+                None => return Err(ParseError {message: "Not matched closing bracket".to_string(), span: idx..idx + 1})
             }
         }
         else if ch == '"' {
             mode = Mode::DoubleQuoteString;
         }
-        else if ch == '\'' {
+        else if ch == '\'' && dialect.single_quote_strings() {
             mode = Mode::SingleQuoteString;
         }
+        else if ch == '|' && dialect.bar_quotes() {
+            mode = Mode::Word;
+            in_bars = true;
+            pending_word = String::new();
+        }
+        else if ch == ';' {
+            mode = Mode::Comment;
+        }
         else if TERMINATOR_CHARS.contains(&ch) {
-            list_stack.last_mut().unwrap().push(LogoValue::Word(Word(ch.to_string())));
+            list_stack.last_mut().unwrap().0.push(SpannedValue::Word(Word(ch.to_string()), idx..idx + 1));
         }
         else {
             mode = Mode::Word;
             pending_word = String::from(ch);
         }
     }
+    let span = pending_start..source.len();
+    if in_bars {
+        return Err(ParseError {message: String::from("Missing closing quote"), span});
+    }
     match mode {
         Mode::None => {},
-        Mode::Word => list_stack.last_mut().unwrap().push(LogoValue::Word(Word(pending_word))),
-        Mode::DoubleQuoteString => list_stack.last_mut().unwrap().push(LogoValue::String(pending_word)),
+        Mode::Word => list_stack.last_mut().unwrap().0.push(SpannedValue::Word(Word(pending_word), span)),
+        Mode::DoubleQuoteString => list_stack.last_mut().unwrap().0.push(SpannedValue::String(pending_word, span)),
         Mode::SingleQuoteString => {
-            return Err(String::from("Missing closing quote"))
-        }
+            return Err(ParseError {message: String::from("Missing closing quote"), span})
+        },
+        Mode::Comment => comments.push(span)
     }
     if list_stack.len() > 1 {
-        return Err(String::from("Missing closing bracket"));
+        let list_start = list_stack.last().unwrap().1;
+        return Err(ParseError {message: String::from("Missing closing bracket"), span: list_start..list_start + 1});
     }
-    return Ok(process_plus_minus(list_stack.pop().unwrap()));
+    Ok((process_plus_minus(list_stack.pop().unwrap().0), comments))
 }
 
-fn process_plus_minus(list: Vec<LogoValue>) -> Vec<LogoValue> {
+fn process_plus_minus(list: Vec<SpannedValue>) -> Vec<SpannedValue> {
     let mut result = Vec::with_capacity(list.len());
     for val in list {
         match val {
-            LogoValue::String(s) => result.push(LogoValue::String(s)),
-            LogoValue::List(sublist) => result.push(LogoValue::List(process_plus_minus(sublist))),
-            LogoValue::Word(word) => {
+            SpannedValue::String(s, span) => result.push(SpannedValue::String(s, span)),
+            SpannedValue::List(sublist, span) => result.push(SpannedValue::List(process_plus_minus(sublist), span)),
+            SpannedValue::Word(word, span) => {
                 let mut cur = String::new();
-                for ch in word.0.chars() {
+                let mut cur_start = span.start;
+                for (idx, ch) in word.0.char_indices() {
+                    let pos = span.start + idx;
                     if ch != '+' && ch != '-' {
                         cur.push(ch);
                         continue;
@@ -102,13 +214,14 @@ fn process_plus_minus(list: Vec<LogoValue>) -> Vec<LogoValue> {
                         cur.push(ch);
                     }
                     else {
-                        result.push(LogoValue::Word(Word(cur)));
-                        result.push(LogoValue::Word(Word(ch.to_string())));
+                        result.push(SpannedValue::Word(Word(cur), cur_start..pos));
+                        result.push(SpannedValue::Word(Word(ch.to_string()), pos..pos + 1));
                         cur = String::new();
+                        cur_start = pos + 1;
                     }
                 }
                 if !cur.is_empty() {
-                    result.push(LogoValue::Word(Word(cur)));
+                    result.push(SpannedValue::Word(Word(cur), cur_start..span.end));
                 }
             }
         }
@@ -117,11 +230,33 @@ fn process_plus_minus(list: Vec<LogoValue>) -> Vec<LogoValue> {
 }
 
 pub fn parse_procedures(source: &str) -> Result<HashMap<String, LogoProcedure>, String> {
+    parse_procedures_with(source, &Locale::english(), Dialect::Native)
+}
+
+/// Same as `parse_procedures`, but also accepts `to`/`end` keywords of the locale
+/// and follows quoting rules of the dialect.
+pub fn parse_procedures_with(source: &str, locale: &Locale, dialect: Dialect) -> Result<HashMap<String, LogoProcedure>, String> {
+    parse_procedures_spanned(source, locale, dialect).map_err(|err| err.message)
+}
+
+/// Same as `parse_procedures_with`, but errors keep the byte range of the source that failed.
+pub fn parse_procedures_spanned(source: &str, locale: &Locale, dialect: Dialect)
+    -> Result<HashMap<String, LogoProcedure>, ParseError> {
+    let procedures = parse_procedures_located(source, locale, dialect)?;
+    Ok(procedures.into_iter().map(|(name, (procedure, _))| (name, procedure)).collect())
+}
+
+/// Procedures with byte offsets of their title lines.
+pub(crate) fn parse_procedures_located(source: &str, locale: &Locale, dialect: Dialect)
+    -> Result<HashMap<String, (LogoProcedure, usize)>, ParseError> {
     let mut result = HashMap::new();
     let mut name = String::new();
     let mut arg_names = Vec::new();
     let mut code = Vec::new();
-    let values = parse(source)?;
+    let mut is_macro = false;
+    let mut doc = String::new();
+    let mut start = 0;
+    let (values, comments) = parse_with_comments_in_dialect(source, dialect)?;
     #[derive(PartialEq)]
     enum Mode {
         None,
@@ -133,22 +268,25 @@ pub fn parse_procedures(source: &str) -> Result<HashMap<String, LogoProcedure>,
 
     for value in values {
         if mode == Mode::None {
-            if let LogoValue::Word(word) = &value {
-                if word.0.to_lowercase() == "to" {
+            if let SpannedValue::Word(word, span) = &value {
+                if locale.is_to(&word.0.to_lowercase()) {
                     mode = Mode::Name;
+                    is_macro = locale.is_macro(&word.0.to_lowercase());
+                    start = span.start;
+                    doc = doc_comment(source, &comments, start);
                     continue;
                 }
             }
         }
         if mode == Mode::Name {
-            if let LogoValue::Word(word) = &value {
+            if let SpannedValue::Word(word, _) = &value {
                 name = word.0.to_lowercase();
                 mode = Mode::Params;
                 continue;
             }
         }
         if mode == Mode::Params {
-            if let LogoValue::Word(word) = &value {
+            if let SpannedValue::Word(word, _) = &value {
                 if let Some(arg_name) = word.0.strip_prefix(":") {
                     arg_names.push(arg_name.to_lowercase());
                     continue;
@@ -157,29 +295,46 @@ pub fn parse_procedures(source: &str) -> Result<HashMap<String, LogoProcedure>,
             mode = Mode::Body;
         }
         if mode == Mode::Body {
-            if let LogoValue::Word(word) = &value {
-                if word.0.to_lowercase() == "end" {
+            if let SpannedValue::Word(word, _) = &value {
+                if locale.is_end(&word.0.to_lowercase()) {
                     mode = Mode::None;
-                    result.insert(name, LogoProcedure {arg_names, code});
+                    result.insert(name, (LogoProcedure {arg_names, code: code.into(), is_macro, doc: std::mem::take(&mut doc)}, start));
                     name = String::new();
                     arg_names = Vec::new();
                     code = Vec::new();
                     continue;
                 }
             }
-            code.push(value);
+            code.push(value.to_value());
             continue;
         }
-        return Err("Invalid procedure syntax".to_string());
+        return Err(ParseError {message: "Invalid procedure syntax".to_string(), span: value.span()});
     }
 
     if mode != Mode::None {
-        return Err("Invalid procedure syntax".to_string());
+        return Err(ParseError {message: "Invalid procedure syntax".to_string(), span: start..source.len()});
     }
 
     Ok(result)
 }
 
+/// Text of the comments on the lines right above the position, a blank line or code ends them.
+fn doc_comment(source: &str, comments: &[Range<usize>], start: usize) -> String {
+    let mut lines = Vec::new();
+    let mut pos = start;
+    for comment in comments.iter().rev().skip_while(|comment| comment.end > start) {
+        let line_start = source[..comment.start].rfind('\n').map_or(0, |idx| idx + 1);
+        let between = &source[comment.end..pos];
+        if !between.trim().is_empty() || between.matches('\n').count() != 1 || !source[line_start..comment.start].trim().is_empty() {
+            break;
+        }
+        lines.push(source[comment.clone()].trim_start_matches(';').trim());
+        pos = line_start;
+    }
+    lines.reverse();
+    lines.join("\n")
+}
+
 #[test]
 fn test_loop_parsing() {
     let result = parse("repeat 12  [rt 30 repeat 4 [fd   50 rt 90]]");
@@ -196,8 +351,8 @@ fn test_loop_parsing() {
                 LogoValue::Word(Word("50".to_string())),
                 LogoValue::Word(Word("rt".to_string())),
                 LogoValue::Word(Word("90".to_string())),
-            ])
-        ])
+            ].into())
+        ].into())
     ];
     assert_eq!(result, Ok(expected));
 }
@@ -224,6 +379,37 @@ fn test_errors() {
     assert_eq!(result, Err("Missing closing quote".to_string()));
 }
 
+#[test]
+fn test_comments() {
+    let source = "fd 10; forward\n; turn\nrt \"a;b\n";
+    let (result, comments) = parse_with_comments(source).unwrap();
+    assert_eq!(result.iter().map(|x| x.to_value()).collect::<Vec<LogoValue>>(), vec![
+        LogoValue::Word(Word("fd".to_string())),
+        LogoValue::Word(Word("10".to_string())),
+        LogoValue::Word(Word("rt".to_string())),
+        LogoValue::String("a".to_string()),
+    ]);
+    let comments: Vec<&str> = comments.into_iter().map(|x| &source[x]).collect();
+    assert_eq!(comments, vec!["; forward", "; turn", ";b"]);
+}
+
+#[test]
+fn test_dialect_quoting() {
+    let result = parse_in_dialect("print \"|hello world| \"don't |a;b|", Dialect::UCBLogo);
+    let expected = vec![
+        LogoValue::Word(Word("print".to_string())),
+        LogoValue::String("hello world".to_string()),
+        LogoValue::String("don't".to_string()),
+        LogoValue::Word(Word("a;b".to_string())),
+    ];
+    assert_eq!(result, Ok(expected));
+    assert_eq!(parse_in_dialect("\"it's", Dialect::AppleLogo), Ok(vec![LogoValue::String("it's".to_string())]));
+    assert!(parse_in_dialect("'long string'", Dialect::AppleLogo).is_ok());
+    assert_eq!(parse("'it''s ok' ''"), Ok(vec![LogoValue::String("it's ok".to_string()), LogoValue::String(String::new())]));
+    assert_eq!(parse_in_dialect("\"|a\\|b| c\\ d", Dialect::FMSLogo),
+               Ok(vec![LogoValue::String("a|b".to_string()), LogoValue::Word(Word("c d".to_string()))]));
+}
+
 #[test]
 fn test_math() {
     let result = parse("2+2");
@@ -249,4 +435,20 @@ fn test_math() {
         LogoValue::Word(Word("-2".to_string())),
     ]);
     assert_eq!(result, expected);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_spans() {
+    let source = "fd 10+2 [rt \"x 'a b']";
+    let result = parse_spanned(source).unwrap();
+    let spans: Vec<&str> = result.iter().map(|x| &source[x.span()]).collect();
+    assert_eq!(spans, vec!["fd", "10", "+", "2", "[rt \"x 'a b']"]);
+    if let SpannedValue::List(list, _) = &result[4] {
+        let spans: Vec<&str> = list.iter().map(|x| &source[x.span()]).collect();
+        assert_eq!(spans, vec!["rt", "\"x", "'a b'"]);
+    }
+    else {
+        panic!("Expected a list");
+    }
+    assert_eq!(parse_spanned("fd [10").unwrap_err().span, 3..4);
+}
@@ -0,0 +1,125 @@
+use std::collections::{HashMap, HashSet};
+use crate::executor_state::Function;
+
+/// Group of primitives that a `Policy` can allow or forbid as a whole.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Category {
+    Control,
+    Math,
+    Logic,
+    Data,
+    Variables,
+    Text,
+    Turtle,
+    Files,
+    Time,
+    System,
+    #[default]
+    Other
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Mode {
+    Whitelist,
+    Blacklist
+}
+
+/// Restricts which primitives programs may call, for example in beginner lessons or for untrusted code.
+/// Procedures are always allowed, as they can only call allowed primitives.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Policy {
+    pub level: String,
+    mode: Mode,
+    names: HashSet<String>,
+    categories: HashSet<Category>
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Policy::unrestricted()
+    }
+}
+
+impl Policy {
+    pub fn unrestricted() -> Self {
+        Policy::deny("full", &[], &[])
+    }
+
+    /// Only the listed primitives and primitives of the listed categories are available.
+    pub fn allow(level: &str, names: &[&str], categories: &[Category]) -> Self {
+        Policy {
+            level: level.to_string(),
+            mode: Mode::Whitelist,
+            names: names.iter().map(|x| x.to_string()).collect(),
+            categories: categories.iter().cloned().collect()
+        }
+    }
+
+    /// All primitives except the listed ones and ones of the listed categories are available.
+    pub fn deny(level: &str, names: &[&str], categories: &[Category]) -> Self {
+        Policy {
+            level: level.to_string(),
+            mode: Mode::Blacklist,
+            names: names.iter().map(|x| x.to_string()).collect(),
+            categories: categories.iter().cloned().collect()
+        }
+    }
+
+    /// Presets: `beginner`, `intermediate`, `sandbox` (no files and time) and `full`.
+    pub fn by_level(level: &str) -> Option<Self> {
+        match level {
+            "beginner" => Some(Policy::allow(level, &["fd", "bk", "rt", "lt", "repeat"], &[])),
+            "intermediate" => Some(Policy::allow(level, &["profile"], &[
+                Category::Control, Category::Math, Category::Logic, Category::Variables,
+                Category::Text, Category::Turtle
+            ])),
+            "sandbox" => Some(Policy::deny(level, &[], &[Category::Files, Category::Time])),
+            "full" => Some(Policy::unrestricted()),
+            _ => None
+        }
+    }
+
+    pub fn allows(&self, name: &str, category: Category) -> bool {
+        let listed = self.names.contains(name) || self.categories.contains(&category);
+        match self.mode {
+            Mode::Whitelist => listed,
+            Mode::Blacklist => !listed
+        }
+    }
+}
+
+/// Sets the category of the listed primitives, names that aren't in `functions` are skipped.
+pub fn set_category<S>(functions: &mut HashMap<String, Function<S>>, category: Category, names: &[&str]) {
+    for name in names {
+        if let Some(function) = functions.get_mut(*name) {
+            function.category = category;
+        }
+    }
+}
+
+#[test]
+fn test_policy() {
+    use crate::executor::execute_str;
+    use crate::executor_state::EState;
+    use crate::locale::Locale;
+    use crate::stdlib::add_stdlib;
+
+    let mut state = EState::new(0);
+    add_stdlib(&mut state);
+    state.functions.insert("fd", Function::from_proc1(|s: &mut EState<i32>, x: i32| -> Result<(), String> {
+        s.state += x;
+        Ok(())
+    }).with_category(Category::Turtle));
+    state.policy = Policy::by_level("beginner").unwrap();
+    execute_str(&mut state, "to step fd 1 end", "repeat 3 [fd 2 step]").unwrap();
+    assert_eq!(state.state, 9);
+    assert_eq!(execute_str(&mut state, "", "show 5"), Err("show is not available at this level".to_string()));
+    assert_eq!(execute_str(&mut state, "", "fd 1 + 2"), Err("sum is not available at this level".to_string()));
+
+    state.policy = Policy::by_level("intermediate").unwrap();
+    execute_str(&mut state, "", "fd 1 + 2").unwrap();
+    assert_eq!(execute_str(&mut state, "", "fd count [1 2]"), Err("count is not available at this level".to_string()));
+
+    state.locale = Locale::ukrainian();
+    assert_eq!(execute_str(&mut state, "", "fd кількість [1 2]"), Err("кількість недоступне на цьому рівні".to_string()));
+}
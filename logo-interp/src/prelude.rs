@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+use crate::core::{LogoConvertible, LogoList, LogoValue, Word};
+use crate::dialect::Dialect;
+use crate::executor_state::{EState, Function};
+use crate::library::LogoLibrary;
+use crate::policy::Category;
+use crate::stdlib::apply;
+#[cfg(feature = "bigint")]
+use crate::bigint::equal;
+#[cfg(not(feature = "bigint"))]
+use crate::stdlib::equal;
+
+/// Standard procedures written in Logo itself, like `circle` and `polygon`.
+/// They rely on the stdlib, turtle ones on the drawing primitives of the host.
+/// Procedures that would recurse once per item of a list, like `sort` and `reduce`, are primitives instead.
+pub struct Prelude;
+
+impl<S: 'static> LogoLibrary<S> for Prelude {
+    fn name(&self) -> &str {
+        "prelude"
+    }
+
+    fn version(&self) -> &str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn primitives(&self, _: Dialect) -> HashMap<String, Function<S>> {
+        let mut functions = HashMap::new();
+        functions.insert("reverse".to_string(), Function::new("reverse", &["list"], reverse)
+            .with_help("Outputs the list with its items in reverse order").with_category(Category::Data));
+        functions.insert("sort".to_string(), Function::new("sort", &["list"], sort)
+            .with_help("Sorts a list of numbers in ascending order").with_category(Category::Data));
+        functions.insert("remove".to_string(), Function::new("remove", &["thing", "list"], remove)
+            .with_help("Outputs the list without the items equal to the thing").with_category(Category::Data));
+        functions.insert("reduce".to_string(), Function::new("reduce", &["template", "list"], reduce)
+            .with_help("Combines items from the right, reduce 'sum' [1 2 3] is sum 1 sum 2 3").with_category(Category::Data));
+        functions.insert("combine".to_string(), Function::new("combine", &["thing1", "thing2"], combine)
+            .with_help("Outputs the list with the thing added at the start, or the two words joined").with_category(Category::Data));
+        functions.insert("cascade".to_string(), Function::new("cascade", &["times", "template", "value"], cascade)
+            .with_help("Applies the template to the value the number of times").with_category(Category::Data));
+        functions
+    }
+
+    fn prelude(&self) -> &str {
+        include_str!("prelude.lgo")
+    }
+}
+
+pub fn add_prelude<S: 'static>(es: &mut EState<S>) {
+    es.install(&Prelude).unwrap();
+}
+
+fn reverse<S>(_: &mut EState<S>, list: LogoList) -> Result<LogoList, String> {
+    let mut items = list.to_vec();
+    items.reverse();
+    Ok(items.into())
+}
+
+/// Equal numbers keep their order, and items stay written as they were.
+fn sort<S>(_: &mut EState<S>, list: LogoList) -> Result<LogoList, String> {
+    let mut items = Vec::with_capacity(list.len());
+    for item in &list {
+        let key = f64::from_logo(item.clone()).map_err(|_| format!("sort doesn't like {} in the list", item))?;
+        items.push((key, item.clone()));
+    }
+    items.sort_by(|a, b| a.0.total_cmp(&b.0));
+    Ok(items.into_iter().map(|(_, item)| item).collect())
+}
+
+fn remove<S>(state: &mut EState<S>, thing: LogoValue, list: LogoList) -> Result<LogoList, String> {
+    let mut items = Vec::with_capacity(list.len());
+    for item in &list {
+        if !equal(state, thing.clone(), item.clone())? {
+            items.push(item.clone());
+        }
+    }
+    Ok(items.into())
+}
+
+fn reduce<S>(state: &mut EState<S>, template: LogoValue, list: LogoList) -> Result<LogoValue, String> {
+    let mut items = list.to_vec();
+    let mut result = match items.pop() {
+        Some(last) => last,
+        None => return Err(format!("reduce doesn't like {} as input list", LogoValue::List(list)))
+    };
+    for item in items.into_iter().rev() {
+        result = apply_output(state, "reduce", &template, vec![item, result])?;
+    }
+    Ok(result)
+}
+
+/// Words are joined, quoted ones stay quoted.
+fn combine<S>(_: &mut EState<S>, thing1: LogoValue, thing2: LogoValue) -> Result<LogoValue, String> {
+    match (thing1, thing2) {
+        (thing1, LogoValue::List(list)) => Ok(LogoValue::List(list.fput(thing1))),
+        (LogoValue::List(list), _) => Err(format!("combine doesn't like {} as input thing1", LogoValue::List(list))),
+        (LogoValue::Word(a), LogoValue::Word(b)) => Ok(LogoValue::Word(Word(a.0 + &b.0))),
+        (a, b) => Ok(LogoValue::String(format!("{}{}", a, b)))
+    }
+}
+
+fn cascade<S>(state: &mut EState<S>, times: i32, template: LogoValue, value: LogoValue) -> Result<LogoValue, String> {
+    let mut value = value;
+    for _ in 0..times {
+        value = apply_output(state, "cascade", &template, vec![value])?;
+    }
+    Ok(value)
+}
+
+fn apply_output<S>(state: &mut EState<S>, name: &str, template: &LogoValue, inputs: Vec<LogoValue>) -> Result<LogoValue, String> {
+    match apply(state, template.clone(), inputs)? {
+        Some(value) => Ok(value),
+        None => Err(format!("{} didn't output to {}", template, name))
+    }
+}
+
+#[test]
+fn test_prelude() {
+    use crate::core::{LogoValue, Word};
+    use crate::executor::execute_str;
+    use crate::stdlib::add_stdlib;
+
+    let mut state = EState::new(());
+    add_stdlib(&mut state);
+    add_prelude(&mut state);
+    let word = |x: &str| LogoValue::Word(Word(x.to_string()));
+    execute_str(&mut state, "to twice :x\noutput :x * 2\nend",
+                "make 'a' sort [3 1 2] make 'b' reverse remove 2 [1 2 3 2] make 'c' reduce 'sum' [1 2 3 4] \
+                 make 'd' cascade 3 'twice' 1 make 'e' apply [[x y] output :x - :y] [5 3] make 'f' procedures").unwrap();
+    assert_eq!(state.vars["a"], LogoValue::List(vec![word("1"), word("2"), word("3")].into()));
+    assert_eq!(state.vars["b"], LogoValue::List(vec![word("3"), word("1")].into()));
+    assert_eq!(state.vars["c"], word("10"));
+    assert_eq!(state.vars["d"], word("8"));
+    assert_eq!(state.vars["e"], word("2"));
+    assert_eq!(state.vars["f"], LogoValue::List(vec![LogoValue::String("twice".to_string())].into()));
+    assert!(state.save_workspace().procedures.iter().all(|x| !x.contains("sort")));
+
+    // Long lists are sorted without deep recursion
+    let numbers: Vec<String> = (1..=1000).rev().map(|x| x.to_string()).collect();
+    execute_str(&mut state, "", &format!("make 'g' reverse sort [{}]", numbers.join(" "))).unwrap();
+    let sorted = match &state.vars["g"] {
+        LogoValue::List(list) => list.to_vec(),
+        _ => panic!()
+    };
+    assert_eq!(sorted.len(), 1000);
+    assert_eq!(sorted[0], word("1000"));
+    assert!(execute_str(&mut state, "", "make 'h' sort [2 a 1]").is_err());
+
+    // So are long lists given to remove, reduce and cascade
+    let ones = vec!["1"; 10_000].join(" ");
+    execute_str(&mut state, "", &format!("make 'i' reduce 'sum' [{}] make 'j' count remove 1 [2 {} 3] \
+                                          make 'k' cascade 10000 [[x] output :x + 1] 0", ones, ones)).unwrap();
+    assert_eq!(state.vars["i"], word("10000"));
+    assert_eq!(state.vars["j"], word("2"));
+    assert_eq!(state.vars["k"], word("10000"));
+
+    execute_str(&mut state, "", "make 'l' combine 'a' 'b' make 'm' combine 1 2 make 'n' combine 'a' [b]").unwrap();
+    assert_eq!(state.vars["l"], LogoValue::String("ab".to_string()));
+    assert_eq!(state.vars["m"], word("12"));
+    assert_eq!(state.vars["n"], LogoValue::List(vec![LogoValue::String("a".to_string()), word("b")].into()));
+    assert!(execute_str(&mut state, "", "make 'o' reduce [[x y] print :x] [1 2]").is_err());
+}
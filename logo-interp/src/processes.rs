@@ -0,0 +1,539 @@
+use std::collections::HashMap;
+use crate::core::{LogoConvertible, LogoList, LogoValue};
+use crate::events::run_timers;
+use crate::executor::{compile, compile_procedure, execute_expr, Code, Op};
+use crate::executor_state::{EState, Function};
+use crate::policy::{Category, set_category};
+use crate::shared::Shared;
+use crate::symbols::Symbol;
+
+/// Primitives for running several scripts at once: `launch`, `forever`, `wait`, `cancel`, `stopall` and `processes`.
+pub(crate) fn add_process_primitives<S: 'static>(functions: &mut HashMap<String, Function<S>>) {
+    functions.insert("launch".to_string(), Function::new("launch", &["instructions", "turtle"], launch)
+        .with_help("Starts a process running the instructions, it takes turns with the other processes"));
+    functions.insert("forever".to_string(), Function::new("forever", &["instructions", "turtle"], forever)
+        .with_help("Starts a process running the instructions over and over until it's cancelled"));
+    functions.insert("wait".to_string(), Function::new("wait", &["ticks"], wait)
+        .with_help("Pauses for the number of ticks, sixtieths of a second, while other processes run"));
+    functions.insert("cancel".to_string(), Function::new("cancel", &["instructions"], cancel)
+        .with_help("Stops processes launched with the instructions"));
+    functions.insert("stopall".to_string(), Function::new("stopall", &[], stopall)
+        .with_help("Stops all processes, timers and conditions of when"));
+    functions.insert("processes".to_string(), Function::new("processes", &[], processes)
+        .with_help("Outputs the instructions of running processes"));
+    set_category(functions, Category::Control, &["launch", "forever", "cancel", "stopall", "processes"]);
+    set_category(functions, Category::Time, &["wait"]);
+}
+
+/// Turtles of the host, each process drives its own one. Numbers of turtles are the ones of `tell` and `who`.
+pub struct Turtles<S> {
+    /// Makes a turtle like the current one, outputs its number.
+    pub hatch: fn(&mut S) -> u32,
+    /// Makes the turtle current, outputs the number of the one that was.
+    pub select: fn(&mut S, u32) -> u32
+}
+
+impl<S> EState<S> {
+    /// Gives processes turtles of their own, without them all processes drive the current turtle.
+    pub fn set_turtles(&mut self, turtles: Turtles<S>) {
+        self.turtles = Some(turtles);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Repeat {
+    Times(i32),
+    Forever
+}
+
+/// Instructions a process runs, with the position of the next one.
+struct Frame {
+    code: Shared<[Op]>,
+    pos: usize,
+    repeat: Repeat,
+    /// Inputs of the procedure the frame runs with their values.
+    inputs: Vec<(Symbol, LogoValue)>,
+    /// Whether the frame runs a procedure, `stop` leaves it.
+    procedure: bool
+}
+
+impl Frame {
+    fn is_finished(&self) -> bool {
+        self.pos >= self.code.len() && match self.repeat {
+            Repeat::Forever => self.code.is_empty(),
+            Repeat::Times(n) => n <= 1
+        }
+    }
+}
+
+/// Interpreter process started by `launch` or `forever`.
+pub struct Process {
+    pub id: u32,
+    /// Instructions the process was launched with.
+    pub code: LogoList,
+    /// Turtle the process drives, if the host has turtles.
+    pub turtle: Option<u32>,
+    frames: Vec<Frame>,
+    wake_tick: u64
+}
+
+impl Process {
+    /// Finds the next instruction, restarting or leaving frames which are done. `None` if the process has finished.
+    fn next_instruction(&mut self) -> Option<(Shared<[Op]>, usize)> {
+        loop {
+            let frame = self.frames.last_mut()?;
+            if frame.pos < frame.code.len() {
+                return Some((frame.code.clone(), frame.pos));
+            }
+            frame.pos = 0;
+            match frame.repeat {
+                Repeat::Forever if !frame.code.is_empty() => {},
+                Repeat::Times(n) if n > 1 => frame.repeat = Repeat::Times(n - 1),
+                _ => {
+                    self.frames.pop();
+                }
+            }
+        }
+    }
+
+    /// Enters a frame, leaving the ones which are done first, so a procedure calling itself last
+    /// runs in constant space. The new frame takes over inputs and procedures of the frames it replaces.
+    fn enter(&mut self, mut frame: Frame) {
+        let mut inputs = Vec::new();
+        while self.frames.last().is_some_and(|frame| frame.is_finished()) {
+            let done = self.frames.pop().unwrap();
+            inputs.splice(0..0, done.inputs);
+            frame.procedure |= done.procedure;
+        }
+        inputs.append(&mut frame.inputs);
+        // Inputs hidden by later ones with the same name can't be seen any more
+        let mut idx = 0;
+        while idx < inputs.len() {
+            match inputs[idx + 1..].iter().any(|(symbol, _)| *symbol == inputs[idx].0) {
+                true => drop(inputs.remove(idx)),
+                false => idx += 1
+            }
+        }
+        frame.inputs = inputs;
+        self.frames.push(frame);
+    }
+}
+
+/// Processes take turns running one instruction each tick, in the order they were launched, so runs are
+/// deterministic. `repeat`, `if`, `ifelse`, `wait` and calls of procedures at the top of a process or of a procedure
+/// it runs are stepped through, other instructions run to the end in one turn, and a `wait` in them
+/// pauses the process once they finish.
+#[derive(Default)]
+pub struct Scheduler {
+    processes: Vec<Process>,
+    next_id: u32,
+    tick: u64,
+    /// Process running an instruction.
+    current: Option<u32>,
+    in_tick: bool
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Scheduler::default()
+    }
+
+    /// Number of ticks run so far.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.processes.is_empty()
+    }
+
+    pub fn processes(&self) -> &[Process] {
+        &self.processes
+    }
+
+    pub fn stop_all(&mut self) {
+        self.processes.clear();
+    }
+
+    /// Stops processes launched with the instructions, returns how many were stopped.
+    pub fn cancel(&mut self, code: &LogoList) -> usize {
+        let count = self.processes.len();
+        self.processes.retain(|process| process.code != *code);
+        count - self.processes.len()
+    }
+
+    fn launch(&mut self, code: LogoList, transformed: Shared<[Op]>, repeat: Repeat, turtle: Option<u32>) -> u32 {
+        self.next_id += 1;
+        self.processes.push(Process {
+            id: self.next_id,
+            code,
+            turtle,
+            frames: vec![Frame {code: transformed, pos: 0, repeat, inputs: Vec::new(), procedure: false}],
+            wake_tick: 0
+        });
+        self.next_id
+    }
+
+    fn get_mut(&mut self, id: u32) -> Option<&mut Process> {
+        self.processes.iter_mut().find(|process| process.id == id)
+    }
+
+    fn remove(&mut self, id: u32) {
+        self.processes.retain(|process| process.id != id);
+    }
+}
+
+/// Runs processes for the number of ticks, a process which fails is stopped and its error is returned.
+pub fn run_ticks<S>(state: &mut EState<S>, ticks: u32) -> Result<(), String> {
+    for _ in 0..ticks {
+        run_tick(state).map_err(|err| state.locale.translate(&err))?;
+    }
+    Ok(())
+}
+
+fn run_tick<S>(state: &mut EState<S>) -> Result<(), String> {
+    state.scheduler.tick += 1;
+    state.scheduler.in_tick = true;
+    let result = run_processes(state).and_then(|_| run_timers(state, state.scheduler.tick));
+    state.scheduler.in_tick = false;
+    result
+}
+
+fn run_processes<S>(state: &mut EState<S>) -> Result<(), String> {
+    let tick = state.scheduler.tick;
+    let ids: Vec<u32> = state.scheduler.processes.iter()
+        .filter(|process| process.wake_tick <= tick)
+        .map(|process| process.id)
+        .collect();
+    for id in ids {
+        state.scheduler.current = Some(id);
+        let result = step_process(state, id);
+        state.scheduler.current = None;
+        if let Err(err) = result {
+            state.scheduler.remove(id);
+            return Err(err);
+        }
+    }
+    Ok(())
+}
+
+enum Step {
+    Next,
+    Enter(Frame),
+    Sleep(u64),
+    /// `stop` or `output` left the procedure the process is in.
+    Return
+}
+
+/// Name of `repeat`, `if`, `ifelse` or `wait` if the symbol calls one of them.
+fn control_word<S>(state: &EState<S>, symbol: Symbol) -> Option<&'static str> {
+    let word = state.symbols.borrow().name(symbol);
+    let name = state.locale.primitive_name(&word);
+    ["repeat", "if", "ifelse", "wait"].into_iter().find(|control| {
+        *control == name && state.functions.get(control).is_some_and(|fun| state.is_allowed(name, fun))
+    })
+}
+
+fn take_input<S, T: LogoConvertible>(state: &mut EState<S>, it: &mut std::iter::Peekable<std::slice::Iter<Op>>,
+                                     word: &str, param: &str) -> Result<T, String> {
+    match execute_expr(state, it)? {
+        Some(value) => T::from_logo(value.clone()).map_err(|_| format!("{} doesn't like {} as input {}", word, value, param)),
+        None => Err(format!("Missing argument for {}", word))
+    }
+}
+
+fn step_process<S>(state: &mut EState<S>, id: u32) -> Result<(), String> {
+    let next = match state.scheduler.get_mut(id) {
+        Some(process) => process.next_instruction(),
+        None => return Ok(())
+    };
+    let (code, pos) = match next {
+        Some(next) => next,
+        None => {
+            state.scheduler.remove(id);
+            return Ok(());
+        }
+    };
+
+    let hidden = set_inputs(state, id)?;
+    let previous = select_turtle(state, id);
+    let result = run_instruction(state, &code, pos);
+    if let Some(previous) = previous {
+        // `tell` in the process changes the turtle it drives
+        let turtle = select_turtle_number(state, previous);
+        if let Some(process) = state.scheduler.get_mut(id) {
+            process.turtle = turtle;
+        }
+    }
+    keep_inputs(state, id, hidden);
+    let (step, consumed) = result?;
+
+    let tick = state.scheduler.tick;
+    let process = match state.scheduler.get_mut(id) {
+        Some(process) => process,
+        None => return Ok(())
+    };
+    if let Some(frame) = process.frames.last_mut() {
+        frame.pos += consumed;
+    }
+    match step {
+        Step::Next => {},
+        Step::Enter(frame) => process.enter(frame),
+        Step::Sleep(ticks) => process.wake_tick = tick + ticks,
+        Step::Return => match process.frames.iter().rposition(|frame| frame.procedure) {
+            Some(idx) => process.frames.truncate(idx),
+            // `stop` at the top of a process ends it
+            None => state.scheduler.remove(id)
+        }
+    }
+    Ok(())
+}
+
+/// Makes the turtle of the process current, outputs the number of the one that was.
+fn select_turtle<S>(state: &mut EState<S>, id: u32) -> Option<u32> {
+    let turtle = state.scheduler.get_mut(id)?.turtle?;
+    select_turtle_number(state, turtle)
+}
+
+fn select_turtle_number<S>(state: &mut EState<S>, turtle: u32) -> Option<u32> {
+    let select = state.turtles.as_ref()?.select;
+    Some(select(&mut state.state, turtle))
+}
+
+/// Runs the instruction at `pos`, outputs what the process does next and how many values it took.
+fn run_instruction<S>(state: &mut EState<S>, code: &[Op], pos: usize) -> Result<(Step, usize), String> {
+    let mut it = code[pos..].iter().peekable();
+    let symbol = match &code[pos] {
+        Op::Call(symbol, _) => Some(*symbol),
+        _ => None
+    };
+    let step = match symbol.and_then(|symbol| control_word(state, symbol)) {
+        Some("repeat") => {
+            it.next();
+            let times: i32 = take_input(state, &mut it, "repeat", "input1")?;
+            let list: LogoList = take_input(state, &mut it, "repeat", "input2")?;
+            match times > 0 {
+                true => Step::Enter(Frame {code: compile(state, &list)?.ops, pos: 0, repeat: Repeat::Times(times), inputs: Vec::new(), procedure: false}),
+                false => Step::Next
+            }
+        },
+        Some("if") => {
+            it.next();
+            let condition: bool = take_input(state, &mut it, "if", "input1")?;
+            let list: LogoList = take_input(state, &mut it, "if", "input2")?;
+            match condition {
+                true => Step::Enter(Frame {code: compile(state, &list)?.ops, pos: 0, repeat: Repeat::Times(1), inputs: Vec::new(), procedure: false}),
+                false => Step::Next
+            }
+        },
+        Some("ifelse") => {
+            it.next();
+            let condition: bool = take_input(state, &mut it, "ifelse", "input1")?;
+            let list_true: LogoList = take_input(state, &mut it, "ifelse", "input2")?;
+            let list_false: LogoList = take_input(state, &mut it, "ifelse", "input3")?;
+            let list = if condition { list_true } else { list_false };
+            Step::Enter(Frame {code: compile(state, &list)?.ops, pos: 0, repeat: Repeat::Times(1), inputs: Vec::new(), procedure: false})
+        },
+        Some("wait") => {
+            it.next();
+            let ticks: i32 = take_input(state, &mut it, "wait", "ticks")?;
+            Step::Sleep(ticks.max(0) as u64)
+        },
+        _ => match symbol.and_then(|symbol| procedure_frame(state, symbol)) {
+            Some((word, code)) => {
+                it.next();
+                let mut inputs = Vec::with_capacity(code.params.len());
+                for (_, symbol) in code.params.iter() {
+                    match execute_expr(state, &mut it)? {
+                        Some(value) => inputs.push((*symbol, value)),
+                        None => return Err(format!("Missing argument for {}", word))
+                    }
+                }
+                Step::Enter(Frame {code: code.ops, pos: 0, repeat: Repeat::Times(1), inputs, procedure: true})
+            },
+            None => match execute_expr(state, &mut it) {
+                Ok(Some(val)) => return Err(format!("Don't know what to do with {}", val)),
+                Ok(None) => Step::Next,
+                Err(err) if err == "Output" => match state.output.take() {
+                    Some(val) => return Err(format!("Don't know what to do with {}", val)),
+                    None => Step::Return
+                },
+                Err(err) => return Err(err)
+            }
+        }
+    };
+    Ok((step, code.len() - pos - it.len()))
+}
+
+/// Name and code of the procedure if the symbol calls one which the process can step through.
+fn procedure_frame<S>(state: &EState<S>, symbol: Symbol) -> Option<(Shared<str>, Code)> {
+    if state.get_function_symbol(symbol).is_some() {
+        return None;
+    }
+    let logo_proc = state.logo_procedures.get_symbol(symbol).filter(|logo_proc| !logo_proc.is_macro)?;
+    let code = compile_procedure(state, logo_proc).ok()?;
+    Some((state.symbols.borrow().name(symbol), code))
+}
+
+/// Sets the inputs of procedures the process is in, outputs the values they hid.
+fn set_inputs<S>(state: &mut EState<S>, id: u32) -> Result<Vec<(Symbol, Option<LogoValue>)>, String> {
+    let inputs: Vec<(Symbol, LogoValue)> = match state.scheduler.get_mut(id) {
+        Some(process) => process.frames.iter().flat_map(|frame| frame.inputs.iter().cloned()).collect(),
+        None => Vec::new()
+    };
+    let mut hidden = Vec::with_capacity(inputs.len());
+    for (symbol, value) in inputs {
+        let old = state.vars.get_symbol(symbol).cloned();
+        if let Err(err) = state.set_input(symbol, value) {
+            for (symbol, value) in hidden.into_iter().rev() {
+                state.restore_var(symbol, value);
+            }
+            return Err(err);
+        }
+        hidden.push((symbol, old));
+    }
+    Ok(hidden)
+}
+
+/// Puts back the variables inputs of the process hid, keeping values the instruction gave the inputs
+/// for its next turn. Processes take turns, so inputs of one don't show in another.
+fn keep_inputs<S>(state: &mut EState<S>, id: u32, hidden: Vec<(Symbol, Option<LogoValue>)>) {
+    let mut values = Vec::with_capacity(hidden.len());
+    for (symbol, value) in hidden.into_iter().rev() {
+        values.push(state.vars.get_symbol(symbol).cloned());
+        state.restore_var(symbol, value);
+    }
+    if let Some(process) = state.scheduler.get_mut(id) {
+        let inputs = process.frames.iter_mut().flat_map(|frame| frame.inputs.iter_mut());
+        for ((_, input), value) in inputs.zip(values.into_iter().rev()) {
+            if let Some(value) = value {
+                *input = value;
+            }
+        }
+    }
+}
+
+/// Without a turtle given the process drives a new one, made like the current turtle.
+fn launch_with<S>(state: &mut EState<S>, name: &str, code: LogoList, repeat: Repeat, turtle: Option<i32>) -> Result<(), String> {
+    let transformed = compile(state, &code)?.ops;
+    let turtle = match turtle {
+        Some(turtle) if turtle < 0 => return Err(format!("{} doesn't like {} as input turtle", name, turtle)),
+        Some(turtle) => Some(turtle as u32),
+        None => state.turtles.as_ref().map(|turtles| turtles.hatch).map(|hatch| hatch(&mut state.state))
+    };
+    state.scheduler.launch(code, transformed, repeat, turtle);
+    Ok(())
+}
+
+fn launch<S>(state: &mut EState<S>, code: LogoList, turtle: Option<i32>) -> Result<(), String> {
+    launch_with(state, "launch", code, Repeat::Times(1), turtle)
+}
+
+fn forever<S>(state: &mut EState<S>, code: LogoList, turtle: Option<i32>) -> Result<(), String> {
+    launch_with(state, "forever", code, Repeat::Forever, turtle)
+}
+
+/// Outside of processes other processes run meanwhile. In a process, `wait` in the middle of an instruction
+/// pauses it once the instruction finishes, handlers of timers don't pause.
+fn wait<S>(state: &mut EState<S>, ticks: i32) -> Result<(), String> {
+    let ticks = ticks.max(0) as u64;
+    let tick = state.scheduler.tick;
+    if let Some(id) = state.scheduler.current {
+        if let Some(process) = state.scheduler.get_mut(id) {
+            process.wake_tick = process.wake_tick.max(tick) + ticks;
+        }
+        return Ok(());
+    }
+    if state.scheduler.in_tick {
+        return Ok(());
+    }
+    for _ in 0..ticks {
+        run_tick(state)?;
+    }
+    Ok(())
+}
+
+fn cancel<S>(state: &mut EState<S>, code: LogoList) -> Result<(), String> {
+    state.scheduler.cancel(&code);
+    Ok(())
+}
+
+fn stopall<S>(state: &mut EState<S>) -> Result<(), String> {
+    state.scheduler.stop_all();
+    state.events.clear_timers();
+    Ok(())
+}
+
+fn processes<S>(state: &mut EState<S>) -> Result<Vec<LogoValue>, String> {
+    Ok(state.scheduler.processes().iter().map(|process| LogoValue::List(process.code.clone())).collect())
+}
+
+#[test]
+fn test_processes() {
+    use crate::core::Word;
+    use crate::executor::execute_str;
+    use crate::stdlib::add_stdlib;
+
+    let mut state = EState::new(Vec::<String>::new());
+    add_stdlib(&mut state);
+    state.functions.insert("add", Function::from_proc1(|s: &mut EState<Vec<String>>, x: String| -> Result<(), String> {
+        s.state.push(x);
+        Ok(())
+    }));
+    execute_str(&mut state, "", "launch [repeat 3 [add 'a' wait 2]] forever [add 'b'] add 'main'").unwrap();
+    assert_eq!(state.state, vec!["main"]);
+    run_ticks(&mut state, 4).unwrap();
+    assert_eq!(state.state.join(" "), "main b a b b b");
+    assert_eq!(state.scheduler.processes().len(), 2);
+
+    execute_str(&mut state, "", "cancel [add 'b'] make 'p' count processes").unwrap();
+    assert_eq!(state.vars["p"], LogoValue::Word(Word("1".to_string())));
+    state.state.clear();
+    execute_str(&mut state, "", "wait 10 add 'done'").unwrap();
+    assert_eq!(state.state, vec!["a", "a", "done"]);
+    assert!(state.scheduler.is_empty());
+
+    execute_str(&mut state, "", "forever [add 'c'] stopall").unwrap();
+    assert!(state.scheduler.is_empty());
+    execute_str(&mut state, "", "launch [add 1 / 0 nosuch]").unwrap();
+    assert!(run_ticks(&mut state, 2).is_err());
+    assert!(state.scheduler.is_empty());
+
+    state.state.clear();
+    execute_str(&mut state, "", "launch [repeat 0 [add 'x'] add 'y']").unwrap();
+    run_ticks(&mut state, 3).unwrap();
+    assert_eq!(state.state, vec!["y"]);
+}
+
+#[test]
+fn test_process_procedures() {
+    use crate::executor::execute_str;
+    use crate::stdlib::add_stdlib;
+
+    let mut state = EState::new(Vec::<String>::new());
+    add_stdlib(&mut state);
+    state.functions.insert("add", Function::from_proc1(|s: &mut EState<Vec<String>>, x: LogoValue| -> Result<(), String> {
+        s.state.push(x.to_string());
+        Ok(())
+    }));
+    // Procedures waiting take turns, each process sees its own inputs
+    let procedures = "to beep :word repeat 3 [add :word wait 1] end \
+                      to countdown :n if :n > 0 [add :n wait 1 countdown :n - 1] end";
+    execute_str(&mut state, procedures, "launch [beep 'ping'] launch [beep 'pong']").unwrap();
+    run_ticks(&mut state, 12).unwrap();
+    assert_eq!(state.state.join(" "), "ping pong ping pong ping pong");
+    assert!(state.scheduler.is_empty());
+    assert!(!state.vars.contains_key("word"));
+
+    // Each instruction stepped through takes a turn, `if` and calls too
+    state.state.clear();
+    execute_str(&mut state, procedures, "launch [countdown 3] launch [beep 'b' add 'done']").unwrap();
+    run_ticks(&mut state, 20).unwrap();
+    assert_eq!(state.state.join(" "), "3 b b 2 b done 1");
+    assert!(state.scheduler.is_empty());
+
+    // Procedures calling themselves last don't pile up frames
+    execute_str(&mut state, "to spin :n if :n > 0 [spin :n - 1] end", "launch [spin 10000]").unwrap();
+    run_ticks(&mut state, 10).unwrap();
+    assert!(state.scheduler.processes()[0].frames.len() <= 3);
+}
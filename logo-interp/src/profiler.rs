@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+use web_time::Instant;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ProfileEntry {
+    pub calls: u64,
+    pub inclusive: Duration,
+    pub exclusive: Duration,
+    pub primitive: bool
+}
+
+struct Frame {
+    name: String,
+    start: Instant,
+    children: Duration
+}
+
+#[derive(Default)]
+pub struct Profiler {
+    enabled: bool,
+    entries: HashMap<String, ProfileEntry>,
+    stack: Vec<Frame>
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Profiler::default()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Clears the collected data and starts counting calls.
+    pub fn start(&mut self) {
+        self.entries.clear();
+        self.stack.clear();
+        self.enabled = true;
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Stops counting calls, the collected data is kept until the next `start`.
+    pub fn stop(&mut self) {
+        self.enabled = false;
+        self.stack.clear();
+    }
+
+    pub fn enter(&mut self, name: &str, primitive: bool) {
+        let entry = self.entries.entry(name.to_string()).or_default();
+        entry.calls += 1;
+        entry.primitive = primitive;
+        self.stack.push(Frame {name: name.to_string(), start: Instant::now(), children: Duration::ZERO});
+    }
+
+    pub fn exit(&mut self) {
+        let frame = match self.stack.pop() {
+            Some(frame) => frame,
+            None => return
+        };
+        let elapsed = frame.start.elapsed();
+        if let Some(parent) = self.stack.last_mut() {
+            parent.children += elapsed;
+        }
+        // Recursive calls are already covered by the outermost frame of the same procedure
+        let recursive = self.stack.iter().any(|f| f.name == frame.name);
+        let entry = self.entries.get_mut(&frame.name).unwrap();
+        if !recursive {
+            entry.inclusive += elapsed;
+        }
+        entry.exclusive += elapsed.saturating_sub(frame.children);
+    }
+
+    pub fn report(&self) -> ProfileReport {
+        let mut entries: Vec<(String, ProfileEntry)> = self.entries.iter()
+            .map(|(name, entry)| (name.clone(), *entry))
+            .collect();
+        entries.sort_by(|a, b| b.1.exclusive.cmp(&a.1.exclusive).then(a.0.cmp(&b.0)));
+        ProfileReport {entries}
+    }
+}
+
+/// Profiling results sorted by exclusive time, most expensive first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfileReport {
+    pub entries: Vec<(String, ProfileEntry)>
+}
+
+impl ProfileReport {
+    pub fn get(&self, name: &str) -> Option<&ProfileEntry> {
+        self.entries.iter().find(|(entry_name, _)| entry_name == name).map(|(_, entry)| entry)
+    }
+}
+
+impl Display for ProfileReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name_width = self.entries.iter().map(|(name, _)| name.len()).max().unwrap_or(0).max(9);
+        writeln!(f, "{:<name_width$} {:>4} {:>10} {:>14} {:>14}",
+                 "procedure", "kind", "calls", "inclusive ms", "exclusive ms")?;
+        for (name, entry) in &self.entries {
+            writeln!(f, "{:<name_width$} {:>4} {:>10} {:>14.3} {:>14.3}",
+                     name,
+                     if entry.primitive { "prim" } else { "proc" },
+                     entry.calls,
+                     entry.inclusive.as_secs_f64() * 1000.0,
+                     entry.exclusive.as_secs_f64() * 1000.0)?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_profiler() {
+    use crate::executor::execute_str;
+    use crate::executor_state::EState;
+    use crate::stdlib::add_stdlib;
+
+    let mut state = EState::new(());
+    add_stdlib(&mut state);
+    state.profiler.start();
+    execute_str(&mut state, "to fact :n if :n < 2 [output 1] output :n * (fact :n - 1) end",
+                "repeat 3 [make \"x fact 5]").unwrap();
+    state.profiler.stop();
+
+    let report = state.profiler.report();
+    assert_eq!(report.get("repeat").unwrap().calls, 1);
+    assert_eq!(report.get("fact").unwrap().calls, 15);
+    assert!(!report.get("fact").unwrap().primitive);
+    assert!(report.get("make").unwrap().primitive);
+    assert!(report.get("fact").unwrap().inclusive <= report.get("repeat").unwrap().inclusive);
+    assert!(format!("{}", report).contains("fact"));
+}
+
+#[test]
+fn test_profile_primitive() {
+    use crate::core::LogoValue;
+    use crate::executor::execute_str;
+    use crate::executor_state::{EState, Function};
+    use crate::stdlib::add_stdlib;
+
+    let mut state = EState::new(Vec::<String>::new());
+    add_stdlib(&mut state);
+    state.functions.insert("print", Function::from_proc1(|s: &mut EState<Vec<String>>, x: LogoValue| -> Result<(), String> {
+        s.state.push(x.to_string());
+        Ok(())
+    }));
+    execute_str(&mut state, "to square :x output :x * :x end", "profile [repeat 3 [make 'y' square 4]]").unwrap();
+    assert_eq!(state.state.len(), 1);
+    let lines: Vec<&str> = state.state[0].lines().collect();
+    assert!(lines[0].starts_with("procedure"));
+    assert!(lines.iter().any(|line| line.starts_with("square") && line.contains(" proc ") && line.contains(" 3 ")));
+    assert!(!state.profiler.is_enabled());
+}
@@ -0,0 +1,72 @@
+use std::ops::{Deref, DerefMut};
+
+/// Reference counted pointer of the interpreter, `Arc` with the `sync` feature and `Rc` without it.
+#[cfg(not(feature = "sync"))]
+pub type Shared<T> = std::rc::Rc<T>;
+#[cfg(feature = "sync")]
+pub type Shared<T> = std::sync::Arc<T>;
+
+/// Bound of primitives and file systems, `Send + Sync` with the `sync` feature, so `EState` can be moved
+/// to another thread. Without the feature every type has it.
+#[cfg(not(feature = "sync"))]
+pub trait MaybeSync {}
+#[cfg(not(feature = "sync"))]
+impl<T: ?Sized> MaybeSync for T {}
+#[cfg(feature = "sync")]
+pub trait MaybeSync: Send + Sync {}
+#[cfg(feature = "sync")]
+impl<T: Send + Sync + ?Sized> MaybeSync for T {}
+
+#[cfg(not(feature = "sync"))]
+type Cell<T> = std::cell::RefCell<T>;
+#[cfg(feature = "sync")]
+type Cell<T> = std::sync::Mutex<T>;
+
+/// Mutable value behind a `Shared` pointer, a `RefCell` or with the `sync` feature a `Mutex`.
+/// Borrows mustn't overlap, with a `Mutex` even shared ones would deadlock.
+#[derive(Debug, Default)]
+pub struct SharedCell<T>(Cell<T>);
+
+impl<T> SharedCell<T> {
+    pub fn new(value: T) -> Self {
+        SharedCell(Cell::new(value))
+    }
+
+    #[cfg(not(feature = "sync"))]
+    pub fn borrow(&self) -> impl Deref<Target = T> + '_ {
+        self.0.borrow()
+    }
+
+    #[cfg(not(feature = "sync"))]
+    pub fn borrow_mut(&self) -> impl DerefMut<Target = T> + '_ {
+        self.0.borrow_mut()
+    }
+
+    #[cfg(feature = "sync")]
+    pub fn borrow(&self) -> impl Deref<Target = T> + '_ {
+        self.0.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    #[cfg(feature = "sync")]
+    pub fn borrow_mut(&self) -> impl DerefMut<Target = T> + '_ {
+        self.0.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+#[cfg(feature = "sync")]
+#[test]
+fn test_send() {
+    use crate::core::{LogoValue, Word};
+    use crate::executor::execute_str;
+    use crate::executor_state::EState;
+    use crate::stdlib::add_stdlib;
+
+    let mut state = EState::new(());
+    add_stdlib(&mut state);
+    let handle = std::thread::spawn(move || {
+        execute_str(&mut state, "to twice :x output :x * 2 end", "make 'a' twice 21").unwrap();
+        state
+    });
+    let state = handle.join().unwrap();
+    assert_eq!(state.vars["a"], LogoValue::Word(Word("42".to_string())));
+}
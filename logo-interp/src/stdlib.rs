@@ -1,83 +1,198 @@
+use std::collections::HashMap;
 use rand::{Rng, thread_rng};
 use crate::core::*;
-use crate::executor::execute;
+use crate::dialect::Dialect;
+use crate::data::add_data_primitives;
+use crate::processes::add_process_primitives;
+use crate::events::add_event_primitives;
+use crate::docs::{attach_docs, STDLIB_DOCS};
+#[cfg(feature = "bigint")]
+use crate::bigint::{difference, equal, greater, int, less, minus, power, product, remainder, round, sum};
+use crate::executor::{call, call_procedure, execute};
+use crate::formatter::{Formatter, string_text};
 use crate::executor_state::*;
+use crate::library::LogoLibrary;
+use crate::policy::{Category, set_category};
+
+/// Primitives that don't depend on the host, `add_stdlib` installs them.
+pub struct StdLib;
+
+impl<S: 'static> LogoLibrary<S> for StdLib {
+    fn name(&self) -> &str {
+        "stdlib"
+    }
+
+    fn version(&self) -> &str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn primitives(&self, dialect: Dialect) -> HashMap<String, Function<S>> {
+        let mut functions = HashMap::new();
+        functions.insert("repeat".to_string(), Function::from_proc2(repeat));
+        functions.insert("show".to_string(), Function::from_proc1(show));
+        functions.insert("print".to_string(), Function::from_proc1(print));
+        functions.insert("pr".to_string(), Function::from_proc1(print));
+        functions.insert("profile".to_string(), Function::from_proc1(profile));
+        functions.insert("help".to_string(), Function::new("help", &["name"], help));
+
+        functions.insert("abs".to_string(), Function::from_fn1(abs));
+        functions.insert("arctan".to_string(), Function::from_fn1(arctan));
+        functions.insert("cos".to_string(), Function::from_fn1(cos));
+        functions.insert("difference".to_string(), Function::from_fn2(difference));
+        functions.insert("exp".to_string(), Function::from_fn1(exp));
+        functions.insert("greater?".to_string(), Function::from_fn2(greater));
+        functions.insert("less?".to_string(), Function::from_fn2(less));
+        functions.insert("int".to_string(), Function::from_fn1(int));
+        functions.insert("log".to_string(), Function::from_fn1(log));
+        functions.insert("ln".to_string(), Function::from_fn1(ln));
+        functions.insert("minus".to_string(), Function::from_fn1(minus));
+        functions.insert("pi".to_string(), Function::from_fn(pi));
+        functions.insert("power".to_string(), Function::from_fn2(power));
+        functions.insert("product".to_string(), Function::new("product", &["a", "b", "rest"], product)
+            .with_help("Outputs the product of the inputs, (product a b ...) multiplies any number of them"));
+        functions.insert("quotient".to_string(), Function::from_fn2(quotient));
+        functions.insert("remainder".to_string(), Function::from_fn2(remainder));
+        functions.insert("random".to_string(), Function::from_fn1(random));
+        functions.insert("round".to_string(), Function::from_fn1(round));
+        functions.insert("sin".to_string(), Function::from_fn1(sin));
+        functions.insert("sqrt".to_string(), Function::from_fn1(sqrt));
+        functions.insert("sum".to_string(), Function::new("sum", &["a", "b", "rest"], sum)
+            .with_help("Outputs the sum of the inputs, (sum a b ...) adds any number of them"));
+        functions.insert("tan".to_string(), Function::from_fn1(tan));
+
+        functions.insert("bf".to_string(), Function::from_fn1(bf));
+        functions.insert("butfirst".to_string(), Function::from_fn1(bf));
+        functions.insert("bl".to_string(), Function::from_fn1(bl));
+        functions.insert("butlast".to_string(), Function::from_fn1(bl));
+        functions.insert("count".to_string(), Function::from_fn1(count));
+        functions.insert("empty?".to_string(), Function::from_fn1(empty));
+        functions.insert("equal?".to_string(), Function::from_fn2(equal));
+        functions.insert("identical?".to_string(), Function::from_fn2(equal));
+        functions.insert("first".to_string(), Function::from_fn1(first));
+        functions.insert("fput".to_string(), Function::from_fn2(fput));
+        functions.insert("item".to_string(), Function::from_fn2(item));
+        functions.insert("last".to_string(), Function::from_fn1(last));
+        functions.insert("list".to_string(), Function::new("list", &["a", "b", "rest"], list)
+            .with_help("Outputs a list joining the input lists, (list a b ...) joins any number of them"));
+        functions.insert("list?".to_string(), Function::from_fn1(is_list));
+        functions.insert("lput".to_string(), Function::from_fn2(lput));
+        functions.insert("member?".to_string(), Function::from_fn2(member));
+        functions.insert("number?".to_string(), Function::from_fn1(number));
+        functions.insert("pick".to_string(), Function::from_fn1(pick));
+        functions.insert("word?".to_string(), Function::from_fn1(word));
+
+        functions.insert("and".to_string(), Function::new("and", &["a", "b", "rest"], and)
+            .with_help("Outputs true if all inputs are true"));
+        functions.insert("or".to_string(), Function::new("or", &["a", "b", "rest"], or)
+            .with_help("Outputs true if any input is true"));
+        functions.insert("not".to_string(), Function::from_fn1(not));
+        functions.insert("if".to_string(), Function::from_proc2(if_fn));
+        functions.insert("ifelse".to_string(), Function::from_proc3(if_else_fn));
+
+        functions.insert("make".to_string(), Function::from_proc2(make));
+        functions.insert("clearname".to_string(), Function::from_proc1(clearname));
+        functions.insert("clearnames".to_string(), Function::from_proc(clearnames));
+        functions.insert("name?".to_string(), Function::from_fn1(name));
+        functions.insert("names".to_string(), Function::from_fn(names));
+        functions.insert("thing".to_string(), Function::from_fn1(thing));
+
+        functions.insert("pprop".to_string(), Function::from_proc3(pprop));
+        functions.insert("gprop".to_string(), Function::from_fn2(gprop));
+        functions.insert("remprop".to_string(), Function::from_proc2(remprop));
+        functions.insert("plist".to_string(), Function::from_fn1(plist));
+
+        functions.insert("po".to_string(), Function::from_proc1(po));
+        functions.insert("pots".to_string(), Function::from_proc(pots));
+        functions.insert("pons".to_string(), Function::from_proc(pons));
+        functions.insert("pops".to_string(), Function::from_proc(pops));
+        functions.insert("save".to_string(), Function::from_proc1(save));
+        functions.insert("load".to_string(), Function::new("load", &["path", "namespace"], load));
+
+        functions.insert("output".to_string(), Function::from_proc1(output));
+        functions.insert("stop".to_string(), Function::from_proc(stop));
+        functions.insert("apply".to_string(), Function::new("apply", &["template", "inputs"], apply)
+            .with_help("Runs the procedure or primitive named by the template, or a list like [[x y] output :x + :y], with the inputs"));
+        functions.insert(".defmacro".to_string(), Function::new(".defmacro", &["name", "text"], defmacro)
+            .with_help("Defines a macro, text is like [[x] [output list :x :x]], the list a macro outputs is run in place of the call"));
+        functions.insert("procedures".to_string(), Function::new("procedures", &[], procedures)
+            .with_help("Outputs names of procedures, without buried ones of libraries"));
+
+        if dialect != Dialect::Native {
+            functions.insert("op".to_string(), Function::from_proc1(output));
+            // Other Logos compare identity of lists there, which has no meaning here
+            functions.insert("identical?".to_string(), Function::unavailable("identical?", &["thing1", "thing2"], dialect));
+        }
+        if dialect == Dialect::UCBLogo || dialect == Dialect::FMSLogo {
+            functions.insert("emptyp".to_string(), Function::from_fn1(empty));
+            functions.insert("equalp".to_string(), Function::from_fn2(equal));
+            functions.insert("listp".to_string(), Function::from_fn1(is_list));
+            functions.insert("memberp".to_string(), Function::from_fn2(member));
+            functions.insert("namep".to_string(), Function::from_fn1(name));
+            functions.insert("numberp".to_string(), Function::from_fn1(number));
+            functions.insert("wordp".to_string(), Function::from_fn1(word));
+        }
+
+        add_data_primitives(&mut functions);
+        add_process_primitives(&mut functions);
+        add_event_primitives(&mut functions);
+
+        set_category(&mut functions, Category::Control, &["repeat", "if", "ifelse", "output", "stop", "op", "apply", ".defmacro"]);
+        set_category(&mut functions, Category::Text, &["show", "print", "pr"]);
+        set_category(&mut functions, Category::System, &["profile"]);
+        set_category(&mut functions, Category::Math, &[
+            "abs", "arctan", "cos", "difference", "exp", "greater?", "less?", "int", "log", "ln", "minus", "pi",
+            "power", "product", "quotient", "remainder", "random", "round", "sin", "sqrt", "sum", "tan"
+        ]);
+        set_category(&mut functions, Category::Data, &[
+            "bf", "butfirst", "bl", "butlast", "count", "empty?", "equal?", "identical?", "first", "fput", "item",
+            "last", "list", "list?", "lput", "member?", "number?", "pick", "word?",
+            "emptyp", "equalp", "listp", "memberp", "numberp", "wordp"
+        ]);
+        set_category(&mut functions, Category::Logic, &["and", "or", "not"]);
+        set_category(&mut functions, Category::Variables, &[
+            "make", "clearname", "clearnames", "name?", "names", "thing", "namep", "procedures", "pprop", "gprop", "remprop", "plist"
+        ]);
+        set_category(&mut functions, Category::Text, &["po", "pots", "pons", "pops", "help"]);
+        set_category(&mut functions, Category::Files, &["save", "load"]);
+        attach_docs(&mut functions, STDLIB_DOCS);
+        functions
+    }
+}
 
 pub fn add_stdlib<S: 'static>(es: &mut EState<S>) {
-    es.functions.insert("repeat".to_string(), Function::from_proc2(repeat));
-    es.functions.insert("show".to_string(), Function::from_proc1(show));
-
-    es.functions.insert("abs".to_string(), Function::from_fn1(abs));
-    es.functions.insert("arctan".to_string(), Function::from_fn1(arctan));
-    es.functions.insert("cos".to_string(), Function::from_fn1(cos));
-    es.functions.insert("difference".to_string(), Function::from_fn2(difference));
-    es.functions.insert("exp".to_string(), Function::from_fn1(exp));
-    es.functions.insert("greater?".to_string(), Function::from_fn2(greater));
-    es.functions.insert("less?".to_string(), Function::from_fn2(less));
-    es.functions.insert("int".to_string(), Function::from_fn1(int));
-    es.functions.insert("log".to_string(), Function::from_fn1(log));
-    es.functions.insert("ln".to_string(), Function::from_fn1(ln));
-    es.functions.insert("minus".to_string(), Function::from_fn1(minus));
-    es.functions.insert("pi".to_string(), Function::from_fn(pi));
-    es.functions.insert("power".to_string(), Function::from_fn2(power));
-    es.functions.insert("product".to_string(), Function::from_fn2(product));
-    es.functions.insert("quotient".to_string(), Function::from_fn2(quotient));
-    es.functions.insert("remainder".to_string(), Function::from_fn2(remainder));
-    es.functions.insert("random".to_string(), Function::from_fn1(random));
-    es.functions.insert("round".to_string(), Function::from_fn1(round));
-    es.functions.insert("sin".to_string(), Function::from_fn1(sin));
-    es.functions.insert("sqrt".to_string(), Function::from_fn1(sqrt));
-    es.functions.insert("sum".to_string(), Function::from_fn2(sum));
-    es.functions.insert("tan".to_string(), Function::from_fn1(tan));
-
-    es.functions.insert("bf".to_string(), Function::from_fn1(bf));
-    es.functions.insert("butfirst".to_string(), Function::from_fn1(bf));
-    es.functions.insert("bl".to_string(), Function::from_fn1(bl));
-    es.functions.insert("butlast".to_string(), Function::from_fn1(bl));
-    es.functions.insert("count".to_string(), Function::from_fn1(count));
-    es.functions.insert("empty?".to_string(), Function::from_fn1(empty));
-    es.functions.insert("equal?".to_string(), Function::from_fn2(equal));
-    es.functions.insert("identical?".to_string(), Function::from_fn2(equal));
-    es.functions.insert("first".to_string(), Function::from_fn1(first));
-    es.functions.insert("fput".to_string(), Function::from_fn2(fput));
-    es.functions.insert("item".to_string(), Function::from_fn2(item));
-    es.functions.insert("last".to_string(), Function::from_fn1(last));
-    es.functions.insert("list".to_string(), Function::from_fn2(list));
-    es.functions.insert("list?".to_string(), Function::from_fn1(is_list));
-    es.functions.insert("lput".to_string(), Function::from_fn2(lput));
-    es.functions.insert("member?".to_string(), Function::from_fn2(member));
-    es.functions.insert("number?".to_string(), Function::from_fn1(number));
-    es.functions.insert("pick".to_string(), Function::from_fn1(pick));
-    es.functions.insert("word?".to_string(), Function::from_fn1(word));
-
-    es.functions.insert("and".to_string(), Function::from_fn2(and));
-    es.functions.insert("or".to_string(), Function::from_fn2(or));
-    es.functions.insert("not".to_string(), Function::from_fn1(not));
-    es.functions.insert("if".to_string(), Function::from_proc2(if_fn));
-    es.functions.insert("ifelse".to_string(), Function::from_proc3(if_else_fn));
-
-    es.functions.insert("make".to_string(), Function::from_proc2(make));
-    es.functions.insert("clearname".to_string(), Function::from_proc1(clearname));
-    es.functions.insert("clearnames".to_string(), Function::from_proc(clearnames));
-    es.functions.insert("name?".to_string(), Function::from_fn1(name));
-    es.functions.insert("names".to_string(), Function::from_fn(names));
-    es.functions.insert("thing".to_string(), Function::from_fn1(thing));
-
-    es.functions.insert("output".to_string(), Function::from_proc1(output));
-}
-
-fn repeat<S>(state: &mut EState<S>, n: i32, cmd: Vec<LogoValue>) -> Result<(), String> {
+    es.install(&StdLib).unwrap();
+}
+
+fn repeat<S>(state: &mut EState<S>, n: i32, cmd: LogoList) -> Result<(), String> {
     for _ in 0..n {
-        execute(state, cmd.clone())?;
+        execute(state, &cmd)?;
     }
     Ok(())
 }
 
-fn show<S>(_: &mut EState<S>, val: LogoValue) -> Result<(), String> {
-    println!("{}", val);
+fn show<S>(state: &mut EState<S>, val: LogoValue) -> Result<(), String> {
+    println!("{}", state.dialect.format_show(&val));
     Ok(())
 }
 
+fn print<S>(state: &mut EState<S>, val: LogoValue) -> Result<(), String> {
+    println!("{}", state.dialect.format_print(&val));
+    Ok(())
+}
+
+/// Prints the report when the instructions finish, nested `profile` calls only run them.
+fn profile<S>(state: &mut EState<S>, cmd: LogoList) -> Result<(), String> {
+    if state.profiler.is_enabled() {
+        return execute(state, &cmd);
+    }
+    state.profiler.start();
+    let result = execute(state, &cmd);
+    state.profiler.stop();
+    result?;
+    let report = state.profiler.report().to_string();
+    print_text(state, report.trim_end().to_string())
+}
+
 fn abs<S>(_: &mut EState<S>, val: f64) -> Result<f64, String> {
     Ok(val.abs())
 }
@@ -90,6 +205,7 @@ fn cos<S>(_: &mut EState<S>, val: f64) -> Result<f64, String> {
     Ok(val.to_radians().cos())
 }
 
+#[cfg(not(feature = "bigint"))]
 fn difference<S>(_: &mut EState<S>, a: f64, b: f64) -> Result<f64, String> {
     Ok(a - b)
 }
@@ -98,14 +214,17 @@ fn exp<S>(_: &mut EState<S>, val: f64) -> Result<f64, String> {
     Ok(val.exp())
 }
 
+#[cfg(not(feature = "bigint"))]
 fn greater<S>(_: &mut EState<S>, a: f64, b: f64) -> Result<bool, String> {
     Ok(a > b)
 }
 
+#[cfg(not(feature = "bigint"))]
 fn less<S>(_: &mut EState<S>, a: f64, b: f64) -> Result<bool, String> {
     Ok(a < b)
 }
 
+#[cfg(not(feature = "bigint"))]
 fn int<S>(_: &mut EState<S>, val: f64) -> Result<i32, String> {
     Ok(val as i32)
 }
@@ -118,6 +237,7 @@ fn ln<S>(_: &mut EState<S>, val: f64) -> Result<f64, String> {
     Ok(val.ln())
 }
 
+#[cfg(not(feature = "bigint"))]
 fn minus<S>(_: &mut EState<S>, val: f64) -> Result<f64, String> {
     Ok(-val)
 }
@@ -126,20 +246,23 @@ fn pi<S>(_: &mut EState<S>) -> Result<f64, String> {
     Ok(std::f64::consts::PI)
 }
 
+#[cfg(not(feature = "bigint"))]
 fn power<S>(_: &mut EState<S>, a: f64, b: f64) -> Result<f64, String> {
     Ok(a.powf(b))
 }
 
-fn product<S>(_: &mut EState<S>, a: f64, b: f64) -> Result<f64, String> {
-    Ok(a * b)
+#[cfg(not(feature = "bigint"))]
+fn product<S>(_: &mut EState<S>, a: f64, b: f64, rest: Rest<f64>) -> Result<f64, String> {
+    Ok(rest.0.iter().fold(a * b, |acc, x| acc * x))
 }
 
 fn quotient<S>(_: &mut EState<S>, a: f64, b: f64) -> Result<f64, String> {
     Ok(a / b)
 }
 
+#[cfg(not(feature = "bigint"))]
 fn remainder<S>(_: &mut EState<S>, a: i32, b: i32) -> Result<i32, String> {
-    Ok(a % b)
+    a.checked_rem(b).ok_or_else(|| "Can't divide by zero".to_string())
 }
 
 fn random<S>(_: &mut EState<S>, val: i32) -> Result<i32, String> {
@@ -149,6 +272,7 @@ fn random<S>(_: &mut EState<S>, val: i32) -> Result<i32, String> {
     Ok((rand::thread_rng().gen::<u32>() % val as u32) as i32)
 }
 
+#[cfg(not(feature = "bigint"))]
 fn round<S>(_: &mut EState<S>, val: f64) -> Result<i32, String> {
     Ok(val.round() as i32)
 }
@@ -161,8 +285,9 @@ fn sqrt<S>(_: &mut EState<S>, val: f64) -> Result<f64, String> {
     Ok(val.sqrt())
 }
 
-fn sum<S>(_: &mut EState<S>, x: f64, y: f64) -> Result<f64, String> {
-    Ok(x + y)
+#[cfg(not(feature = "bigint"))]
+fn sum<S>(_: &mut EState<S>, x: f64, y: f64, rest: Rest<f64>) -> Result<f64, String> {
+    Ok(rest.0.iter().fold(x + y, |acc, x| acc + x))
 }
 
 fn tan<S>(_: &mut EState<S>, val: f64) -> Result<f64, String> {
@@ -170,62 +295,67 @@ fn tan<S>(_: &mut EState<S>, val: f64) -> Result<f64, String> {
 }
 
 
-fn bf<S>(_: &mut EState<S>, mut val: Vec<LogoValue>) -> Result<Vec<LogoValue>, String> {
-    if val.len() == 0 {
+fn bf<S>(_: &mut EState<S>, val: LogoList) -> Result<LogoList, String> {
+    if val.is_empty() {
         return Err("Can't remove an element from an empty list".to_string());
     }
-    val.remove(0);
-    Ok(val)
+    Ok(val.butfirst())
 }
 
 fn bl<S>(_: &mut EState<S>, mut val: Vec<LogoValue>) -> Result<Vec<LogoValue>, String> {
-    if val.len() == 0 {
+    if val.is_empty() {
         return Err("Can't remove an element from an empty list".to_string());
     }
     val.pop();
     Ok(val)
 }
 
-fn count<S>(_: &mut EState<S>, val: Vec<LogoValue>) -> Result<i32, String> {
+fn count<S>(_: &mut EState<S>, val: LogoList) -> Result<i32, String> {
     Ok(val.len() as i32)
 }
 
-fn empty<S>(_: &mut EState<S>, val: Vec<LogoValue>) -> Result<bool, String> {
+fn empty<S>(_: &mut EState<S>, val: LogoList) -> Result<bool, String> {
     Ok(val.is_empty())
 }
 
-fn equal<S>(_: &mut EState<S>, a: LogoValue, b: LogoValue) -> Result<bool, String> {
+#[cfg(not(feature = "bigint"))]
+pub(crate) fn equal<S>(_: &mut EState<S>, a: LogoValue, b: LogoValue) -> Result<bool, String> {
     Ok(a == b)
 }
 
-fn first<S>(_: &mut EState<S>, val: Vec<LogoValue>) -> Result<LogoValue, String> {
-    if val.len() == 0 {
-        return Err("Can't get an element from an empty list".to_string());
+fn first<S>(_: &mut EState<S>, val: LogoList) -> Result<LogoValue, String> {
+    match val.first() {
+        Some(val) => Ok(val.clone()),
+        None => Err("Can't get an element from an empty list".to_string())
     }
-    Ok(val.first().unwrap().clone())
 }
 
-fn fput<S>(_: &mut EState<S>, a: LogoValue, mut b: Vec<LogoValue>) -> Result<Vec<LogoValue>, String> {
-    b.insert(0, a);
-    Ok(b)
+fn fput<S>(_: &mut EState<S>, a: LogoValue, b: LogoList) -> Result<LogoList, String> {
+    Ok(b.fput(a))
 }
 
-fn item<S>(_: &mut EState<S>, idx: i32, val: Vec<LogoValue>) -> Result<LogoValue, String> {
-    if idx < 0 || idx >= val.len() as i32 {
+fn item<S>(_: &mut EState<S>, idx: i32, val: LogoList) -> Result<LogoValue, String> {
+    if idx < 0 {
         return Err("No such item".to_string());
     }
-    Ok(val[idx as usize].clone())
+    match val.get(idx as usize) {
+        Some(val) => Ok(val.clone()),
+        None => Err("No such item".to_string())
+    }
 }
 
-fn last<S>(_: &mut EState<S>, val: Vec<LogoValue>) -> Result<LogoValue, String> {
-    if val.len() == 0 {
-        return Err("Can't get an element from an empty list".to_string());
+fn last<S>(_: &mut EState<S>, val: LogoList) -> Result<LogoValue, String> {
+    match val.last() {
+        Some(val) => Ok(val.clone()),
+        None => Err("Can't get an element from an empty list".to_string())
     }
-    Ok(val.last().unwrap().clone())
 }
 
-fn list<S>(_: &mut EState<S>, mut a: Vec<LogoValue>, mut b: Vec<LogoValue>) -> Result<Vec<LogoValue>, String> {
+fn list<S>(_: &mut EState<S>, mut a: Vec<LogoValue>, mut b: Vec<LogoValue>, rest: Rest<Vec<LogoValue>>) -> Result<Vec<LogoValue>, String> {
     a.append(&mut b);
+    for mut list in rest.0 {
+        a.append(&mut list);
+    }
     Ok(a)
 }
 
@@ -238,14 +368,13 @@ fn is_list<S>(_: &mut EState<S>, a: LogoValue) -> Result<bool, String> {
     }
 }
 
-fn lput<S>(_: &mut EState<S>, a: LogoValue, mut b: Vec<LogoValue>) -> Result<Vec<LogoValue>, String> {
-    b.push(a);
-    Ok(b)
+fn lput<S>(_: &mut EState<S>, a: LogoValue, b: LogoList) -> Result<LogoList, String> {
+    Ok(b.lput(a))
 }
 
-fn member<S>(_: &mut EState<S>, a: LogoValue, b: Vec<LogoValue>) -> Result<bool, String> {
-    for b_el in b {
-        if a == b_el {
+fn member<S>(_: &mut EState<S>, a: LogoValue, b: LogoList) -> Result<bool, String> {
+    for b_el in &b {
+        if a == *b_el {
             return Ok(true)
         }
     }
@@ -254,7 +383,7 @@ fn member<S>(_: &mut EState<S>, a: LogoValue, b: Vec<LogoValue>) -> Result<bool,
 
 fn number<S>(_: &mut EState<S>, a: LogoValue) -> Result<bool, String> {
     if let LogoValue::Word(word) = a {
-        if let Ok(_) = word.0.parse::<f32>() {
+        if word.0.parse::<f32>().is_ok() {
             Ok(true)
         }
         else {
@@ -266,11 +395,11 @@ fn number<S>(_: &mut EState<S>, a: LogoValue) -> Result<bool, String> {
     }
 }
 
-fn pick<S>(_: &mut EState<S>, val: Vec<LogoValue>) -> Result<LogoValue, String> {
-    if val.len() == 0 {
+fn pick<S>(_: &mut EState<S>, val: LogoList) -> Result<LogoValue, String> {
+    if val.is_empty() {
         return Err("Can't get an element from an empty list".to_string());
     }
-    Ok(val[thread_rng().gen::<usize>() % val.len()].clone())
+    Ok(val.get(thread_rng().gen::<usize>() % val.len()).unwrap().clone())
 }
 
 fn word<S>(_: &mut EState<S>, a: LogoValue) -> Result<bool, String> {
@@ -283,47 +412,53 @@ fn word<S>(_: &mut EState<S>, a: LogoValue) -> Result<bool, String> {
 }
 
 
-fn and<S>(_: &mut EState<S>, a: bool, b: bool) -> Result<bool, String> {
-    Ok(a && b)
+fn and<S>(_: &mut EState<S>, a: bool, b: bool, rest: Rest<bool>) -> Result<bool, String> {
+    Ok(a && b && rest.0.iter().all(|x| *x))
 }
 
-fn or<S>(_: &mut EState<S>, a: bool, b: bool) -> Result<bool, String> {
-    Ok(a || b)
+fn or<S>(_: &mut EState<S>, a: bool, b: bool, rest: Rest<bool>) -> Result<bool, String> {
+    Ok(a || b || rest.0.iter().any(|x| *x))
 }
 
 fn not<S>(_: &mut EState<S>, a: bool) -> Result<bool, String> {
     Ok(!a)
 }
 
-fn if_fn<S>(state: &mut EState<S>, a: bool, cmd: Vec<LogoValue>) -> Result<(), String> {
+fn if_fn<S>(state: &mut EState<S>, a: bool, cmd: LogoList) -> Result<(), String> {
     if a {
-        execute(state, cmd)?;
+        execute(state, &cmd)?;
     }
     Ok(())
 }
 
-fn if_else_fn<S>(state: &mut EState<S>, a: bool, cmd_true: Vec<LogoValue>, cmd_false: Vec<LogoValue>) -> Result<(), String> {
+fn if_else_fn<S>(state: &mut EState<S>, a: bool, cmd_true: LogoList, cmd_false: LogoList) -> Result<(), String> {
     if a {
-        execute(state, cmd_true)?;
+        execute(state, &cmd_true)?;
     }
     else {
-        execute(state, cmd_false)?;
+        execute(state, &cmd_false)?;
     }
     Ok(())
 }
 
 
 fn make<S>(state: &mut EState<S>, name: String, val: LogoValue) -> Result<(), String> {
-    state.vars.insert(name.to_lowercase(), val);
-    Ok(())
+    let symbol = state.symbols.borrow_mut().intern(&name);
+    state.set_var(symbol, val)
 }
 
 fn clearname<S>(state: &mut EState<S>, name: String) -> Result<(), String> {
-    state.vars.remove(name.to_lowercase().as_str());
+    let symbol = state.symbols.borrow().get(&name);
+    if let Some(symbol) = symbol {
+        state.restore_var(symbol, None);
+    }
     Ok(())
 }
 
 fn clearnames<S>(state: &mut EState<S>) -> Result<(), String> {
+    for value in state.vars.values() {
+        state.usage.remove(value);
+    }
     state.vars.clear();
     Ok(())
 }
@@ -335,7 +470,7 @@ fn name<S>(state: &mut EState<S>, name: String) -> Result<bool, String> {
 fn names<S>(state: &mut EState<S>) -> Result<Vec<LogoValue>, String> {
     let mut result = Vec::with_capacity(state.vars.len());
     for key in state.vars.keys() {
-        result.push(LogoValue::String(key.clone()));
+        result.push(LogoValue::String(key.to_string()));
     }
     Ok(result)
 }
@@ -348,8 +483,183 @@ fn thing<S>(state: &mut EState<S>, name: String) -> Result<LogoValue, String> {
     Ok(state.vars[name.as_str()].clone())
 }
 
+fn pprop<S>(state: &mut EState<S>, name: String, prop: String, val: LogoValue) -> Result<(), String> {
+    let prop = prop.to_lowercase();
+    state.limits.check_value(&val)?;
+    let plist = state.plists.entry(name.to_lowercase()).or_default();
+    match plist.iter_mut().find(|(x, _)| *x == prop) {
+        Some(entry) => entry.1 = val,
+        None => plist.push((prop, val))
+    }
+    Ok(())
+}
+
+fn gprop<S>(state: &mut EState<S>, name: String, prop: String) -> Result<LogoValue, String> {
+    let prop = prop.to_lowercase();
+    let value = state.plists.get(&name.to_lowercase())
+        .and_then(|plist| plist.iter().find(|(x, _)| *x == prop))
+        .map(|(_, value)| value.clone());
+    Ok(value.unwrap_or(LogoValue::List(LogoList::new())))
+}
+
+fn remprop<S>(state: &mut EState<S>, name: String, prop: String) -> Result<(), String> {
+    let name = name.to_lowercase();
+    let prop = prop.to_lowercase();
+    if let Some(plist) = state.plists.get_mut(&name) {
+        plist.retain(|(x, _)| *x != prop);
+        if plist.is_empty() {
+            state.plists.remove(&name);
+        }
+    }
+    Ok(())
+}
+
+fn plist<S>(state: &mut EState<S>, name: String) -> Result<Vec<LogoValue>, String> {
+    let mut result = Vec::new();
+    if let Some(plist) = state.plists.get(&name.to_lowercase()) {
+        for (prop, value) in plist {
+            result.push(LogoValue::String(prop.clone()));
+            result.push(value.clone());
+        }
+    }
+    Ok(result)
+}
+
+/// Prints through the current `print` primitive, so hosts which show text elsewhere get it too.
+fn print_text<S>(state: &mut EState<S>, text: String) -> Result<(), String> {
+    let print = match state.functions.get("print") {
+        Some(print) => print.f.clone(),
+        None => return Ok(())
+    };
+    (print)(state, vec![LogoValue::String(text)])?;
+    Ok(())
+}
+
+fn po<S>(state: &mut EState<S>, names: LogoValue) -> Result<(), String> {
+    let names = match names {
+        LogoValue::List(list) => list,
+        name => LogoList::new().fput(name)
+    };
+    let formatter = Formatter::new(state);
+    let mut text = Vec::new();
+    for name in names {
+        let name = match name {
+            LogoValue::Word(Word(name)) | LogoValue::String(name) => name.to_lowercase(),
+            _ => return Err("Type mismatch".to_string())
+        };
+        match state.logo_procedures.get(&name) {
+            Some(procedure) => text.push(formatter.format_procedure(&name, procedure)),
+            None => return Err(format!("Don't know how to {}", name))
+        }
+    }
+    print_text(state, text.join("\n").trim_end().to_string())
+}
+
+fn help<S>(state: &mut EState<S>, name: String) -> Result<(), String> {
+    match state.help_text(&name) {
+        Some(text) => print_text(state, text),
+        None => Err(format!("Don't know how to {}", name))
+    }
+}
+
+fn pots<S>(state: &mut EState<S>) -> Result<(), String> {
+    let titles: Vec<String> = state.own_procedures().into_iter().map(|(name, procedure)| {
+        let mut title = format!("to {}", name);
+        for arg_name in &procedure.arg_names {
+            title += &format!(" :{}", arg_name);
+        }
+        title
+    }).collect();
+    print_text(state, titles.join("\n"))
+}
+
+fn pons<S>(state: &mut EState<S>) -> Result<(), String> {
+    let formatter = Formatter::new(state);
+    let mut vars: Vec<(&str, &LogoValue)> = state.vars.iter().collect();
+    vars.sort_by(|a, b| a.0.cmp(b.0));
+    let lines: Vec<String> = vars.into_iter().map(|(name, value)| {
+        format!("make {} {}", string_text(name, state.dialect), formatter.format_values(std::slice::from_ref(value)))
+    }).collect();
+    print_text(state, lines.join("\n"))
+}
+
+fn pops<S>(state: &mut EState<S>) -> Result<(), String> {
+    let formatter = Formatter::new(state);
+    let mut plists: Vec<_> = state.plists.iter().collect();
+    plists.sort_by(|a, b| a.0.cmp(b.0));
+    let mut lines = Vec::new();
+    for (name, plist) in plists {
+        for (prop, value) in plist {
+            lines.push(format!("pprop {} {} {}", string_text(name, state.dialect), string_text(prop, state.dialect),
+                               formatter.format_values(std::slice::from_ref(value))));
+        }
+    }
+    print_text(state, lines.join("\n"))
+}
+
+fn save<S>(state: &mut EState<S>, path: String) -> Result<(), String> {
+    let text = state.save_workspace().to_text();
+    state.files.write(&path, &text)
+}
+
+fn load<S>(state: &mut EState<S>, path: String, namespace: Option<String>) -> Result<(), String> {
+    state.load_file(&path, namespace.as_deref())
+}
+
+pub(crate) fn apply<S>(state: &mut EState<S>, template: LogoValue, inputs: Vec<LogoValue>) -> Result<Option<LogoValue>, String> {
+    match template {
+        LogoValue::Word(Word(name)) | LogoValue::String(name) => call(state, &name, inputs),
+        LogoValue::List(list) if matches!(list.first(), Some(LogoValue::List(_))) => {
+            let arg_names = match list.first() {
+                Some(LogoValue::List(params)) => input_names(params),
+                _ => None
+            };
+            let arg_names = match arg_names {
+                Some(arg_names) => arg_names,
+                None => return Err(format!("apply doesn't like {} as input template", LogoValue::List(list)))
+            };
+            call_procedure(state, "apply", &LogoProcedure {arg_names, code: list.butfirst(), is_macro: false, doc: String::new()}, inputs)
+        },
+        _ => Err(format!("apply doesn't like {} as input template", template))
+    }
+}
+
+/// Names of inputs in a list like `[x :y]`.
+fn input_names(params: &LogoList) -> Option<Vec<String>> {
+    params.iter().map(|param| match param {
+        LogoValue::Word(Word(name)) => Some(name.trim_start_matches(':').to_lowercase()),
+        _ => None
+    }).collect()
+}
+
+/// Text is a list of inputs followed by lists of instructions, like `[[x] [output list :x :x]]`.
+fn defmacro<S>(state: &mut EState<S>, name: String, text: LogoList) -> Result<(), String> {
+    let invalid = || format!(".defmacro doesn't like {} as input text", LogoValue::List(text.clone()));
+    let arg_names = match text.first() {
+        Some(LogoValue::List(params)) => input_names(params).ok_or_else(invalid)?,
+        _ => return Err(invalid())
+    };
+    let mut code = Vec::new();
+    for line in &text.butfirst() {
+        match line {
+            LogoValue::List(line) => code.extend(line.iter().cloned()),
+            _ => return Err(invalid())
+        }
+    }
+    state.logo_procedures.insert(name.to_lowercase(), LogoProcedure {arg_names, code: code.into(), is_macro: true, doc: String::new()});
+    Ok(())
+}
+
+fn procedures<S>(state: &mut EState<S>) -> Result<Vec<String>, String> {
+    Ok(state.own_procedures().into_iter().map(|(name, _)| name).collect())
+}
 
 fn output<S>(state: &mut EState<S>, val: LogoValue) -> Result<(), String> {
     state.output = Some(val);
     Err("Output".to_string())
 }
+
+fn stop<S>(state: &mut EState<S>) -> Result<(), String> {
+    state.output = None;
+    Err("Output".to_string())
+}
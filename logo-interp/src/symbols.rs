@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+use std::ops::Index;
+use crate::shared::{Shared, SharedCell};
+
+/// Interned name, spellings which differ only in case get the same symbol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    pub fn id(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Gives out symbols for names. The case of every spelling is folded only the first time it's seen,
+/// later lookups of the same spelling take a single hash.
+#[derive(Debug, Default)]
+pub struct Interner {
+    spellings: HashMap<String, Symbol>,
+    names: Vec<Shared<str>>
+}
+
+/// Interner shared by `EState` and its symbol tables.
+pub type SharedInterner = Shared<SharedCell<Interner>>;
+
+impl Interner {
+    pub fn new() -> Self {
+        Interner::default()
+    }
+
+    pub fn intern(&mut self, spelling: &str) -> Symbol {
+        if let Some(symbol) = self.spellings.get(spelling) {
+            return *symbol;
+        }
+        let name = spelling.to_lowercase();
+        let symbol = match self.spellings.get(&name) {
+            Some(symbol) => *symbol,
+            None => {
+                let symbol = Symbol(self.names.len() as u32);
+                self.names.push(Shared::from(name.as_str()));
+                self.spellings.insert(name, symbol);
+                symbol
+            }
+        };
+        self.spellings.insert(spelling.to_string(), symbol);
+        symbol
+    }
+
+    /// Same as `intern`, but names which were never interned give `None`.
+    pub fn get(&self, spelling: &str) -> Option<Symbol> {
+        match self.spellings.get(spelling) {
+            Some(symbol) => Some(*symbol),
+            None => self.spellings.get(&spelling.to_lowercase()).cloned()
+        }
+    }
+
+    /// Lowercase name of the symbol.
+    pub fn name(&self, symbol: Symbol) -> Shared<str> {
+        self.names[symbol.id()].clone()
+    }
+}
+
+/// Map from case-insensitive names to values, where lookups by symbol are plain indexing.
+/// Entries are kept in the order their symbols were interned.
+pub struct SymbolTable<V> {
+    symbols: SharedInterner,
+    entries: Vec<Option<(Shared<str>, V)>>,
+    len: usize,
+    generation: u64
+}
+
+impl<V> SymbolTable<V> {
+    pub fn new(symbols: SharedInterner) -> Self {
+        SymbolTable {symbols, entries: Vec::new(), len: 0, generation: 0}
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Changes each time an entry is added, replaced or removed.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn get_symbol(&self, symbol: Symbol) -> Option<&V> {
+        match self.entries.get(symbol.id()) {
+            Some(Some((_, value))) => Some(value),
+            _ => None
+        }
+    }
+
+    pub fn insert_symbol(&mut self, symbol: Symbol, value: V) -> Option<V> {
+        if self.entries.len() <= symbol.id() {
+            self.entries.resize_with(symbol.id() + 1, || None);
+        }
+        let name = self.symbols.borrow().name(symbol);
+        let old = self.entries[symbol.id()].replace((name, value)).map(|(_, value)| value);
+        self.generation += 1;
+        if old.is_none() {
+            self.len += 1;
+        }
+        old
+    }
+
+    pub fn remove_symbol(&mut self, symbol: Symbol) -> Option<V> {
+        let old = self.entries.get_mut(symbol.id())?.take().map(|(_, value)| value);
+        if old.is_some() {
+            self.len -= 1;
+            self.generation += 1;
+        }
+        old
+    }
+
+    pub fn get(&self, name: &str) -> Option<&V> {
+        let symbol = self.symbols.borrow().get(name)?;
+        self.get_symbol(symbol)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut V> {
+        let symbol = self.symbols.borrow().get(name)?;
+        match self.entries.get_mut(symbol.id()) {
+            Some(Some((_, value))) => Some(value),
+            _ => None
+        }
+    }
+
+    pub fn contains_key(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    pub fn insert(&mut self, name: impl AsRef<str>, value: V) -> Option<V> {
+        let symbol = self.symbols.borrow_mut().intern(name.as_ref());
+        self.insert_symbol(symbol, value)
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<V> {
+        let symbol = self.symbols.borrow().get(name)?;
+        self.remove_symbol(symbol)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.len = 0;
+        self.generation += 1;
+    }
+
+    /// Names are lowercase.
+    pub fn iter(&self) -> Iter<'_, V> {
+        Iter {entries: self.entries.iter()}
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.iter().map(|(name, _)| name)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.iter().map(|(_, value)| value)
+    }
+
+    /// Copy of the entries by their lowercase names, for code written against the `HashMap` tables
+    /// `EState` used to have.
+    pub fn to_map(&self) -> HashMap<String, V> where V: Clone {
+        self.iter().map(|(name, value)| (name.to_string(), value.clone())).collect()
+    }
+}
+
+pub struct Iter<'a, V> {
+    entries: std::slice::Iter<'a, Option<(Shared<str>, V)>>
+}
+
+impl<'a, V> Iterator for Iter<'a, V> {
+    type Item = (&'a str, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (name, value) = self.entries.by_ref().flatten().next()?;
+        Some((name.as_ref(), value))
+    }
+}
+
+impl<'a, V> IntoIterator for &'a SymbolTable<V> {
+    type Item = (&'a str, &'a V);
+    type IntoIter = Iter<'a, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<V> Extend<(String, V)> for SymbolTable<V> {
+    fn extend<T: IntoIterator<Item = (String, V)>>(&mut self, iter: T) {
+        for (name, value) in iter {
+            self.insert(name, value);
+        }
+    }
+}
+
+impl<V> Index<&str> for SymbolTable<V> {
+    type Output = V;
+
+    fn index(&self, name: &str) -> &V {
+        self.get(name).unwrap_or_else(|| panic!("No entry {}", name))
+    }
+}
+
+#[test]
+fn test_symbols() {
+    let symbols = SharedInterner::default();
+    let mut table = SymbolTable::new(symbols.clone());
+    table.insert("Size", 1);
+    table.insert("pos", 2);
+    let size = symbols.borrow_mut().intern("SIZE");
+    assert_eq!(table.get_symbol(size), Some(&1));
+    assert_eq!(&*symbols.borrow().name(size), "size");
+    assert_eq!(table["size"], 1);
+    assert_eq!(table.insert("size", 3), Some(1));
+    assert_eq!(table.iter().collect::<Vec<_>>(), vec![("size", &3), ("pos", &2)]);
+    assert_eq!(table.remove("POS"), Some(2));
+    assert_eq!(table.len(), 1);
+    assert!(table.get("unknown").is_none());
+    assert_eq!(table.to_map(), HashMap::from([("size".to_string(), 3)]));
+}
@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use crate::shared::{MaybeSync, Shared, SharedCell};
+
+/// Files that `save`, `load` and other file primitives work with.
+pub trait FileSystem: MaybeSync {
+    fn read(&self, path: &str) -> Result<String, String>;
+    fn write(&mut self, path: &str, contents: &str) -> Result<(), String>;
+    fn exists(&self, path: &str) -> bool;
+}
+
+/// Files kept in memory, clones share the same files, so the host can look at what programs wrote.
+#[derive(Clone, Default)]
+pub struct MemoryFileSystem {
+    files: Shared<SharedCell<HashMap<String, String>>>
+}
+
+impl MemoryFileSystem {
+    pub fn new() -> Self {
+        MemoryFileSystem::default()
+    }
+
+    pub fn files(&self) -> Vec<String> {
+        let mut result: Vec<String> = self.files.borrow().keys().cloned().collect();
+        result.sort();
+        result
+    }
+}
+
+impl FileSystem for MemoryFileSystem {
+    fn read(&self, path: &str) -> Result<String, String> {
+        match self.files.borrow().get(path) {
+            Some(contents) => Ok(contents.clone()),
+            None => Err(format!("File {} not found", path))
+        }
+    }
+
+    fn write(&mut self, path: &str, contents: &str) -> Result<(), String> {
+        self.files.borrow_mut().insert(path.to_string(), contents.to_string());
+        Ok(())
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        self.files.borrow().contains_key(path)
+    }
+}
+
+/// Files inside a directory, paths that lead outside of it are rejected.
+pub struct DirectoryFileSystem {
+    root: PathBuf
+}
+
+impl DirectoryFileSystem {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        DirectoryFileSystem {root: root.into()}
+    }
+
+    fn resolve(&self, path: &str) -> Result<PathBuf, String> {
+        let relative = Path::new(path);
+        if !relative.components().all(|x| matches!(x, Component::Normal(_) | Component::CurDir)) {
+            return Err(format!("Invalid file name {}", path));
+        }
+        Ok(self.root.join(relative))
+    }
+}
+
+impl FileSystem for DirectoryFileSystem {
+    fn read(&self, path: &str) -> Result<String, String> {
+        std::fs::read_to_string(self.resolve(path)?).map_err(|_| format!("File {} not found", path))
+    }
+
+    fn write(&mut self, path: &str, contents: &str) -> Result<(), String> {
+        std::fs::write(self.resolve(path)?, contents).map_err(|err| format!("Can't write {}: {}", path, err))
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        self.resolve(path).is_ok_and(|x| x.is_file())
+    }
+}
@@ -0,0 +1,249 @@
+use std::collections::HashMap;
+use crate::core::{LogoList, LogoProcedure, LogoValue, Word};
+use crate::dialect::Dialect;
+use crate::executor_state::EState;
+use crate::formatter::{Formatter, string_text};
+use crate::locale::Locale;
+use crate::parser::{parse_procedures_with, parse_with_comments_in_dialect, SpannedValue};
+
+/// Version written by `Workspace::to_text`, files of newer versions are refused.
+pub const WORKSPACE_VERSION: i32 = 1;
+
+/// Procedures, variables, property lists and host state, as saved by `save` and restored by `load`.
+/// The text form is Logo-like and always uses the native dialect and English keywords.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Workspace {
+    pub version: i32,
+    /// Source text of each procedure, from `to` to `end`.
+    pub procedures: Vec<String>,
+    pub vars: Vec<(String, LogoValue)>,
+    pub plists: Vec<(String, Vec<(String, LogoValue)>)>,
+    /// Host state like the turtle position, by section name.
+    pub sections: Vec<(String, LogoValue)>
+}
+
+/// Host state that is saved with the workspace, see `EState::add_state_section`.
+pub struct StateSection<S> {
+    pub name: String,
+    pub save: fn(&S) -> LogoValue,
+    pub load: fn(&mut S, LogoValue) -> Result<(), String>
+}
+
+fn value_text(formatter: &Formatter, value: &LogoValue) -> String {
+    formatter.format_values(&[LogoValue::List(LogoList::new().fput(value.clone()))])
+}
+
+impl Workspace {
+    pub fn to_text(&self) -> String {
+        let formatter = Formatter::plain();
+        let mut result = format!("; Logo workspace\nversion {}\n", self.version);
+        for procedure in &self.procedures {
+            result += "\n";
+            result += procedure.trim_end();
+            result += "\n";
+        }
+        if !self.vars.is_empty() || !self.plists.is_empty() || !self.sections.is_empty() {
+            result += "\n";
+        }
+        for (name, value) in &self.vars {
+            result += &format!("variable {} {}\n", string_text(name, Dialect::Native), value_text(&formatter, value));
+        }
+        for (name, props) in &self.plists {
+            for (prop, value) in props {
+                result += &format!("property {} {} {}\n", string_text(name, Dialect::Native),
+                                   string_text(prop, Dialect::Native), value_text(&formatter, value));
+            }
+        }
+        for (name, value) in &self.sections {
+            result += &format!("state {} {}\n", string_text(name, Dialect::Native), value_text(&formatter, value));
+        }
+        result
+    }
+
+    /// Whether the text starts with the `version` entry, files of other kinds hold procedure source.
+    pub fn is_workspace(text: &str) -> bool {
+        match parse_with_comments_in_dialect(text, Dialect::Native) {
+            Ok((values, _)) => matches!(values.first(), Some(SpannedValue::Word(word, _)) if word.0.to_lowercase() == "version"),
+            Err(_) => false
+        }
+    }
+
+    pub fn from_text(text: &str) -> Result<Self, String> {
+        let (values, _) = parse_with_comments_in_dialect(text, Dialect::Native).map_err(|err| err.message)?;
+        let mut workspace = Workspace {version: 0, ..Default::default()};
+        let mut it = values.into_iter();
+        while let Some(value) = it.next() {
+            let keyword = match &value {
+                SpannedValue::Word(word, _) => word.0.to_lowercase(),
+                _ => return Err("Invalid workspace file".to_string())
+            };
+            match keyword.as_str() {
+                "version" => {
+                    workspace.version = match it.next().map(|x| x.to_value()) {
+                        Some(LogoValue::Word(word)) => word.0.parse().map_err(|_| "Invalid workspace version".to_string())?,
+                        _ => return Err("Invalid workspace version".to_string())
+                    };
+                    if workspace.version > WORKSPACE_VERSION {
+                        return Err(format!("Workspace version {} is newer than supported", workspace.version));
+                    }
+                },
+                "to" | ".macro" => {
+                    let start = value.span().start;
+                    let end = it.by_ref()
+                        .find(|x| matches!(x, SpannedValue::Word(word, _) if word.0.to_lowercase() == "end"))
+                        .ok_or("Missing 'end' in workspace file".to_string())?;
+                    workspace.procedures.push(text[start..end.span().end].to_string() + "\n");
+                },
+                "variable" => {
+                    let name = take_name(&mut it)?;
+                    workspace.vars.push((name, take_value(&mut it)?));
+                },
+                "property" => {
+                    let name = take_name(&mut it)?;
+                    let prop = take_name(&mut it)?;
+                    let value = take_value(&mut it)?;
+                    match workspace.plists.iter_mut().find(|(x, _)| *x == name) {
+                        Some((_, props)) => props.push((prop, value)),
+                        None => workspace.plists.push((name, vec![(prop, value)]))
+                    }
+                },
+                "state" => {
+                    let name = take_name(&mut it)?;
+                    workspace.sections.push((name, take_value(&mut it)?));
+                },
+                _ => return Err(format!("Invalid workspace entry {}", keyword))
+            }
+        }
+        if workspace.version == 0 {
+            return Err("Not a workspace file".to_string());
+        }
+        Ok(workspace)
+    }
+}
+
+fn take_name(it: &mut impl Iterator<Item = SpannedValue>) -> Result<String, String> {
+    match it.next().map(|x| x.to_value()) {
+        Some(LogoValue::String(name)) | Some(LogoValue::Word(Word(name))) => Ok(name),
+        _ => Err("Invalid workspace file".to_string())
+    }
+}
+
+fn take_value(it: &mut impl Iterator<Item = SpannedValue>) -> Result<LogoValue, String> {
+    match it.next().map(|x| x.to_value()) {
+        Some(LogoValue::List(list)) if list.len() == 1 => Ok(list.first().unwrap().clone()),
+        _ => Err("Invalid workspace file".to_string())
+    }
+}
+
+impl<S> EState<S> {
+    /// Host state with the name is saved and loaded with the workspace.
+    pub fn add_state_section(&mut self, name: &str, save: fn(&S) -> LogoValue,
+                             load: fn(&mut S, LogoValue) -> Result<(), String>) {
+        self.state_sections.retain(|x| x.name != name);
+        self.state_sections.push(StateSection {name: name.to_string(), save, load});
+    }
+
+    /// Procedures that programs define or load, sorted by name.
+    /// Procedures of libraries are buried, they're left out here and so from `pots` and saved workspaces.
+    pub fn own_procedures(&self) -> Vec<(String, LogoProcedure)> {
+        let library_procedures = self.library_procedures();
+        let mut result: Vec<(String, LogoProcedure)> = self.logo_procedures.iter()
+            .filter(|(name, procedure)| library_procedures.get(*name) != Some(procedure))
+            .map(|(name, procedure)| (name.to_string(), procedure.clone()))
+            .collect();
+        result.sort_by(|a, b| a.0.cmp(&b.0));
+        result
+    }
+
+    /// Procedures except ones of libraries, variables, property lists and host state.
+    pub fn save_workspace(&self) -> Workspace {
+        let mut formatter = Formatter::new(self).with_dialect(Dialect::Native);
+        formatter.normalize_case = false;
+        let mut vars: Vec<_> = self.vars.iter().map(|(name, value)| (name.to_string(), value.clone())).collect();
+        vars.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut plists: Vec<_> = self.plists.iter().map(|(name, props)| (name.clone(), props.clone())).collect();
+        plists.sort_by(|a, b| a.0.cmp(&b.0));
+        Workspace {
+            version: WORKSPACE_VERSION,
+            procedures: self.own_procedures().iter().map(|(name, procedure)| formatter.format_procedure(name, procedure)).collect(),
+            vars,
+            plists,
+            sections: self.state_sections.iter().map(|x| (x.name.clone(), (x.save)(&self.state))).collect()
+        }
+    }
+
+    /// Adds the workspace contents to the current ones, loaded procedures stay available in later runs.
+    pub fn load_workspace(&mut self, workspace: Workspace) -> Result<(), String> {
+        let mut procedures = HashMap::new();
+        for source in &workspace.procedures {
+            procedures.extend(parse_procedures_with(source, &Locale::english(), Dialect::Native)?);
+        }
+        for (name, value) in &workspace.sections {
+            if let Some(section) = self.state_sections.iter().find(|x| x.name == *name) {
+                (section.load)(&mut self.state, value.clone())?;
+            }
+        }
+        for (name, procedure) in procedures {
+            self.logo_procedures.insert(name.clone(), procedure.clone());
+            self.loaded_procedures.insert(name, procedure);
+        }
+        for (name, value) in workspace.vars {
+            let symbol = self.symbols.borrow_mut().intern(&name);
+            self.set_var(symbol, value)?;
+        }
+        for (name, props) in workspace.plists {
+            let plist = self.plists.entry(name).or_default();
+            for (prop, value) in props {
+                match plist.iter_mut().find(|(x, _)| *x == prop) {
+                    Some(entry) => entry.1 = value,
+                    None => plist.push((prop, value))
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_workspace() {
+    use crate::executor::execute_str;
+    use crate::stdlib::add_stdlib;
+    use crate::vfs::{FileSystem, MemoryFileSystem};
+
+    let files = MemoryFileSystem::new();
+    let mut state = EState::new(());
+    add_stdlib(&mut state);
+    state.files = Box::new(files.clone());
+    state.add_state_section("counter", |_: &()| LogoValue::Word(Word("7".to_string())), |_: &mut (), value| {
+        match value == LogoValue::Word(Word("7".to_string())) {
+            true => Ok(()),
+            false => Err("Unexpected state".to_string())
+        }
+    });
+    execute_str(&mut state, "to double :x\noutput :x * 2\nend",
+                "make 'size' 10 make 'name' 'Big dog' make 'nums' [1 [2 'a b'] x] \
+                 make 'quoted' [['it''s ok'] 'a''b c' \"don't] \
+                 pprop 'dog' 'legs' 4 pprop 'dog' 'sound' 'woof woof' pprop 'dog' 'it''s' 'a''b c' save 'ws.lgo'").unwrap();
+
+    let text = files.read("ws.lgo").unwrap();
+    let workspace = Workspace::from_text(&text).unwrap();
+    assert_eq!(workspace, state.save_workspace());
+    assert_eq!(workspace.procedures, vec!["to double :x\n  output :x * 2\nend\n"]);
+    assert!(text.contains("variable \"name ['Big dog']\n"));
+    assert!(text.contains("property \"dog \"legs [4]\n"));
+
+    let mut other = EState::new(());
+    add_stdlib(&mut other);
+    other.files = Box::new(files.clone());
+    execute_str(&mut other, "", "load 'ws.lgo'").unwrap();
+    execute_str(&mut other, "", "make 'result' double :size make 'legs' gprop 'dog' 'legs'").unwrap();
+    assert_eq!(other.vars["result"], LogoValue::Word(Word("20".to_string())));
+    assert_eq!(other.vars["legs"], LogoValue::Word(Word("4".to_string())));
+    assert_eq!(other.vars["nums"], state.vars["nums"]);
+    assert_eq!(other.vars["quoted"], state.vars["quoted"]);
+    assert_eq!(other.plists["dog"], state.plists["dog"]);
+    assert!(text.contains("variable \"quoted [[['it''s ok'] 'a''b c' \"don't]]\n"));
+
+    assert_eq!(Workspace::from_text("version 2"), Err("Workspace version 2 is newer than supported".to_string()));
+    assert_eq!(Workspace::from_text("show 1"), Err("Invalid workspace entry show".to_string()));
+}
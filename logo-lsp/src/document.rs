@@ -0,0 +1,145 @@
+use std::ops::Range;
+use serde_json::{json, Value};
+use logo_runtime::logo_interp::executor_state::EState;
+use logo_runtime::logo_interp::lexer::{tokenize, TokenKind};
+
+/// Offset in the text of an LSP position, characters of positions count UTF-16 code units.
+pub fn offset_of(text: &str, position: &Value) -> usize {
+    let line = position["line"].as_u64().unwrap_or(0);
+    let character = position["character"].as_u64().unwrap_or(0);
+    let mut line_start = 0;
+    for _ in 0..line {
+        match text[line_start..].find('\n') {
+            Some(idx) => line_start += idx + 1,
+            None => return text.len()
+        }
+    }
+    let mut units = 0;
+    for (idx, ch) in text[line_start..].char_indices() {
+        if ch == '\n' || units >= character {
+            return line_start + idx;
+        }
+        units += ch.len_utf16() as u64;
+    }
+    text.len()
+}
+
+pub fn position_of(text: &str, offset: usize) -> Value {
+    let before = &text[..offset.min(text.len())];
+    let line_start = before.rfind('\n').map_or(0, |idx| idx + 1);
+    let character: usize = before[line_start..].chars().map(|ch| ch.len_utf16()).sum();
+    json!({"line": before.matches('\n').count(), "character": character})
+}
+
+pub fn range_of(text: &str, span: &Range<usize>) -> Value {
+    json!({"start": position_of(text, span.start), "end": position_of(text, span.end)})
+}
+
+/// Procedure defined with `to` or `.macro` in a document.
+pub struct Procedure {
+    pub name: String,
+    pub name_span: Range<usize>,
+    /// From `to` to `end`, or to the end of the text if it's missing.
+    pub span: Range<usize>
+}
+
+pub fn procedures<S>(state: &EState<S>, text: &str) -> Vec<Procedure> {
+    let tokens = tokenize(text, state.dialect);
+    let mut result = Vec::new();
+    let mut current: Option<Procedure> = None;
+    let mut depth = 0;
+    let mut idx = 0;
+    while idx < tokens.len() {
+        let token = &tokens[idx];
+        idx += 1;
+        let word = text[token.span.clone()].to_lowercase();
+        match token.kind {
+            TokenKind::Bracket if word == "[" || word == "(" => depth += 1,
+            TokenKind::Bracket => depth = 0.max(depth - 1),
+            TokenKind::Word if depth == 0 && current.is_none()
+                && (state.locale.is_to(&word) || state.locale.is_macro(&word)) => {
+                if let Some(name) = tokens.get(idx) {
+                    current = Some(Procedure {
+                        name: text[name.span.clone()].to_lowercase(),
+                        name_span: name.span.clone(),
+                        span: token.span.start..text.len()
+                    });
+                    idx += 1;
+                }
+            },
+            TokenKind::Word if depth == 0 && state.locale.is_end(&word) => {
+                if let Some(mut procedure) = current.take() {
+                    procedure.span.end = token.span.end;
+                    result.push(procedure);
+                }
+            },
+            _ => {}
+        }
+    }
+    result.extend(current);
+    result
+}
+
+/// Blanks out the code keeping line breaks, byte lengths and comments.
+fn blank(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut in_comment = false;
+    for ch in text.chars() {
+        match ch {
+            '\n' => in_comment = false,
+            ';' => in_comment = true,
+            _ => {}
+        }
+        if in_comment || ch == '\n' {
+            result.push(ch);
+        }
+        else {
+            result.extend(std::iter::repeat_n(' ', ch.len_utf8()));
+        }
+    }
+    result
+}
+
+/// Splits the text into procedure definitions and commands, the two sources of `checker::check`.
+/// Both are as long as the text, so offsets in them are offsets in the text.
+pub fn split_sources(text: &str, procedures: &[Procedure]) -> (String, String) {
+    let mut proc_source = String::with_capacity(text.len());
+    let mut source = String::with_capacity(text.len());
+    let mut pos = 0;
+    for procedure in procedures {
+        proc_source += &blank(&text[pos..procedure.span.start]);
+        source += &text[pos..procedure.span.start];
+        proc_source += &text[procedure.span.clone()];
+        source += &blank(&text[procedure.span.clone()]);
+        pos = procedure.span.end;
+    }
+    proc_source += &blank(&text[pos..]);
+    source += &text[pos..];
+    (proc_source, source)
+}
+
+/// Lowercase word at the offset, with its span.
+pub fn word_at<S>(state: &EState<S>, text: &str, offset: usize) -> Option<(String, Range<usize>)> {
+    let token = tokenize(text, state.dialect).into_iter()
+        .find(|token| token.kind == TokenKind::Word && token.span.start <= offset && offset <= token.span.end)?;
+    Some((text[token.span.clone()].to_lowercase(), token.span))
+}
+
+#[test]
+fn test_document() {
+    let text = "; Ще квадрат 😀\nto square :size\nrepeat 4 [fd :size rt 90]\nend\nsquare 10";
+    let offset = offset_of(text, &json!({"line": 0, "character": 15}));
+    assert_eq!(&text[offset..], "\nto square :size\nrepeat 4 [fd :size rt 90]\nend\nsquare 10");
+    assert_eq!(position_of(text, offset), json!({"line": 0, "character": 15}));
+    assert_eq!(offset_of(text, &json!({"line": 9, "character": 0})), text.len());
+
+    let state = EState::new(());
+    let procedures = procedures(&state, text);
+    assert_eq!(procedures.len(), 1);
+    assert_eq!(&text[procedures[0].span.clone()], "to square :size\nrepeat 4 [fd :size rt 90]\nend");
+    let (proc_source, source) = split_sources(text, &procedures);
+    assert_eq!(proc_source.len(), text.len());
+    assert_eq!(source.len(), text.len());
+    assert!(proc_source.starts_with("; Ще квадрат 😀\nto square") && proc_source.ends_with("end\n         "));
+    assert!(source.starts_with("; Ще квадрат 😀\n   ") && source.ends_with("   \nsquare 10"));
+}
@@ -0,0 +1,18 @@
+mod document;
+mod server;
+mod transport;
+
+use std::io::{stdin, stdout};
+use std::process::ExitCode;
+use crate::server::Server;
+
+fn main() -> ExitCode {
+    match Server::new().run(&mut stdin().lock(), &mut stdout().lock()) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(err) => {
+            eprintln!("{}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
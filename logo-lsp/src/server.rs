@@ -0,0 +1,313 @@
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use serde_json::{json, Value};
+use logo_runtime::drawinglib::add_drawinglib;
+use logo_runtime::logo_interp::checker::{check, Severity};
+use logo_runtime::logo_interp::completion::CompletionKind;
+use logo_runtime::logo_interp::dialect::Dialect;
+use logo_runtime::logo_interp::executor_state::EState;
+use logo_runtime::logo_interp::formatter::Formatter;
+use logo_runtime::logo_interp::locale::Locale;
+use logo_runtime::logo_interp::parser::parse_procedures_with;
+use logo_runtime::logo_interp::prelude::add_prelude;
+use logo_runtime::logo_interp::stdlib::add_stdlib;
+use logo_runtime::state::{NoOpDelegate, State};
+use crate::document::{offset_of, procedures, range_of, split_sources, word_at};
+use crate::transport::{read_message, write_message};
+
+const INVALID_PARAMS: i64 = -32602;
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// Kinds of completion items and symbols in the protocol.
+const COMPLETION_FUNCTION: u32 = 3;
+const COMPLETION_VARIABLE: u32 = 6;
+const SYMBOL_FUNCTION: u32 = 12;
+
+fn new_state(dialect: Dialect) -> EState<State<NoOpDelegate>> {
+    let mut state = EState::with_dialect(State::new(800, 450, NoOpDelegate{}), dialect);
+    add_stdlib(&mut state);
+    add_drawinglib(&mut state);
+    add_prelude(&mut state);
+    state
+}
+
+pub struct Server {
+    state: EState<State<NoOpDelegate>>,
+    /// Texts of the open documents by URI.
+    documents: HashMap<String, String>,
+    shutdown: bool,
+    exit: bool
+}
+
+impl Default for Server {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Server {state: new_state(Dialect::Native), documents: HashMap::new(), shutdown: false, exit: false}
+    }
+
+    /// Serves messages until `exit` or the end of the input, outputs whether `shutdown` came before.
+    pub fn run(&mut self, reader: &mut impl BufRead, writer: &mut impl Write) -> Result<bool, String> {
+        while !self.exit {
+            let Some(message) = read_message(reader)? else {
+                break;
+            };
+            for reply in self.handle(message) {
+                write_message(writer, &reply)?;
+            }
+        }
+        Ok(self.shutdown)
+    }
+
+    /// Response to a request, or notifications to send for a notification.
+    pub fn handle(&mut self, message: Value) -> Vec<Value> {
+        let Some(method) = message["method"].as_str() else {
+            // Responses to requests, the server doesn't send any
+            return Vec::new();
+        };
+        let params = &message["params"];
+        match message.get("id") {
+            Some(id) => vec![match self.request(method, params) {
+                Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+                Err((code, error)) => json!({"jsonrpc": "2.0", "id": id, "error": {"code": code, "message": error}})
+            }],
+            None => self.notification(method, params)
+        }
+    }
+
+    fn request(&mut self, method: &str, params: &Value) -> Result<Value, (i64, String)> {
+        match method {
+            "initialize" => Ok(self.initialize(&params["initializationOptions"])),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            },
+            "textDocument/hover" => self.hover(params),
+            "textDocument/definition" => self.definition(params),
+            "textDocument/completion" => self.completion(params),
+            "textDocument/documentSymbol" => self.symbols(params),
+            "textDocument/formatting" => self.formatting(params),
+            _ => Err((METHOD_NOT_FOUND, format!("Unknown method {}", method)))
+        }
+    }
+
+    fn notification(&mut self, method: &str, params: &Value) -> Vec<Value> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default().to_string();
+        match method {
+            "exit" => {
+                self.exit = true;
+                Vec::new()
+            },
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.documents.insert(uri.clone(), text.to_string());
+                vec![self.diagnostics(&uri)]
+            },
+            "textDocument/didChange" => {
+                // Full sync, the last change is the whole text
+                let Some(text) = params["contentChanges"].as_array().and_then(|changes| changes.last())
+                    .and_then(|change| change["text"].as_str()) else {
+                    return Vec::new();
+                };
+                self.documents.insert(uri.clone(), text.to_string());
+                vec![self.diagnostics(&uri)]
+            },
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                vec![self.diagnostics(&uri)]
+            },
+            _ => Vec::new()
+        }
+    }
+
+    /// Takes `dialect` and `locale` by name from the initialization options.
+    fn initialize(&mut self, options: &Value) -> Value {
+        if let Some(dialect) = options["dialect"].as_str().and_then(Dialect::by_name) {
+            self.state = new_state(dialect);
+        }
+        if let Some(locale) = options["locale"].as_str().and_then(Locale::by_name) {
+            self.state.locale = locale;
+        }
+        json!({
+            "capabilities": {
+                "textDocumentSync": 1,
+                "hoverProvider": true,
+                "definitionProvider": true,
+                "completionProvider": {"triggerCharacters": [":", "\""]},
+                "documentSymbolProvider": true,
+                "documentFormattingProvider": true
+            },
+            "serverInfo": {"name": env!("CARGO_PKG_NAME"), "version": env!("CARGO_PKG_VERSION")}
+        })
+    }
+
+    fn document<'a>(&'a self, params: &'a Value) -> Result<(&'a str, &'a str), (i64, String)> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        match self.documents.get(uri) {
+            Some(text) => Ok((uri, text)),
+            None => Err((INVALID_PARAMS, format!("Document {} isn't open", uri)))
+        }
+    }
+
+    /// `publishDiagnostics` with the problems `checker::check` finds in the document, none if it's closed.
+    fn diagnostics(&self, uri: &str) -> Value {
+        let diagnostics = match self.documents.get(uri) {
+            None => Vec::new(),
+            Some(text) => {
+                let (proc_source, source) = split_sources(text, &procedures(&self.state, text));
+                check(&self.state, &proc_source, &source).into_iter().map(|diagnostic| json!({
+                    "range": range_of(text, &diagnostic.span),
+                    "severity": match diagnostic.severity {
+                        Severity::Error => 1,
+                        Severity::Warning => 2
+                    },
+                    "source": "logo",
+                    "message": diagnostic.message
+                })).collect()
+            }
+        };
+        json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": {"uri": uri, "diagnostics": diagnostics}
+        })
+    }
+
+    /// Title line and doc comment of procedures of the document, help of primitives.
+    fn hover(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let (_, text) = self.document(params)?;
+        let Some((name, span)) = word_at(&self.state, text, offset_of(text, &params["position"])) else {
+            return Ok(Value::Null);
+        };
+        let defined = procedures(&self.state, text);
+        let contents = if defined.iter().any(|procedure| procedure.name == name) {
+            let (proc_source, _) = split_sources(text, &defined);
+            parse_procedures_with(&proc_source, &self.state.locale, self.state.dialect).ok()
+                .and_then(|mut parsed| parsed.remove(&name))
+                .map(|procedure| {
+                    let mut title = format!("{} {}", self.state.locale.to_keyword, name);
+                    for arg in &procedure.arg_names {
+                        title += &format!(" :{}", arg);
+                    }
+                    match procedure.doc.is_empty() {
+                        true => title,
+                        false => format!("{}\n\n{}", title, procedure.doc)
+                    }
+                })
+        }
+        else {
+            self.state.help_text(&name)
+        };
+        Ok(match contents {
+            Some(contents) => json!({
+                "contents": {"kind": "plaintext", "value": contents},
+                "range": range_of(text, &span)
+            }),
+            None => Value::Null
+        })
+    }
+
+    /// Name in the title line of the procedure called at the position.
+    fn definition(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let (uri, text) = self.document(params)?;
+        let Some((name, _)) = word_at(&self.state, text, offset_of(text, &params["position"])) else {
+            return Ok(Value::Null);
+        };
+        Ok(match procedures(&self.state, text).into_iter().find(|procedure| procedure.name == name) {
+            Some(procedure) => json!({"uri": uri, "range": range_of(text, &procedure.name_span)}),
+            None => Value::Null
+        })
+    }
+
+    fn completion(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let (_, text) = self.document(params)?;
+        let completions = self.state.complete(text, offset_of(text, &params["position"]));
+        let range = range_of(text, &completions.span);
+        let items: Vec<Value> = completions.items.iter().map(|item| json!({
+            "label": item.name,
+            "kind": match item.kind {
+                CompletionKind::Primitive | CompletionKind::Procedure => COMPLETION_FUNCTION,
+                CompletionKind::Variable => COMPLETION_VARIABLE
+            },
+            "detail": item.label(),
+            "documentation": item.description,
+            "textEdit": {"range": range, "newText": item.name}
+        })).collect();
+        Ok(json!({"isIncomplete": false, "items": items}))
+    }
+
+    fn symbols(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let (_, text) = self.document(params)?;
+        Ok(procedures(&self.state, text).iter().map(|procedure| json!({
+            "name": procedure.name,
+            "kind": SYMBOL_FUNCTION,
+            "range": range_of(text, &procedure.span),
+            "selectionRange": range_of(text, &procedure.name_span)
+        })).collect())
+    }
+
+    /// Replaces the whole document, no edits if it doesn't parse, diagnostics show why.
+    fn formatting(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let (_, text) = self.document(params)?;
+        Ok(match Formatter::new(&self.state).format_source(text) {
+            Ok(formatted) => json!([{"range": range_of(text, &(0..text.len())), "newText": formatted}]),
+            Err(_) => json!([])
+        })
+    }
+}
+
+#[test]
+fn test_server() {
+    let uri = "file:///square.logo";
+    let text = "; Draws a square\nto square :size\nrepeat 4 [fd :size rt 90]\nend\nsquare 50 fd\n";
+    let at = |line: u32, character: u32| json!({"textDocument": {"uri": uri}, "position": {"line": line, "character": character}});
+    let messages = [
+        json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {"capabilities": {}}}),
+        json!({"jsonrpc": "2.0", "method": "initialized", "params": {}}),
+        json!({"jsonrpc": "2.0", "method": "textDocument/didOpen",
+            "params": {"textDocument": {"uri": uri, "languageId": "logo", "version": 1, "text": text}}}),
+        json!({"jsonrpc": "2.0", "id": 2, "method": "textDocument/hover", "params": at(2, 11)}),
+        json!({"jsonrpc": "2.0", "id": 3, "method": "textDocument/hover", "params": at(4, 2)}),
+        json!({"jsonrpc": "2.0", "id": 4, "method": "textDocument/definition", "params": at(4, 2)}),
+        json!({"jsonrpc": "2.0", "id": 5, "method": "textDocument/completion", "params": at(4, 3)}),
+        json!({"jsonrpc": "2.0", "id": 6, "method": "textDocument/documentSymbol", "params": {"textDocument": {"uri": uri}}}),
+        json!({"jsonrpc": "2.0", "id": 7, "method": "textDocument/formatting",
+            "params": {"textDocument": {"uri": uri}, "options": {"tabSize": 4, "insertSpaces": true}}}),
+        json!({"jsonrpc": "2.0", "id": 8, "method": "workspace/symbol", "params": {"query": ""}}),
+        json!({"jsonrpc": "2.0", "id": 9, "method": "shutdown"}),
+        json!({"jsonrpc": "2.0", "method": "exit"})
+    ];
+    let mut input = Vec::new();
+    for message in &messages {
+        write_message(&mut input, message).unwrap();
+    }
+    let mut output = Vec::new();
+    assert!(Server::new().run(&mut &input[..], &mut output).unwrap());
+    let mut reader = &output[..];
+    let mut replies = Vec::new();
+    while let Some(reply) = read_message(&mut reader).unwrap() {
+        replies.push(reply);
+    }
+    assert_eq!(replies.len(), 10);
+
+    assert_eq!(replies[0]["result"]["capabilities"]["hoverProvider"], true);
+    let diagnostics = &replies[1]["params"]["diagnostics"];
+    assert_eq!(diagnostics.as_array().unwrap().len(), 1);
+    assert_eq!(diagnostics[0]["severity"], 1);
+    assert_eq!(diagnostics[0]["range"]["start"], json!({"line": 4, "character": 10}));
+    assert!(replies[2]["result"]["contents"]["value"].as_str().unwrap().starts_with("fd :distance\n"));
+    assert_eq!(replies[3]["result"]["contents"]["value"], "to square :size\n\nDraws a square");
+    assert_eq!(replies[4]["result"]["range"], json!({"start": {"line": 1, "character": 3}, "end": {"line": 1, "character": 9}}));
+    let items = replies[5]["result"]["items"].as_array().unwrap();
+    assert!(items.iter().any(|item| item["label"] == "square" && item["detail"] == "square :size"));
+    assert_eq!(items[0]["textEdit"]["range"], json!({"start": {"line": 4, "character": 0}, "end": {"line": 4, "character": 6}}));
+    assert_eq!(replies[6]["result"][0]["name"], "square");
+    assert_eq!(replies[6]["result"][0]["range"]["end"], json!({"line": 3, "character": 3}));
+    assert!(replies[7]["result"][0]["newText"].as_str().unwrap().contains("repeat 4 [fd :size rt 90]"));
+    assert_eq!(replies[8]["error"]["code"], METHOD_NOT_FOUND);
+    assert_eq!(replies[9]["result"], Value::Null);
+}
@@ -0,0 +1,32 @@
+use std::io::{BufRead, Write};
+use serde_json::Value;
+
+/// Reads a message framed with a `Content-Length` header, `None` at the end of the input.
+pub fn read_message(reader: &mut impl BufRead) -> Result<Option<Value>, String> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).map_err(|err| err.to_string())? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = Some(value.trim().parse::<usize>().map_err(|_| format!("Invalid header {}", line))?);
+            }
+        }
+    }
+    let length = length.ok_or("Missing Content-Length header")?;
+    let mut content = vec![0; length];
+    reader.read_exact(&mut content).map_err(|err| err.to_string())?;
+    serde_json::from_slice(&content).map(Some).map_err(|err| err.to_string())
+}
+
+pub fn write_message(writer: &mut impl Write, message: &Value) -> Result<(), String> {
+    let content = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{}", content.len(), content).map_err(|err| err.to_string())?;
+    writer.flush().map_err(|err| err.to_string())
+}
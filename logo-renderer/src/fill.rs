@@ -26,8 +26,7 @@ pub fn flood_fill(width: i32, height: i32, data: &mut [u8], x: i32, y: i32, colo
     let mut used = vec![false; (width * height) as usize];
     let mut queue = Vec::new();
     push_next(&mut queue, &mut used, x, y);
-    while !queue.is_empty() {
-        let cur = queue.pop().unwrap();
+    while let Some(cur) = queue.pop() {
         let cur_color = get_color(data, cur.0, cur.1);
         if cur_color != orig_color {
             continue;
@@ -8,17 +8,38 @@ use raqote::*;
 use logo_runtime::colors::LogoColor;
 use logo_runtime::common::Pos;
 use logo_runtime::drawinglib::add_drawinglib;
+use logo_runtime::logo_interp::dialect::Dialect;
 use logo_runtime::logo_interp::executor::execute_str;
 use logo_runtime::logo_interp::executor_state::EState;
+use logo_runtime::logo_interp::formatter::Formatter;
+use logo_runtime::logo_interp::completion::{Completions, SignatureHelp};
+use logo_runtime::logo_interp::lexer::Token;
+use logo_runtime::logo_interp::limits::Limits;
+use logo_runtime::logo_interp::locale::Locale;
+use logo_runtime::logo_interp::policy::Policy;
+use logo_runtime::logo_interp::workspace::Workspace;
+use logo_runtime::logo_interp::prelude::add_prelude;
+use logo_runtime::logo_interp::processes::run_ticks;
+use logo_runtime::logo_interp::events::{dispatch_event, Event};
 use logo_runtime::logo_interp::stdlib::add_stdlib;
 use logo_runtime::state::{Delegate, State};
 use crate::fill::flood_fill;
 
+#[cfg(not(feature = "sync"))]
+pub type ShowFn = Box<dyn Fn(&str)>;
+#[cfg(feature = "sync")]
+pub type ShowFn = Box<dyn Fn(&str) + Send + Sync>;
+
 pub struct DrawingDelegate {
     pub dt: DrawTarget,
-    pub show_fn: Option<Box<dyn Fn(&str)>>,
+    pub show_fn: Option<ShowFn>,
 }
 
+// The rasterizer of raqote keeps pointers to its edges, which is why `DrawTarget` isn't `Send`,
+// but they only point into memory the rasterizer owns, so the whole target can be moved to another thread.
+#[cfg(feature = "sync")]
+unsafe impl Send for DrawingDelegate {}
+
 impl DrawingDelegate {
     fn transform_coords(&self, pos: Pos) -> (f32, f32) {
         let width = self.dt.width() as f64;
@@ -80,18 +101,156 @@ pub struct Context {
 
 impl Context {
     pub fn new(width: i32, height: i32) -> Self {
+        Self::with_dialect(width, height, Dialect::Native)
+    }
+
+    /// Creates a context which follows the quoting, printing and primitives of another Logo.
+    pub fn with_dialect(width: i32, height: i32, dialect: Dialect) -> Self {
+        Self::with_options(width, height, dialect, Policy::unrestricted())
+    }
+
+    /// Creates a context where only primitives of a level preset, like `beginner` or `sandbox`, are available.
+    pub fn with_level(width: i32, height: i32, level: &str) -> Result<Self, String> {
+        match Policy::by_level(level) {
+            Some(policy) => Ok(Self::with_options(width, height, Dialect::Native, policy)),
+            None => Err(format!("Unknown level {}", level))
+        }
+    }
+
+    pub fn with_options(width: i32, height: i32, dialect: Dialect, policy: Policy) -> Self {
         let dt = DrawTarget::new(width, height);
         let dd = DrawingDelegate { dt, show_fn: None };
-        let mut state = EState::new(State::new(width, height, dd));
+        let mut state = EState::with_dialect(State::new(width, height, dd), dialect);
         state.state.delegate.clear_graphics();
         add_stdlib(&mut state);
         add_drawinglib(&mut state);
-        return Self {state}
+        add_prelude(&mut state);
+        state.policy = policy;
+        Self {state}
     }
 
     pub fn render(&mut self, proc_source: &str, cmd_source: &str) -> Result<Vec<u8>, String> {
         execute_str(&mut self.state, proc_source, cmd_source)?;
         Ok(Vec::from(self.state.state.delegate.dt.get_data_u8()))
     }
+
+    /// Runs processes started by `launch` and `forever` for a tick, front-ends call it 60 times a second
+    /// while `is_running` and show the image.
+    pub fn tick(&mut self) -> Result<Vec<u8>, String> {
+        run_ticks(&mut self.state, 1)?;
+        Ok(Vec::from(self.state.state.delegate.dt.get_data_u8()))
+    }
+
+    /// Whether there are processes, timers or conditions of `when` which need ticks.
+    pub fn is_running(&self) -> bool {
+        !self.state.scheduler.is_empty() || self.state.events.has_timers()
+    }
+
+    pub fn stop_all(&mut self) {
+        self.state.scheduler.stop_all();
+        self.state.events.clear_timers();
+    }
+
+    /// Runs the handler of a key or a click and returns the image. Front-ends send keys as the typed text,
+    /// like `a` or `A`, or as `left`, `right`, `up`, `down`, `enter`, `escape` and `backspace`.
+    pub fn dispatch_event(&mut self, event: Event) -> Result<Vec<u8>, String> {
+        dispatch_event(&mut self.state, &event)?;
+        Ok(Vec::from(self.state.state.delegate.dt.get_data_u8()))
+    }
+
+    /// Turtle coordinates of a pixel of the canvas, for click events.
+    pub fn canvas_to_turtle(&self, x: f64, y: f64) -> (f64, f64) {
+        let width = self.state.state.delegate.dt.width() as f64;
+        let height = self.state.state.delegate.dt.height() as f64;
+        (x - width / 2f64, height / 2f64 - y)
+    }
+
+    /// Caps on list length, word length and total size of variables, see `Limits::untrusted`.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.state.limits = limits;
+    }
+
+    /// Procedures, variables, property lists and the turtle state in the workspace text format.
+    pub fn save_workspace(&self) -> String {
+        self.state.save_workspace().to_text()
+    }
+
+    pub fn load_workspace(&mut self, text: &str) -> Result<(), String> {
+        self.state.load_workspace(Workspace::from_text(text)?)
+    }
+
+    /// Switches names of primitives, keywords and error messages to one of the built-in locales.
+    pub fn set_locale(&mut self, name: &str) -> Result<(), String> {
+        match Locale::by_name(name) {
+            Some(locale) => {
+                self.state.locale = locale;
+                Ok(())
+            },
+            None => Err(format!("Unknown locale {}", name))
+        }
+    }
+
+    /// Re-indents the source, keeping its meaning and comments.
+    pub fn format(&self, source: &str) -> Result<String, String> {
+        match Formatter::new(&self.state).format_source(source) {
+            Ok(formatted) => Ok(formatted),
+            Err(err) => Err(err.message)
+        }
+    }
+
+    /// Tokens of the source with their kinds for colouring it, works on code which is still being typed.
+    pub fn highlight(&self, source: &str) -> Vec<Token> {
+        self.state.highlight(source)
+    }
+
+    /// Names completing the word at the cursor, a byte offset in the source.
+    pub fn complete(&self, source: &str, cursor: usize) -> Completions {
+        self.state.complete(source, cursor)
+    }
+
+    /// Signature of the call whose inputs are typed at the cursor.
+    pub fn signature_help(&self, source: &str, cursor: usize) -> Option<SignatureHelp> {
+        self.state.signature_help(source, cursor)
+    }
+
+    /// Documentation of a primitive or a procedure, the same text `help` prints.
+    pub fn help(&self, name: &str) -> Option<String> {
+        self.state.help_text(name)
+    }
+
+    /// Documentation of all available primitives and own procedures as Markdown.
+    pub fn docs_markdown(&self) -> String {
+        self.state.docs_markdown()
+    }
+
+    pub fn set_profiling(&mut self, enabled: bool) {
+        if enabled {
+            self.state.profiler.start();
+        }
+        else {
+            self.state.profiler.stop();
+        }
+    }
+
+    /// Returns the collected profile as a table and clears it.
+    pub fn take_profile_report(&mut self) -> Option<String> {
+        let report = self.state.profiler.report();
+        self.state.profiler.clear();
+        if report.entries.is_empty() {
+            return None;
+        }
+        Some(format!("{}", report))
+    }
 }
 
+
+#[cfg(feature = "sync")]
+#[test]
+fn test_send() {
+    let mut context = Context::new(100, 100);
+    let handle = std::thread::spawn(move || {
+        context.render("", "fd 10").unwrap();
+        context
+    });
+    assert_eq!(handle.join().unwrap().state.state.data.turtle_pos.y, 10f64);
+}
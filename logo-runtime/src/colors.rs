@@ -1,14 +1,16 @@
 use wasm_bindgen::prelude::*;
+use logo_interp::dialect::Dialect;
 
 #[wasm_bindgen]
 #[derive(Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LogoColor {
     pub r: u8,
     pub g: u8,
     pub b: u8
 }
 
-static COLORS: &'static [(u8, u8, u8)] = &[
+static COLORS: &[(u8, u8, u8)] = &[
     (255, 255, 255),
     (235, 235, 235),
     (230, 230, 230),
@@ -151,6 +153,36 @@ static COLORS: &'static [(u8, u8, u8)] = &[
     (85, 12, 90),
 ];
 
+/// Standard palette of UCBLogo and FMSLogo.
+static UCB_COLORS: &[(u8, u8, u8)] = &[
+    (0, 0, 0),
+    (0, 0, 255),
+    (0, 255, 0),
+    (0, 255, 255),
+    (255, 0, 0),
+    (255, 0, 255),
+    (255, 255, 0),
+    (255, 255, 255),
+    (155, 96, 59),
+    (197, 136, 18),
+    (100, 162, 64),
+    (120, 187, 187),
+    (255, 149, 119),
+    (144, 113, 208),
+    (255, 163, 0),
+    (183, 183, 183),
+];
+
+/// Pen colors of Apple Logo.
+static APPLE_COLORS: &[(u8, u8, u8)] = &[
+    (0, 0, 0),
+    (255, 255, 255),
+    (20, 245, 60),
+    (255, 68, 253),
+    (255, 106, 60),
+    (20, 207, 253),
+];
+
 pub fn colors_count() -> i32 {
     COLORS.len() as i32
 }
@@ -159,3 +191,17 @@ pub fn get_color(idx: i32) -> LogoColor {
     let res = COLORS[idx as usize];
     LogoColor{r: res.0, g: res.1, b: res.2}
 }
+
+/// Color by its number in the palette of the dialect.
+pub fn get_dialect_color(dialect: Dialect, idx: i32) -> Option<LogoColor> {
+    let palette = match dialect {
+        Dialect::Native => COLORS,
+        Dialect::UCBLogo | Dialect::FMSLogo => UCB_COLORS,
+        Dialect::AppleLogo => APPLE_COLORS
+    };
+    if idx < 0 || idx as usize >= palette.len() {
+        return None;
+    }
+    let res = palette[idx as usize];
+    Some(LogoColor{r: res.0, g: res.1, b: res.2})
+}
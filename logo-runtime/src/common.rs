@@ -1,6 +1,7 @@
 use wasm_bindgen::prelude::*;
 
 #[derive(Copy, Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[wasm_bindgen]
 pub struct Pos {
     pub x: f64,
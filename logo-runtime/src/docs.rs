@@ -0,0 +1,99 @@
+use logo_interp::docs::Doc;
+
+/// Docs of the primitives `add_drawinglib` installs, `print` and `show` keep the ones of the stdlib.
+pub static DRAWING_DOCS: &[Doc] = &[
+    Doc {names: &["cg", "clearscreen", "cs", "draw"], description: "Clears the canvas and puts the turtles home, heading up",
+        inputs: &[], output: "", example: "cg"},
+    Doc {names: &["clean"], description: "Clears the canvas, the turtle stays where it is",
+        inputs: &[], output: "", example: "clean"},
+    Doc {names: &["fill"], description: "Fills the area around the turtle with the pen color, up to lines of other colors",
+        inputs: &[], output: "", example: "repeat 4 [fd 100 rt 90] rt 45 pu fd 10 fill"},
+
+    Doc {names: &["pu", "penup"], description: "Lifts the pen, the turtle moves without drawing",
+        inputs: &[], output: "", example: "pu fd 50 pd"},
+    Doc {names: &["pd", "pendown"], description: "Puts the pen down, the turtle draws when it moves",
+        inputs: &[], output: "", example: "pu fd 50 pd"},
+    Doc {names: &["pe", "penerase"], description: "Makes the turtle erase lines it moves over",
+        inputs: &[], output: "", example: "pe bk 50 pd"},
+    Doc {names: &["pen"], description: "Outputs the state of the pen, one of pu, pd and pe",
+        inputs: &[], output: "a word", example: "print pen"},
+    Doc {names: &["setpen"], description: "Sets the state of the pen as pen outputs it",
+        inputs: &[("state", "pu, pd or pe")], output: "", example: "make \"old pen pu fd 50 setpen :old"},
+
+    Doc {names: &["fd", "forward"], description: "Moves the turtle forward, drawing a line if the pen is down",
+        inputs: &[("distance", "number of steps")], output: "", example: "fd 100"},
+    Doc {names: &["bk", "back"], description: "Moves the turtle backward, drawing a line if the pen is down",
+        inputs: &[("distance", "number of steps")], output: "", example: "bk 100"},
+    Doc {names: &["rt", "right"], description: "Turns the turtle right",
+        inputs: &[("angle", "angle in degrees")], output: "", example: "rt 90"},
+    Doc {names: &["lt", "left"], description: "Turns the turtle left",
+        inputs: &[("angle", "angle in degrees")], output: "", example: "lt 90"},
+
+    Doc {names: &["heading"], description: "Outputs the direction of the turtle, 0 is up and 90 is right",
+        inputs: &[], output: "the angle in degrees", example: "print heading"},
+    Doc {names: &["seth", "setheading"], description: "Turns the turtle to the direction, 0 is up and 90 is right",
+        inputs: &[("angle", "angle in degrees")], output: "", example: "seth 180"},
+    Doc {names: &["pos"], description: "Outputs the position of the turtle, the center of the canvas is [0 0]",
+        inputs: &[], output: "a list [x y]", example: "print pos"},
+    Doc {names: &["setpos"], description: "Moves the turtle to the position, drawing a line if the pen is down",
+        inputs: &[("pos", "list [x y]")], output: "", example: "setpos [100 50]"},
+    Doc {names: &["setxy"], description: "Moves the turtle to the position, drawing a line if the pen is down",
+        inputs: &[("x", "horizontal coordinate"), ("y", "vertical coordinate")], output: "", example: "setxy 100 50"},
+    Doc {names: &["setx"], description: "Moves the turtle horizontally to the coordinate",
+        inputs: &[("x", "horizontal coordinate")], output: "", example: "setx 100"},
+    Doc {names: &["sety"], description: "Moves the turtle vertically to the coordinate",
+        inputs: &[("y", "vertical coordinate")], output: "", example: "sety 100"},
+    Doc {names: &["xcoor"], description: "Outputs the horizontal coordinate of the turtle",
+        inputs: &[], output: "a number", example: "print xcoor"},
+    Doc {names: &["ycoor"], description: "Outputs the vertical coordinate of the turtle",
+        inputs: &[], output: "a number", example: "print ycoor"},
+    Doc {names: &["home"], description: "Moves the turtle to the center of the canvas and turns it up",
+        inputs: &[], output: "", example: "home"},
+
+    Doc {names: &["pensize"], description: "Outputs the width of lines",
+        inputs: &[], output: "a number", example: "print pensize"},
+    Doc {names: &["setpensize"], description: "Sets the width of lines",
+        inputs: &[("size", "width in pixels")], output: "", example: "setpensize 5"},
+    Doc {names: &["ht", "hideturtle"], description: "Hides the turtle",
+        inputs: &[], output: "", example: "ht"},
+    Doc {names: &["st", "showturtle"], description: "Shows the turtle",
+        inputs: &[], output: "", example: "st"},
+    Doc {names: &["tell"], description: "Makes the turtle with the number current, turtles up to it are made at home",
+        inputs: &[("turtle", "number of the turtle")], output: "", example: "tell 1 fd 50 tell 0"},
+    Doc {names: &["who"], description: "Outputs the number of the current turtle, processes get turtles of their own",
+        inputs: &[], output: "a number", example: "print who"},
+
+    Doc {names: &["setc", "setcolor"], description: "Sets the pen color by its number. There are 140 colors in 14 groups \
+        of 10, each group goes from light to dark: 0-9 white to black, 10-19 red, 20-29 orange, 30-39 brown, \
+        40-49 yellow, 50-59 lime, 60-69 green, 70-79 sea green, 80-89 cyan, 90-99 sky blue, 100-109 blue, \
+        110-119 violet, 120-129 magenta, 130-139 pink",
+        inputs: &[("color", "number from 0 to 139")], output: "", example: "setc 14 fd 100"},
+    Doc {names: &["color"], description: "Outputs the number of the pen color, -1 if it was set by red, green and blue",
+        inputs: &[], output: "a whole number", example: "print color"},
+    Doc {names: &["setpencolor", "setpc"], description: "Sets the pen color by its number or by a list of red, green and blue. \
+        UCBLogo and FMSLogo have 16 colors: 0 black, 1 blue, 2 green, 3 cyan, 4 red, 5 magenta, 6 yellow, 7 white, \
+        8 brown, 9 tan, 10 forest, 11 aqua, 12 salmon, 13 purple, 14 orange, 15 grey, UCBLogo takes \
+        components of lists in percents. Apple Logo has 6 colors: 0 black, 1 white, 2 green, 3 violet, 4 orange, 5 blue",
+        inputs: &[("color", "number of the color or list [red green blue]")], output: "", example: "setpencolor [255 128 0]"},
+    Doc {names: &["pencolor", "pc"], description: "Outputs the pen color, a number if it was set by one or a list of red, green and blue",
+        inputs: &[], output: "a number or a list", example: "print pencolor"}
+];
+
+#[test]
+fn test_docs() {
+    use logo_interp::dialect::Dialect;
+    use logo_interp::executor_state::EState;
+    use logo_interp::stdlib::add_stdlib;
+    use crate::drawinglib::add_drawinglib;
+    use crate::state::{NoOpDelegate, State};
+
+    for dialect in [Dialect::Native, Dialect::UCBLogo, Dialect::FMSLogo, Dialect::AppleLogo] {
+        let mut state = EState::with_dialect(State::new(800, 450, NoOpDelegate{}), dialect);
+        add_stdlib(&mut state);
+        add_drawinglib(&mut state);
+        for (name, function) in &state.functions {
+            assert!(function.doc.is_some_and(|doc| doc.names.contains(&name)), "{} has no docs", name);
+        }
+        assert!(state.help_text("print").unwrap().starts_with("print :thing\n"));
+    }
+}
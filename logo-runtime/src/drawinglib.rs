@@ -1,58 +1,196 @@
-use logo_interp::core::LogoValue;
+use std::collections::HashMap;
+use logo_interp::core::{LogoConvertible, LogoValue};
+use logo_interp::dialect::Dialect;
+use logo_interp::docs::{attach_docs, STDLIB_DOCS};
 use logo_interp::executor_state::*;
-use crate::colors::{LogoColor, colors_count, get_color};
+use logo_interp::library::LogoLibrary;
+use logo_interp::policy::Category;
+use logo_interp::processes::Turtles;
+use crate::colors::{LogoColor, colors_count, get_color, get_dialect_color};
 use crate::common::Pos;
+use crate::docs::DRAWING_DOCS;
 use crate::state::{Delegate, PenState, State};
 
-pub fn add_drawinglib<D: Delegate + 'static>(es: &mut EState<State<D>>) {
-    es.functions.insert("show".to_string(), Function::from_proc1(show));
+/// Turtle graphics primitives, `add_drawinglib` installs them after the stdlib.
+pub struct DrawingLib;
+
+impl<D: Delegate + 'static> LogoLibrary<State<D>> for DrawingLib {
+    fn name(&self) -> &str {
+        "drawing"
+    }
+
+    fn version(&self) -> &str {
+        env!("CARGO_PKG_VERSION")
+    }
+
+    fn primitives(&self, dialect: Dialect) -> HashMap<String, Function<State<D>>> {
+        let mut functions = drawing_primitives(dialect);
+        for (name, function) in functions.iter_mut() {
+            function.category = match name.as_str() {
+                "show" | "print" | "pr" => Category::Text,
+                _ => Category::Turtle
+            };
+        }
+        // Overridden `print` and `show` keep the docs of the stdlib
+        attach_docs(&mut functions, STDLIB_DOCS);
+        attach_docs(&mut functions, DRAWING_DOCS);
+        functions
+    }
+
+    /// Text is shown through the delegate instead of the standard output.
+    fn overrides(&self) -> &[&str] {
+        &["show", "print", "pr"]
+    }
 
-    es.functions.insert("cg".to_string(), Function::from_proc(cg));
-    es.functions.insert("clean".to_string(), Function::from_proc(clean));
-    es.functions.insert("fill".to_string(), Function::from_proc(fill));
+    fn on_install(&self, es: &mut EState<State<D>>) {
+        es.add_state_section("turtle", save_turtle, load_turtle);
+        es.set_turtles(Turtles {hatch: hatch_turtle, select: select_turtle});
+        if es.dialect != Dialect::Native {
+            es.state.data.color_idx = 0;
+            es.state.data.pen_color = get_dialect_color(es.dialect, 0).unwrap();
+        }
+    }
+}
 
-    es.functions.insert("pu".to_string(), Function::from_proc(pu));
-    es.functions.insert("pd".to_string(), Function::from_proc(pd));
-    es.functions.insert("pe".to_string(), Function::from_proc(pe));
+/// Turtle and pen state as `[x y heading visible pen pensize color r g b]`.
+fn save_turtle<D: Delegate>(state: &State<D>) -> LogoValue {
+    let data = &state.data;
+    let pen = match data.pen_state {
+        PenState::Up => 0,
+        PenState::Down => 1,
+        PenState::Erase => 2
+    };
+    vec![
+        data.turtle_pos.x, data.turtle_pos.y, data.turtle_angle, data.turtle_visible as i32 as f64,
+        pen as f64, data.pen_size, data.color_idx as f64,
+        data.pen_color.r as f64, data.pen_color.g as f64, data.pen_color.b as f64
+    ].to_logo()
+}
 
-    es.functions.insert("rt".to_string(), Function::from_proc1(rt));
-    es.functions.insert("right".to_string(), Function::from_proc1(rt));
-    es.functions.insert("lt".to_string(), Function::from_proc1(lt));
-    es.functions.insert("left".to_string(), Function::from_proc1(lt));
-    es.functions.insert("fd".to_string(), Function::from_proc1(fd));
-    es.functions.insert("bk".to_string(), Function::from_proc1(bk));
+fn load_turtle<D: Delegate>(state: &mut State<D>, value: LogoValue) -> Result<(), String> {
+    let values = Vec::<f64>::from_logo(value)?;
+    if values.len() != 10 {
+        return Err("Invalid turtle state".to_string());
+    }
+    let data = &mut state.data;
+    data.turtle_pos = Pos{x: values[0], y: values[1]};
+    data.turtle_angle = values[2];
+    data.turtle_visible = values[3] != 0.0;
+    data.pen_state = match values[4] as i32 {
+        0 => PenState::Up,
+        2 => PenState::Erase,
+        _ => PenState::Down
+    };
+    data.pen_size = values[5];
+    data.color_idx = values[6] as i32;
+    data.pen_color = LogoColor{r: values[7] as u8, g: values[8] as u8, b: values[9] as u8};
+    Ok(())
+}
 
-    es.functions.insert("heading".to_string(), Function::from_fn(heading));
-    es.functions.insert("seth".to_string(), Function::from_proc1(seth));
-    es.functions.insert("setheading".to_string(), Function::from_proc1(seth));
-    es.functions.insert("setpos".to_string(), Function::from_proc1(setpos));
-    es.functions.insert("setx".to_string(), Function::from_proc1(setx));
-    es.functions.insert("sety".to_string(), Function::from_proc1(sety));
-    es.functions.insert("pos".to_string(), Function::from_fn(pos));
-    es.functions.insert("xcoor".to_string(), Function::from_fn(xcoor));
-    es.functions.insert("ycoor".to_string(), Function::from_fn(ycoor));
-    es.functions.insert("home".to_string(), Function::from_proc(home));
+fn hatch_turtle<D: Delegate>(state: &mut State<D>) -> u32 {
+    state.hatch_turtle() as u32
+}
 
-    es.functions.insert("pensize".to_string(), Function::from_fn(pensize));
-    es.functions.insert("setpensize".to_string(), Function::from_proc1(setpensize));
+fn select_turtle<D: Delegate>(state: &mut State<D>, turtle: u32) -> u32 {
+    state.select_turtle(turtle as usize) as u32
+}
 
-    es.functions.insert("ht".to_string(), Function::from_proc(ht));
-    es.functions.insert("st".to_string(), Function::from_proc(st));
+pub fn add_drawinglib<D: Delegate + 'static>(es: &mut EState<State<D>>) {
+    es.install(&DrawingLib).unwrap();
+}
 
-    es.functions.insert("setc".to_string(), Function::from_proc1(setc));
-    es.functions.insert("setcolor".to_string(), Function::from_proc1(setc));
-    es.functions.insert("color".to_string(), Function::from_fn(color));
+fn drawing_primitives<D: Delegate + 'static>(dialect: Dialect) -> HashMap<String, Function<State<D>>> {
+    let mut functions = HashMap::new();
+    functions.insert("show".to_string(), Function::from_proc1(show));
+    functions.insert("print".to_string(), Function::from_proc1(print));
+    functions.insert("pr".to_string(), Function::from_proc1(print));
+
+    functions.insert("cg".to_string(), Function::from_proc(cg));
+    functions.insert("clean".to_string(), Function::from_proc(clean));
+    functions.insert("fill".to_string(), Function::from_proc(fill));
+
+    functions.insert("pu".to_string(), Function::from_proc(pu));
+    functions.insert("pd".to_string(), Function::from_proc(pd));
+    functions.insert("pe".to_string(), Function::from_proc(pe));
+    functions.insert("pen".to_string(), Function::from_fn(pen));
+    functions.insert("setpen".to_string(), Function::from_proc1(setpen));
+
+    functions.insert("rt".to_string(), Function::from_proc1(rt));
+    functions.insert("right".to_string(), Function::from_proc1(rt));
+    functions.insert("lt".to_string(), Function::from_proc1(lt));
+    functions.insert("left".to_string(), Function::from_proc1(lt));
+    functions.insert("fd".to_string(), Function::from_proc1(fd));
+    functions.insert("bk".to_string(), Function::from_proc1(bk));
+
+    functions.insert("heading".to_string(), Function::from_fn(heading));
+    functions.insert("seth".to_string(), Function::from_proc1(seth));
+    functions.insert("setheading".to_string(), Function::from_proc1(seth));
+    functions.insert("setpos".to_string(), Function::from_proc1(setpos));
+    functions.insert("setx".to_string(), Function::from_proc1(setx));
+    functions.insert("sety".to_string(), Function::from_proc1(sety));
+    functions.insert("pos".to_string(), Function::from_fn(pos));
+    functions.insert("xcoor".to_string(), Function::from_fn(xcoor));
+    functions.insert("ycoor".to_string(), Function::from_fn(ycoor));
+    functions.insert("home".to_string(), Function::from_proc(home));
+
+    functions.insert("pensize".to_string(), Function::from_fn(pensize));
+    functions.insert("setpensize".to_string(), Function::from_proc1(setpensize));
+
+    functions.insert("ht".to_string(), Function::from_proc(ht));
+    functions.insert("st".to_string(), Function::from_proc(st));
+
+    functions.insert("tell".to_string(), Function::from_proc1(tell));
+    functions.insert("who".to_string(), Function::from_fn(who));
+
+    if dialect == Dialect::Native {
+        functions.insert("setc".to_string(), Function::from_proc1(setc));
+        functions.insert("setcolor".to_string(), Function::from_proc1(setc));
+        functions.insert("color".to_string(), Function::from_fn(color));
+        return functions;
+    }
+    // Numbers of the native palette don't match the colors of the dialect, setpc is used instead
+    for name in ["setc", "setcolor"] {
+        functions.insert(name.to_string(), Function::unavailable(name, &["color"], dialect));
+    }
+    functions.insert("color".to_string(), Function::unavailable("color", &[], dialect));
+
+    functions.insert("forward".to_string(), Function::from_proc1(fd));
+    functions.insert("back".to_string(), Function::from_proc1(bk));
+    functions.insert("penup".to_string(), Function::from_proc(pu));
+    functions.insert("pendown".to_string(), Function::from_proc(pd));
+    functions.insert("penerase".to_string(), Function::from_proc(pe));
+    functions.insert("clearscreen".to_string(), Function::from_proc(cg));
+    functions.insert("cs".to_string(), Function::from_proc(cg));
+    functions.insert("hideturtle".to_string(), Function::from_proc(ht));
+    functions.insert("showturtle".to_string(), Function::from_proc(st));
+    functions.insert("setxy".to_string(), Function::from_proc2(setxy));
+    functions.insert("setpc".to_string(), Function::from_proc1(setpencolor));
+    functions.insert("pc".to_string(), Function::from_fn(pencolor));
+    if dialect == Dialect::AppleLogo {
+        functions.insert("draw".to_string(), Function::from_proc(cg));
+    }
+    else {
+        functions.insert("setpencolor".to_string(), Function::from_proc1(setpencolor));
+        functions.insert("pencolor".to_string(), Function::from_fn(pencolor));
+    }
+    functions
 }
 
 fn show<D: Delegate>(state: &mut EState<State<D>>, val: LogoValue) -> Result<(), String> {
-    state.state.delegate.show(format!("{}", val).as_str());
+    let text = state.dialect.format_show(&val);
+    state.state.delegate.show(text.as_str());
+    Ok(())
+}
+
+fn print<D: Delegate>(state: &mut EState<State<D>>, val: LogoValue) -> Result<(), String> {
+    let text = state.dialect.format_print(&val);
+    state.state.delegate.show(text.as_str());
     Ok(())
 }
 
 fn cg<D: Delegate>(state: &mut EState<State<D>>) -> Result<(), String> {
     let state = &mut state.state;
-    state.data.turtle_pos = Pos{x: 0f64, y: 0f64};
-    state.data.turtle_angle = 0f64;
+    state.home_turtles();
     state.delegate.clear_graphics();
     Ok(())
 }
@@ -64,7 +202,7 @@ fn clean<D: Delegate>(state: &mut EState<State<D>>) -> Result<(), String> {
 
 fn fill<D: Delegate>(state: &mut EState<State<D>>) -> Result<(), String> {
     let state = &mut state.state;
-    state.delegate.fill(state.data.turtle_pos, get_color(state.data.color_idx));
+    state.delegate.fill(state.data.turtle_pos, state.data.pen_color);
     Ok(())
 }
 
@@ -83,6 +221,26 @@ fn pe<D: Delegate>(state: &mut EState<State<D>>) -> Result<(), String> {
     Ok(())
 }
 
+fn pen<D: Delegate>(state: &mut EState<State<D>>) -> Result<String, String> {
+    let name = match state.state.data.pen_state {
+        PenState::Up => "pu",
+        PenState::Down => "pd",
+        PenState::Erase => "pe"
+    };
+    Ok(name.to_string())
+}
+
+/// Takes the pen state as `pen` outputs it, so procedures can put it back.
+fn setpen<D: Delegate>(state: &mut EState<State<D>>, name: String) -> Result<(), String> {
+    state.state.data.pen_state = match name.as_str() {
+        "pu" | "penup" => PenState::Up,
+        "pd" | "pendown" => PenState::Down,
+        "pe" | "penerase" => PenState::Erase,
+        _ => return Err(format!("setpen doesn't like {} as input", name))
+    };
+    Ok(())
+}
+
 fn rt<D: Delegate>(state: &mut EState<State<D>>, val: f64) -> Result<(), String> {
     state.state.data.turtle_angle += val;
     Ok(())
@@ -144,6 +302,11 @@ fn setx<D: Delegate>(state: &mut EState<State<D>>, x: f64) -> Result<(), String>
     Ok(())
 }
 
+fn setxy<D: Delegate>(state: &mut EState<State<D>>, x: f64, y: f64) -> Result<(), String> {
+    move_turtle(&mut state.state, Pos{x, y});
+    Ok(())
+}
+
 fn sety<D: Delegate>(state: &mut EState<State<D>>, y: f64) -> Result<(), String> {
     let x = state.state.data.turtle_pos.x;
     move_turtle(&mut state.state, Pos{x, y});
@@ -175,14 +338,65 @@ fn st<D: Delegate>(state: &mut EState<State<D>>) -> Result<(), String> {
     Ok(())
 }
 
+fn tell<D: Delegate>(state: &mut EState<State<D>>, turtle: i32) -> Result<(), String> {
+    if turtle < 0 {
+        return Err(format!("tell doesn't like {} as input", turtle));
+    }
+    state.state.select_turtle(turtle as usize);
+    Ok(())
+}
+
+fn who<D: Delegate>(state: &mut EState<State<D>>) -> Result<i32, String> {
+    Ok(state.state.turtle() as i32)
+}
+
 fn setc<D: Delegate>(state: &mut EState<State<D>>, color: i32) -> Result<(), String> {
     if color < 0 || color >= colors_count() {
         return Err(format!("Invalid color number {}", color));
     }
     state.state.data.color_idx = color;
+    state.state.data.pen_color = get_color(color);
     Ok(())
 }
 
+/// Maximum of RGB components in the dialect, UCBLogo uses percents.
+fn rgb_scale(dialect: Dialect) -> f64 {
+    if dialect == Dialect::UCBLogo { 100.0 } else { 255.0 }
+}
+
+fn setpencolor<D: Delegate>(state: &mut EState<State<D>>, color: LogoValue) -> Result<(), String> {
+    if let LogoValue::List(_) = color {
+        let rgb = Vec::<f64>::from_logo(color)?;
+        if rgb.len() != 3 || state.dialect == Dialect::AppleLogo {
+            return Err("Invalid color".to_string());
+        }
+        let scale = rgb_scale(state.dialect);
+        let component = |x: f64| (x / scale * 255.0).round().clamp(0.0, 255.0) as u8;
+        state.state.data.color_idx = -1;
+        state.state.data.pen_color = LogoColor{r: component(rgb[0]), g: component(rgb[1]), b: component(rgb[2])};
+        return Ok(());
+    }
+    let idx = i32::from_logo(color)?;
+    match get_dialect_color(state.dialect, idx) {
+        Some(color) => {
+            state.state.data.color_idx = idx;
+            state.state.data.pen_color = color;
+            Ok(())
+        },
+        None => Err(format!("Invalid color number {}", idx))
+    }
+}
+
+fn pencolor<D: Delegate>(state: &mut EState<State<D>>) -> Result<LogoValue, String> {
+    let data = &state.state.data;
+    if data.color_idx >= 0 {
+        return Ok(data.color_idx.to_logo());
+    }
+    let scale = rgb_scale(state.dialect);
+    let component = |x: u8| (x as f64 / 255.0 * scale).round();
+    Ok(vec![component(data.pen_color.r), component(data.pen_color.g), component(data.pen_color.b)].to_logo())
+}
+
 fn color<D: Delegate>(state: &mut EState<State<D>>) -> Result<i32, String> {
     Ok(state.state.data.color_idx)
 }
@@ -193,36 +407,36 @@ fn move_turtle<D: Delegate>(state: &mut State<D>, pos: Pos) {
     let h2 = state.data.canvas_height as f64 / 2f64;
     if pos.y > old_pos.y + f64::EPSILON {
         let xp = intersect_horizontal(old_pos, pos, h2, -w2, w2);
-        if xp.is_some() {
-            draw_line(state, old_pos, Pos{x: xp.unwrap(), y: h2});
-            state.data.turtle_pos = Pos{x: xp.unwrap(), y: -h2};
+        if let Some(xp) = xp {
+            draw_line(state, old_pos, Pos{x: xp, y: h2});
+            state.data.turtle_pos = Pos{x: xp, y: -h2};
             move_turtle(state, Pos{x: pos.x, y: pos.y - state.data.canvas_height as f64});
             return;
         }
     }
     if pos.y + f64::EPSILON < old_pos.y {
         let xp = intersect_horizontal(old_pos, pos, -h2, -w2, w2);
-        if xp.is_some() {
-            draw_line(state, old_pos, Pos{x: xp.unwrap(), y: -h2});
-            state.data.turtle_pos = Pos{x: xp.unwrap(), y: h2};
+        if let Some(xp) = xp {
+            draw_line(state, old_pos, Pos{x: xp, y: -h2});
+            state.data.turtle_pos = Pos{x: xp, y: h2};
             move_turtle(state, Pos{x: pos.x, y: pos.y + state.data.canvas_height as f64});
             return;
         }
     }
     if pos.x > old_pos.x + f64::EPSILON {
         let yp = intersect_vertical(old_pos, pos, w2, -h2, h2);
-        if yp.is_some() {
-            draw_line(state, old_pos, Pos{x: w2, y: yp.unwrap()});
-            state.data.turtle_pos = Pos{x: -w2, y: yp.unwrap()};
+        if let Some(yp) = yp {
+            draw_line(state, old_pos, Pos{x: w2, y: yp});
+            state.data.turtle_pos = Pos{x: -w2, y: yp};
             move_turtle(state, Pos{x: pos.x - state.data.canvas_width as f64, y: pos.y});
             return;
         }
     }
     if pos.x + f64::EPSILON < old_pos.x {
         let yp = intersect_vertical(old_pos, pos, -w2, -h2, h2);
-        if yp.is_some() {
-            draw_line(state, old_pos, Pos{x: -w2, y: yp.unwrap()});
-            state.data.turtle_pos = Pos{x: w2, y: yp.unwrap()};
+        if let Some(yp) = yp {
+            draw_line(state, old_pos, Pos{x: -w2, y: yp});
+            state.data.turtle_pos = Pos{x: w2, y: yp};
             move_turtle(state, Pos{x: pos.x + state.data.canvas_width as f64, y: pos.y});
             return;
         }
@@ -232,7 +446,7 @@ fn move_turtle<D: Delegate>(state: &mut State<D>, pos: Pos) {
 }
 
 fn draw_line<D: Delegate>(state: &mut State<D>, p1: Pos, p2: Pos) {
-    let mut color = get_color(state.data.color_idx);
+    let mut color = state.data.pen_color;
     if state.data.pen_state == PenState::Erase {
         color = LogoColor{r: 255, g: 255, b: 255};
     }
@@ -280,4 +494,113 @@ fn test_move_turtle() {
     rt(&mut state, 90.0).unwrap();
     fd(&mut state, 500.0).unwrap();
     assert_relative_eq!(state.state.data.turtle_pos.x, -300.0, epsilon = 0.00001);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_dialects() {
+    use logo_interp::executor::execute_str;
+    use logo_interp::prelude::add_prelude;
+    use logo_interp::stdlib::add_stdlib;
+    use crate::state::NoOpDelegate;
+    use approx::assert_relative_eq;
+
+    let program = "to square :size\nrepeat 4 [forward :size right 90]\nend\n\
+                   to move :x :y\npenup setxy :x :y pendown\nend";
+
+    let mut state = EState::with_dialect(State::new(800, 450, NoOpDelegate{}), Dialect::UCBLogo);
+    add_stdlib(&mut state);
+    add_drawinglib(&mut state);
+    execute_str(&mut state, program, "cs setpencolor [100 0 0] move 10 20 square 50 print \"|all done|").unwrap();
+    assert_relative_eq!(state.state.data.turtle_pos.x, 10.0, epsilon = 0.00001);
+    assert_relative_eq!(state.state.data.turtle_pos.y, 20.0, epsilon = 0.00001);
+    assert!(state.state.data.pen_color == LogoColor{r: 255, g: 0, b: 0});
+    execute_str(&mut state, "", "setpc 4 setpencolor pc").unwrap();
+    assert!(state.state.data.pen_color == LogoColor{r: 255, g: 0, b: 0});
+    assert_eq!(execute_str(&mut state, "", "setc 4"), Err("setc is not available in UCBLogo".to_string()));
+    assert!(execute_str(&mut state, "", "print identical? 1 1").unwrap_err().contains("not available in UCBLogo"));
+    add_prelude(&mut state);
+    execute_str(&mut state, program, "move 10 20 circle 50 polygon 6 10").unwrap();
+    assert_relative_eq!(state.state.data.turtle_pos.x, 10.0, epsilon = 0.00001);
+    assert_relative_eq!(state.state.data.turtle_pos.y, 20.0, epsilon = 0.00001);
+
+    let mut state = EState::with_dialect(State::new(800, 450, NoOpDelegate{}), Dialect::AppleLogo);
+    add_stdlib(&mut state);
+    add_drawinglib(&mut state);
+    execute_str(&mut state, program, "draw setpc 5 move 0 0 square 30").unwrap();
+    assert!(state.state.data.pen_color == LogoColor{r: 20, g: 207, b: 253});
+    assert!(execute_str(&mut state, "", "setpc [255 0 0]").is_err());
+}
+
+#[test]
+fn test_circle() {
+    use logo_interp::executor::execute_str;
+    use logo_interp::prelude::add_prelude;
+    use logo_interp::stdlib::add_stdlib;
+    use approx::assert_relative_eq;
+
+    struct Lines(Vec<(Pos, Pos)>);
+    impl Delegate for Lines {
+        fn clear_graphics(&mut self) {}
+        fn draw_line(&mut self, from: Pos, to: Pos, _pen_size: f64, _color: LogoColor) {
+            self.0.push((from, to));
+        }
+        fn fill(&mut self, _pos: Pos, _color: LogoColor) {}
+        fn show(&mut self, _message: &str) {}
+    }
+
+    let mut state = EState::new(State::new(800, 450, Lines(Vec::new())));
+    add_stdlib(&mut state);
+    add_drawinglib(&mut state);
+    add_prelude(&mut state);
+    execute_str(&mut state, "", "pu setpos [10 20] pd rt 30 circle 50 arc 92 50").unwrap();
+    let data = &state.state.data;
+    assert_relative_eq!(data.turtle_pos.x, 10.0, epsilon = 0.00001);
+    assert_relative_eq!(data.turtle_pos.y, 20.0, epsilon = 0.00001);
+    assert_relative_eq!(data.turtle_angle, 30.0, epsilon = 0.00001);
+    assert!(data.pen_state == PenState::Down);
+    let lines = &state.state.delegate.0;
+    assert_eq!(lines.len(), 72 + 19);
+    for (from, to) in lines {
+        for pos in [from, to] {
+            assert_relative_eq!(((pos.x - 10.0).powi(2) + (pos.y - 20.0).powi(2)).sqrt(), 50.0, epsilon = 0.00001);
+        }
+    }
+
+    execute_str(&mut state, "", "pu circle 20").unwrap();
+    assert!(state.state.data.pen_state == PenState::Up);
+    assert_eq!(state.state.delegate.0.len(), 72 + 19);
+}
+
+#[test]
+fn test_turtles() {
+    use logo_interp::executor::execute_str;
+    use logo_interp::processes::run_ticks;
+    use logo_interp::stdlib::add_stdlib;
+    use crate::state::NoOpDelegate;
+    use approx::assert_relative_eq;
+
+    let mut state = EState::new(State::new(800, 450, NoOpDelegate{}));
+    add_stdlib(&mut state);
+    add_drawinglib(&mut state);
+    // Each process drives a turtle of its own
+    execute_str(&mut state, "", "launch [repeat 100 [fd 1]] launch [repeat 100 [bk 1]]").unwrap();
+    run_ticks(&mut state, 300).unwrap();
+    let ys: Vec<f64> = state.state.turtles().map(|data| data.turtle_pos.y).collect();
+    assert_eq!(ys.len(), 3);
+    assert_relative_eq!(ys[0], 0.0, epsilon = 0.00001);
+    assert_relative_eq!(ys[1], 100.0, epsilon = 0.00001);
+    assert_relative_eq!(ys[2], -100.0, epsilon = 0.00001);
+
+    // Processes given the same turtle share it, `tell` in a process changes the turtle it drives
+    execute_str(&mut state, "", "(launch [rt 90 fd 10] 5) (launch [fd 10 tell 6 fd 20] 5)").unwrap();
+    run_ticks(&mut state, 10).unwrap();
+    assert_eq!(state.state.turtle(), 0);
+    execute_str(&mut state, "", "tell 5 make 'a' xcoor tell 6 make 'b' ycoor make 'c' who tell 0").unwrap();
+    assert_eq!(state.vars["a"], 20.to_logo());
+    assert_eq!(state.vars["b"], 20.to_logo());
+    assert_eq!(state.vars["c"], 6.to_logo());
+    assert!(execute_str(&mut state, "", "tell -1").is_err());
+
+    execute_str(&mut state, "", "cg").unwrap();
+    assert!(state.state.turtles().all(|data| data.turtle_pos.x == 0.0 && data.turtle_pos.y == 0.0));
+}
@@ -4,3 +4,4 @@ pub mod common;
 pub mod state;
 pub mod colors;
 pub mod drawinglib;
+pub mod docs;
@@ -20,6 +20,7 @@ impl Delegate for NoOpDelegate {
 
 #[wasm_bindgen]
 #[derive(Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "lowercase"))]
 pub enum PenState {
     Up,
     Down,
@@ -28,6 +29,7 @@ pub enum PenState {
 
 #[wasm_bindgen]
 #[derive(Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct StateData {
     pub canvas_width: i32,
     pub canvas_height: i32,
@@ -36,28 +38,78 @@ pub struct StateData {
     pub turtle_visible: bool,
     pub pen_state: PenState,
     pub pen_size: f64,
+    /// Number of the pen color in the palette of the dialect, -1 if it was set by RGB components.
     pub color_idx: i32,
+    pub pen_color: LogoColor,
 }
 
 pub struct State<D: Delegate> {
+    /// The current turtle, drawing primitives move it.
     pub data: StateData,
+    /// Turtles by number, the place of the current one is only updated when another one becomes current.
+    turtles: Vec<StateData>,
+    turtle: usize,
     pub delegate: D,
 }
 
 impl<D: Delegate> State<D> {
     pub fn new(canvas_width: i32, canvas_height: i32, delegate: D) -> Self {
+        let data = StateData {
+            canvas_width,
+            canvas_height,
+            turtle_pos: Pos { x: 0f64, y: 0f64 },
+            turtle_angle: 0f64,
+            turtle_visible: true,
+            pen_state: PenState::Down,
+            pen_size: 1f64,
+            color_idx: 9,
+            pen_color: LogoColor{r: 0, g: 0, b: 0},
+        };
         State {
-            data: StateData {
-                canvas_width,
-                canvas_height,
-                turtle_pos: Pos { x: 0f64, y: 0f64 },
-                turtle_angle: 0f64,
-                turtle_visible: true,
-                pen_state: PenState::Down,
-                pen_size: 1f64,
-                color_idx: 9,
-            },
+            data,
+            turtles: vec![data],
+            turtle: 0,
             delegate
         }
     }
+
+    /// Number of the current turtle.
+    pub fn turtle(&self) -> usize {
+        self.turtle
+    }
+
+    /// All turtles by number, the current one included.
+    pub fn turtles(&self) -> impl Iterator<Item = &StateData> {
+        self.turtles.iter().enumerate().map(|(idx, data)| if idx == self.turtle { &self.data } else { data })
+    }
+
+    /// Makes the turtle current, turtles up to its number are made at home with the pen of the current one.
+    /// Outputs the number of the turtle that was current.
+    pub fn select_turtle(&mut self, turtle: usize) -> usize {
+        let previous = self.turtle;
+        if turtle == previous {
+            return previous;
+        }
+        self.turtles[previous] = self.data;
+        while self.turtles.len() <= turtle {
+            self.turtles.push(StateData {turtle_pos: Pos { x: 0f64, y: 0f64 }, turtle_angle: 0f64, ..self.data});
+        }
+        self.data = self.turtles[turtle];
+        self.turtle = turtle;
+        previous
+    }
+
+    /// Adds a turtle like the current one, outputs its number.
+    pub fn hatch_turtle(&mut self) -> usize {
+        self.turtles.push(self.data);
+        self.turtles.len() - 1
+    }
+
+    /// Puts every turtle home, heading up.
+    pub fn home_turtles(&mut self) {
+        for data in self.turtles.iter_mut().chain(std::iter::once(&mut self.data)) {
+            data.turtle_pos = Pos { x: 0f64, y: 0f64 };
+            data.turtle_angle = 0f64;
+        }
+    }
 }
@@ -1,11 +1,56 @@
 use wasm_bindgen::prelude::*;
 
 use logo_renderer::Context;
+use logo_renderer::logo_runtime::logo_interp::events::Event;
+use logo_renderer::logo_runtime::logo_interp::lexer::TokenKind;
+use logo_renderer::logo_runtime::logo_interp::dialect::Dialect;
+use logo_renderer::logo_runtime::logo_interp::limits::Limits;
 use logo_renderer::logo_runtime::state::StateData;
 
+/// Kinds in the order of their numbers in `context_highlight`.
+const TOKEN_KINDS: [TokenKind; 11] = [
+    TokenKind::Primitive, TokenKind::Procedure, TokenKind::Keyword, TokenKind::Variable, TokenKind::Number,
+    TokenKind::QuotedWord, TokenKind::String, TokenKind::Bracket, TokenKind::Operator, TokenKind::Comment,
+    TokenKind::Word
+];
+
+// Programs in the browser may come from untrusted links, so contexts are created with limits
+
 #[wasm_bindgen]
 pub fn context_create(width: i32, height: i32) -> Context {
-   Context::new(width, height)
+    let mut context = Context::new(width, height);
+    context.set_limits(Limits::untrusted());
+    context
+}
+
+#[wasm_bindgen]
+pub fn context_create_with_dialect(width: i32, height: i32, dialect: &str) -> Result<Context, String> {
+    match Dialect::by_name(dialect) {
+        Some(dialect) => {
+            let mut context = Context::with_dialect(width, height, dialect);
+            context.set_limits(Limits::untrusted());
+            Ok(context)
+        },
+        None => Err(format!("Unknown dialect {}", dialect))
+    }
+}
+
+#[wasm_bindgen]
+pub fn context_create_with_level(width: i32, height: i32, level: &str) -> Result<Context, String> {
+    let mut context = Context::with_level(width, height, level)?;
+    context.set_limits(Limits::untrusted());
+    Ok(context)
+}
+
+/// Replaces the default limits, zero means unlimited.
+#[wasm_bindgen]
+pub fn context_set_limits(context: &mut Context, max_list_length: usize, max_string_length: usize, max_total_size: usize) {
+    let limit = |x: usize| if x == 0 { None } else { Some(x) };
+    context.set_limits(Limits {
+        max_list_length: limit(max_list_length),
+        max_string_length: limit(max_string_length),
+        max_total_size: limit(max_total_size)
+    });
 }
 
 #[wasm_bindgen]
@@ -13,6 +58,74 @@ pub fn context_render(context: &mut Context, proc_source: &str, cmd_source: &str
     context.render(proc_source, cmd_source)
 }
 
+/// Runs processes for a tick, call it 60 times a second while `context_is_running` is true.
+#[wasm_bindgen]
+pub fn context_tick(context: &mut Context) -> Result<Vec<u8>, String> {
+    context.tick()
+}
+
+#[wasm_bindgen]
+pub fn context_is_running(context: &Context) -> bool {
+    context.is_running()
+}
+
+#[wasm_bindgen]
+pub fn context_stop_all(context: &mut Context) {
+    context.stop_all()
+}
+
+/// Runs the key handler, see `Context::dispatch_event` for names of the keys.
+#[wasm_bindgen]
+pub fn context_dispatch_key(context: &mut Context, key: &str) -> Result<Vec<u8>, String> {
+    context.dispatch_event(Event::Key(key.to_string()))
+}
+
+/// Runs the click handler, `x` and `y` are pixels of the canvas.
+#[wasm_bindgen]
+pub fn context_dispatch_click(context: &mut Context, x: f64, y: f64) -> Result<Vec<u8>, String> {
+    let (x, y) = context.canvas_to_turtle(x, y);
+    context.dispatch_event(Event::Click {x, y})
+}
+
+/// Tokens of the source as triples of byte offsets of the start and the end, and the kind,
+/// `token_kind_name` gives names of the kinds.
+#[wasm_bindgen]
+pub fn context_highlight(context: &Context, source: &str) -> Vec<u32> {
+    context.highlight(source).into_iter()
+        .flat_map(|token| [token.span.start as u32, token.span.end as u32, token.kind as u32])
+        .collect()
+}
+
+#[wasm_bindgen]
+pub fn context_help(context: &Context, name: &str) -> Option<String> {
+    context.help(name)
+}
+
+#[wasm_bindgen]
+pub fn context_docs_markdown(context: &Context) -> String {
+    context.docs_markdown()
+}
+
+#[wasm_bindgen]
+pub fn token_kind_name(kind: u32) -> Option<String> {
+    TOKEN_KINDS.get(kind as usize).map(|kind| kind.name().to_string())
+}
+
+#[wasm_bindgen]
+pub fn context_set_locale(context: &mut Context, name: &str) -> Result<(), String> {
+    context.set_locale(name)
+}
+
+#[wasm_bindgen]
+pub fn context_save_workspace(context: &Context) -> String {
+    context.save_workspace()
+}
+
+#[wasm_bindgen]
+pub fn context_load_workspace(context: &mut Context, text: &str) -> Result<(), String> {
+    context.load_workspace(text)
+}
+
 #[wasm_bindgen]
 pub fn context_get_state(context: &mut Context) -> StateData {
     context.state.state.data